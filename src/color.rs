@@ -1,5 +1,7 @@
 use imgui::ImColor32;
 use serde::{Serialize, Deserialize};
+use anyhow::{anyhow, bail, Result};
+use std::str::FromStr;
 
 /// Wraps u32 that represents a packed RGBA color. Mostly used by types in the
 /// low level custom drawing API, such as [`DrawListMut`](crate::DrawListMut).
@@ -109,7 +111,107 @@ impl Color {
             _ => {}
         }
 
-        Self::from_rgb((r * 255.0) as _, (g * 255.0) as _, (b * 255.0) as _)
+        Self::from_rgb_f32s(r, g, b)
+    }
+
+    /// Opposite of [`Self::from_hsv`]. Returns hue in degrees (`0.0..360.0`), saturation and value
+    /// in `0.0..=100.0`. Alpha is ignored
+    pub fn to_hsv(self) -> (f32, f32, f32) {
+        let [r, g, b, _] = self.to_rgba_f32s();
+        let max = r.max(g).max(b);
+        let min = r.min(g).min(b);
+        let delta = max - min;
+
+        let s = if max > 0.0 { delta / max } else { 0.0 };
+        (hue(r, g, b, max, delta), s * 100.0, max * 100.0)
+    }
+
+    /// Construct a fully opaque color from hue in degrees, saturation and lightness in `0.0..=100.0`
+    pub fn from_hsl(h: f32, s: f32, l: f32) -> Self {
+        let s = saturate(s / 100.0);
+        let l = saturate(l / 100.0);
+
+        // HSL is HSV with the value and saturation stretched so 100% lightness is white
+        let v = l + s * l.min(1.0 - l);
+        let s_v = if v > 0.0 { 2.0 * (1.0 - l / v) } else { 0.0 };
+        Self::from_hsv(h.rem_euclid(360.0), s_v * 100.0, v * 100.0)
+    }
+
+    /// Opposite of [`Self::from_hsl`]. Returns hue in degrees (`0.0..360.0`), saturation and lightness
+    /// in `0.0..=100.0`. Alpha is ignored
+    pub fn to_hsl(self) -> (f32, f32, f32) {
+        let [r, g, b, _] = self.to_rgba_f32s();
+        let max = r.max(g).max(b);
+        let min = r.min(g).min(b);
+        let delta = max - min;
+
+        let l = (max + min) / 2.0;
+        let s = if l > 0.0 && l < 1.0 { delta / (1.0 - (2.0 * l - 1.0).abs()) } else { 0.0 };
+        (hue(r, g, b, max, delta), s * 100.0, l * 100.0)
+    }
+
+    /// Interpolates every channel (including alpha) between `self` at `t = 0.0` and `other` at `t = 1.0`
+    pub fn lerp(self, other: Color, t: f32) -> Self {
+        let a = self.to_rgba_f32s();
+        let b = other.to_rgba_f32s();
+        let channel = |i: usize| a[i] + (b[i] - a[i]) * t;
+        Self::from_rgba_f32s(channel(0), channel(1), channel(2), channel(3))
+    }
+
+    /// Like [`Self::lerp`], but interpolates in the OKLab color space so the midpoint of two
+    /// colors looks halfway between them instead of muddy or too dark
+    pub fn lerp_oklab(self, other: Color, t: f32) -> Self {
+        let a = linear_to_oklab(self.to_linear());
+        let b = linear_to_oklab(other.to_linear());
+        let channel = |i: usize| a[i] + (b[i] - a[i]) * t;
+        let [red, green, blue] = oklab_to_linear([channel(0), channel(1), channel(2)]);
+
+        let alpha = u8_to_f32_sat(self.a) + (u8_to_f32_sat(other.a) - u8_to_f32_sat(self.a)) * t;
+        Self::from_linear([red, green, blue, alpha])
+    }
+
+    /// Composites `self` on top of `background` with the "over" operator, like drawing
+    /// `self` over a surface of `background`
+    pub fn blend_over(self, background: Color) -> Self {
+        let [sr, sg, sb, sa] = self.to_rgba_f32s();
+        let [br, bg, bb, ba] = background.to_rgba_f32s();
+
+        let a = sa + ba * (1.0 - sa);
+        if a <= 0.0 {
+            return Self::TRANSPARENT;
+        }
+        // Work in premultiplied alpha, then divide it back out
+        let channel = |s: f32, b: f32| (s * sa + b * ba * (1.0 - sa)) / a;
+        Self::from_rgba_f32s(channel(sr, br), channel(sg, bg), channel(sb, bb), a)
+    }
+
+    /// Raises the HSL lightness by `amount` percentage points, keeping alpha
+    pub fn lighten(self, amount: f32) -> Self {
+        let (h, s, l) = self.to_hsl();
+        Self::from_hsl(h, s, l + amount).opacity(self.a)
+    }
+
+    /// Lowers the HSL lightness by `amount` percentage points, keeping alpha
+    pub fn darken(self, amount: f32) -> Self {
+        self.lighten(-amount)
+    }
+
+    /// Raises the HSL saturation by `amount` percentage points, keeping alpha. Negative amounts desaturate
+    pub fn saturate(self, amount: f32) -> Self {
+        let (h, s, l) = self.to_hsl();
+        Self::from_hsl(h, s + amount, l).opacity(self.a)
+    }
+
+    /// The channels converted from sRGB to linear light, in `[r, g, b, a]` order. Alpha is already linear
+    pub fn to_linear(self) -> [f32; 4] {
+        let [r, g, b, a] = self.to_rgba_f32s();
+        [srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b), a]
+    }
+
+    /// Opposite of [`Self::to_linear`]
+    pub fn from_linear(rgba: [f32; 4]) -> Self {
+        let [r, g, b, a] = rgba;
+        Self::from_rgba_f32s(linear_to_srgb(r), linear_to_srgb(g), linear_to_srgb(b), a)
     }
 
     /// Creates a new color from a hex value formatted like 0xRED_GREEN_BLUE_ALPHA
@@ -340,6 +442,154 @@ impl From<Color> for (f32, f32, f32, f32) {
     }
 }
 
+impl Color {
+    /// Looks up a palette color by name, ignoring case and treating `-` like `_`. For example `"blue3"` or `"dark-gray1"`
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::NAMED.iter()
+            .find(|(named, _)| names_match(named, name))
+            .map(|&(_, color)| color)
+    }
+}
+
+/// Parses `#RGB`, `#RGBA`, `#RRGGBB`, `#RRGGBBAA`, `rgb(r, g, b)`, `rgba(r, g, b, a)`,
+/// `hsl(h, s%, l%)`, `hsla(h, s%, l%, a)` and palette names like `blue3`
+impl FromStr for Color {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        if let Some(hex) = s.strip_prefix('#') {
+            return parse_hex(hex).ok_or_else(|| anyhow!("Invalid hex color {:?}", s));
+        }
+
+        if let Some(open) = s.find('(') {
+            let function = s[..open].trim().to_ascii_lowercase();
+            let arguments = s[open + 1..].strip_suffix(')').ok_or_else(|| anyhow!("Missing ')' in {:?}", s))?;
+            let arguments: Vec<&str> = arguments.split(',').map(str::trim).collect();
+
+            return match (function.as_str(), arguments.as_slice()) {
+                ("rgb", &[r, g, b]) => Ok(Self::from_rgb_f32s(parse_channel(r)?, parse_channel(g)?, parse_channel(b)?)),
+                ("rgba", &[r, g, b, a]) => Ok(Self::from_rgba_f32s(parse_channel(r)?, parse_channel(g)?, parse_channel(b)?, parse_alpha(a)?)),
+                ("hsl", &[h, sat, l]) => Ok(Self::from_hsl(parse_number(h)?, parse_percentage(sat)?, parse_percentage(l)?)),
+                ("hsla", &[h, sat, l, a]) => {
+                    let color = Self::from_hsl(parse_number(h)?, parse_percentage(sat)?, parse_percentage(l)?);
+                    Ok(color.opacity(f32_to_u8_sat(parse_alpha(a)?)))
+                }
+                _ => bail!("Unknown color function or wrong number of arguments in {:?}", s),
+            };
+        }
+
+        Self::from_name(s).ok_or_else(|| anyhow!("Unknown color {:?}", s))
+    }
+}
+
+/// Formats as `#RRGGBB`, or `#RRGGBBAA` when the color isn't opaque
+impl std::fmt::Display for Color {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let [r, g, b, a] = self.to_rgba();
+        match a {
+            0xff => write!(f, "#{:02X}{:02X}{:02X}", r, g, b),
+            _ => write!(f, "#{:02X}{:02X}{:02X}{:02X}", r, g, b, a),
+        }
+    }
+}
+
+/// Compares color names ignoring case and surrounding whitespace, treating `-` like `_`
+pub(crate) fn names_match(a: &str, b: &str) -> bool {
+    let normalize = |c: char| if c == '-' { '_' } else { c.to_ascii_lowercase() };
+    a.trim().chars().map(normalize).eq(b.trim().chars().map(normalize))
+}
+
+fn parse_hex(hex: &str) -> Option<Color> {
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let digit = |i: usize| u8::from_str_radix(&hex[i..i + 1], 16).ok().map(|d| d * 17);
+    let byte = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+
+    match hex.len() {
+        3 => Some(Color::from_rgb(digit(0)?, digit(1)?, digit(2)?)),
+        4 => Some(Color::from_rgba(digit(0)?, digit(1)?, digit(2)?, digit(3)?)),
+        6 => Some(Color::from_rgb(byte(0)?, byte(2)?, byte(4)?)),
+        8 => Some(Color::from_rgba(byte(0)?, byte(2)?, byte(4)?, byte(6)?)),
+        _ => None,
+    }
+}
+
+fn parse_number(s: &str) -> Result<f32> {
+    s.parse().map_err(|_| anyhow!("Invalid number {:?}", s))
+}
+
+/// A percentage in `0.0..=100.0`, with or without the `%`
+fn parse_percentage(s: &str) -> Result<f32> {
+    parse_number(s.strip_suffix('%').unwrap_or(s))
+}
+
+/// `0..=255` or a percentage, returned in `0.0..=1.0`
+fn parse_channel(s: &str) -> Result<f32> {
+    match s.strip_suffix('%') {
+        Some(percentage) => Ok(parse_number(percentage)? / 100.0),
+        None => Ok(parse_number(s)? / 255.0),
+    }
+}
+
+/// `0.0..=1.0` or a percentage, returned in `0.0..=1.0`
+fn parse_alpha(s: &str) -> Result<f32> {
+    match s.strip_suffix('%') {
+        Some(percentage) => Ok(parse_number(percentage)? / 100.0),
+        None => parse_number(s),
+    }
+}
+
+/// Serializes a [`Color`] as its [`Display`](std::fmt::Display) string instead of a `u32`.
+/// Deserializing accepts any string [`FromStr`] does as well as the `u32` format, so existing configs keep loading.
+///
+/// ```ignore
+/// #[derive(Serialize, Deserialize)]
+/// struct Config {
+///     #[serde(with = "crate::color::serde_string")]
+///     color: Color,
+/// }
+/// ```
+pub mod serde_string {
+    use super::Color;
+    use serde::{Serializer, Deserializer, de};
+    use std::convert::TryFrom;
+
+    pub fn serialize<S: Serializer>(color: &Color, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(color)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+        struct ColorVisitor;
+
+        impl<'de> de::Visitor<'de> for ColorVisitor {
+            type Value = Color;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a color string or a packed u32 color")
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<Color, E> {
+                value.parse().map_err(|e| E::custom(format!("{:#}", e)))
+            }
+
+            fn visit_u64<E: de::Error>(self, value: u64) -> Result<Color, E> {
+                let bits = u32::try_from(value).map_err(|_| E::custom("color is larger than a u32"))?;
+                Ok(Color::from_bits(bits))
+            }
+
+            // Formats like TOML only have signed integers
+            fn visit_i64<E: de::Error>(self, value: i64) -> Result<Color, E> {
+                let bits = u32::try_from(value).map_err(|_| E::custom("color is not in the range of a u32"))?;
+                Ok(Color::from_bits(bits))
+            }
+        }
+
+        deserializer.deserialize_any(ColorVisitor)
+    }
+}
+
 // These utilities might be worth making `pub` as free functions in
 // `crate::color` so user code can ensure their numeric handling is
 // consistent...
@@ -392,101 +642,258 @@ pub(crate) fn u8_to_f32_sat(u: u8) -> f32 {
     (u as f32) * (1.0 / 255.0)
 }
 
-macro_rules! static_color {
-    ($name:ident,$hex:literal) => {
-        // pub fn $name() -> Self {
-        //     Self::from_hex($hex)
-        // }
-        pub const $name: Self = Self::from_hex($hex);
+/// Converts an sRGB encoded channel in `0.0..=1.0` to linear light
+pub fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Opposite of [`srgb_to_linear`]
+pub fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Hue in degrees shared by HSV and HSL
+fn hue(r: f32, g: f32, b: f32, max: f32, delta: f32) -> f32 {
+    if delta <= 0.0 {
+        return 0.0;
+    }
+    let h = if max == r {
+        (g - b) / delta
+    } else if max == g {
+        (b - r) / delta + 2.0
+    } else {
+        (r - g) / delta + 4.0
+    };
+    (h * 60.0).rem_euclid(360.0)
+}
+
+/// Linear sRGB to OKLab, see https://bottosson.github.io/posts/oklab/
+fn linear_to_oklab(rgba: [f32; 4]) -> [f32; 3] {
+    let [r, g, b, _] = rgba;
+    let l = (0.4122214708 * r + 0.5363325363 * g + 0.0514459929 * b).cbrt();
+    let m = (0.2119034982 * r + 0.6806995451 * g + 0.1073969566 * b).cbrt();
+    let s = (0.0883024619 * r + 0.2817188376 * g + 0.6299787005 * b).cbrt();
+    [
+        0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s,
+        1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s,
+        0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s,
+    ]
+}
+
+/// Opposite of [`linear_to_oklab`]
+fn oklab_to_linear(lab: [f32; 3]) -> [f32; 3] {
+    let [l, a, b] = lab;
+    let l_ = (l + 0.3963377774 * a + 0.2158037573 * b).powi(3);
+    let m_ = (l - 0.1055613458 * a - 0.0638541728 * b).powi(3);
+    let s_ = (l - 0.0894841775 * a - 1.2914855480 * b).powi(3);
+    [
+        4.0767416621 * l_ - 3.3077115913 * m_ + 0.2309699292 * s_,
+        -1.2684380046 * l_ + 2.6097574011 * m_ - 0.3413193965 * s_,
+        -0.0041960863 * l_ - 0.7034186147 * m_ + 1.7076147010 * s_,
+    ]
+}
+
+macro_rules! static_colors {
+    ($($name:ident => $hex:literal),* $(,)?) => {
+        impl Color {
+            $(pub const $name: Self = Self::from_hex($hex);)*
+
+            /// Every palette color with the name of its constant
+            pub const NAMED: &'static [(&'static str, Color)] = &[
+                ("BLACK", Self::BLACK),
+                ("WHITE", Self::WHITE),
+                ("TRANSPARENT", Self::TRANSPARENT),
+                $((stringify!($name), Self::$name)),*
+            ];
+        }
     };
 }
 
 // Colors from https://blueprintjs.com/docs/#core/colors
 // Source: https://github.com/palantir/blueprint/tree/develop/packages/core/src/common/colors.ts
-impl Color {
-    static_color!(BLUE1, 0x0E5A8A);
-    static_color!(BLUE2, 0x106BA3);
-    static_color!(BLUE3, 0x137CBD);
-    static_color!(BLUE4, 0x2B95D6);
-    static_color!(BLUE5, 0x48AFF0);
-    static_color!(COBALT1, 0x1F4B99);
-    static_color!(COBALT2, 0x2458B3);
-    static_color!(COBALT3, 0x2965CC);
-    static_color!(COBALT4, 0x4580E6);
-    static_color!(COBALT5, 0x669EFF);
-    static_color!(DARK_GRAY1, 0x182026);
-    static_color!(DARK_GRAY2, 0x202B33);
-    static_color!(DARK_GRAY3, 0x293742);
-    static_color!(DARK_GRAY4, 0x30404D);
-    static_color!(DARK_GRAY5, 0x394B59);
-    static_color!(FOREST1, 0x1D7324);
-    static_color!(FOREST2, 0x238C2C);
-    static_color!(FOREST3, 0x29A634);
-    static_color!(FOREST4, 0x43BF4D);
-    static_color!(FOREST5, 0x62D96B);
-    static_color!(GOLD1, 0xA67908);
-    static_color!(GOLD2, 0xBF8C0A);
-    static_color!(GOLD3, 0xD99E0B);
-    static_color!(GOLD4, 0xF2B824);
-    static_color!(GOLD5, 0xFFC940);
-    static_color!(GRAY1, 0x5C7080);
-    static_color!(GRAY2, 0x738694);
-    static_color!(GRAY3, 0x8A9BA8);
-    static_color!(GRAY4, 0xA7B6C2);
-    static_color!(GRAY5, 0xBFCCD6);
-    static_color!(GREEN1, 0x0A6640);
-    static_color!(GREEN2, 0x0D8050);
-    static_color!(GREEN3, 0x0F9960);
-    static_color!(GREEN4, 0x15B371);
-    static_color!(GREEN5, 0x3DCC91);
-    static_color!(INDIGO1, 0x5642A6);
-    static_color!(INDIGO2, 0x634DBF);
-    static_color!(INDIGO3, 0x7157D9);
-    static_color!(INDIGO4, 0x9179F2);
-    static_color!(INDIGO5, 0xAD99FF);
-    static_color!(LIGHT_GRAY1, 0xCED9E0);
-    static_color!(LIGHT_GRAY2, 0xD8E1E8);
-    static_color!(LIGHT_GRAY3, 0xE1E8ED);
-    static_color!(LIGHT_GRAY4, 0xEBF1F5);
-    static_color!(LIGHT_GRAY5, 0xF5F8FA);
-    static_color!(LIME1, 0x728C23);
-    static_color!(LIME2, 0x87A629);
-    static_color!(LIME3, 0x9BBF30);
-    static_color!(LIME4, 0xB6D94C);
-    static_color!(LIME5, 0xD1F26D);
-    static_color!(ORANGE1, 0xA66321);
-    static_color!(ORANGE2, 0xBF7326);
-    static_color!(ORANGE3, 0xD9822B);
-    static_color!(ORANGE4, 0xF29D49);
-    static_color!(ORANGE5, 0xFFB366);
-    static_color!(RED1, 0xA82A2A);
-    static_color!(RED2, 0xC23030);
-    static_color!(RED3, 0xDB3737);
-    static_color!(RED4, 0xF55656);
-    static_color!(RED5, 0xFF7373);
-    static_color!(ROSE1, 0xA82255);
-    static_color!(ROSE2, 0xC22762);
-    static_color!(ROSE3, 0xDB2C6F);
-    static_color!(ROSE4, 0xF5498B);
-    static_color!(ROSE5, 0xFF66A1);
-    static_color!(SEPIA1, 0x63411E);
-    static_color!(SEPIA2, 0x7D5125);
-    static_color!(SEPIA3, 0x96622D);
-    static_color!(SEPIA4, 0xB07B46);
-    static_color!(SEPIA5, 0xC99765);
-    static_color!(TURQUOISE1, 0x008075);
-    static_color!(TURQUOISE2, 0x00998C);
-    static_color!(TURQUOISE3, 0x00B3A4);
-    static_color!(TURQUOISE4, 0x14CCBD);
-    static_color!(TURQUOISE5, 0x2EE6D6);
-    static_color!(VERMILION1, 0x9E2B0E);
-    static_color!(VERMILION2, 0xB83211);
-    static_color!(VERMILION3, 0xD13913);
-    static_color!(VERMILION4, 0xEB532D);
-    static_color!(VERMILION5, 0xFF6E4A);
-    static_color!(VIOLET1, 0x5C255C);
-    static_color!(VIOLET2, 0x752F75);
-    static_color!(VIOLET3, 0x8F398F);
-    static_color!(VIOLET4, 0xA854A8);
-    static_color!(VIOLET5, 0xC274C2);
+static_colors! {
+    BLUE1 => 0x0E5A8A,
+    BLUE2 => 0x106BA3,
+    BLUE3 => 0x137CBD,
+    BLUE4 => 0x2B95D6,
+    BLUE5 => 0x48AFF0,
+    COBALT1 => 0x1F4B99,
+    COBALT2 => 0x2458B3,
+    COBALT3 => 0x2965CC,
+    COBALT4 => 0x4580E6,
+    COBALT5 => 0x669EFF,
+    DARK_GRAY1 => 0x182026,
+    DARK_GRAY2 => 0x202B33,
+    DARK_GRAY3 => 0x293742,
+    DARK_GRAY4 => 0x30404D,
+    DARK_GRAY5 => 0x394B59,
+    FOREST1 => 0x1D7324,
+    FOREST2 => 0x238C2C,
+    FOREST3 => 0x29A634,
+    FOREST4 => 0x43BF4D,
+    FOREST5 => 0x62D96B,
+    GOLD1 => 0xA67908,
+    GOLD2 => 0xBF8C0A,
+    GOLD3 => 0xD99E0B,
+    GOLD4 => 0xF2B824,
+    GOLD5 => 0xFFC940,
+    GRAY1 => 0x5C7080,
+    GRAY2 => 0x738694,
+    GRAY3 => 0x8A9BA8,
+    GRAY4 => 0xA7B6C2,
+    GRAY5 => 0xBFCCD6,
+    GREEN1 => 0x0A6640,
+    GREEN2 => 0x0D8050,
+    GREEN3 => 0x0F9960,
+    GREEN4 => 0x15B371,
+    GREEN5 => 0x3DCC91,
+    INDIGO1 => 0x5642A6,
+    INDIGO2 => 0x634DBF,
+    INDIGO3 => 0x7157D9,
+    INDIGO4 => 0x9179F2,
+    INDIGO5 => 0xAD99FF,
+    LIGHT_GRAY1 => 0xCED9E0,
+    LIGHT_GRAY2 => 0xD8E1E8,
+    LIGHT_GRAY3 => 0xE1E8ED,
+    LIGHT_GRAY4 => 0xEBF1F5,
+    LIGHT_GRAY5 => 0xF5F8FA,
+    LIME1 => 0x728C23,
+    LIME2 => 0x87A629,
+    LIME3 => 0x9BBF30,
+    LIME4 => 0xB6D94C,
+    LIME5 => 0xD1F26D,
+    ORANGE1 => 0xA66321,
+    ORANGE2 => 0xBF7326,
+    ORANGE3 => 0xD9822B,
+    ORANGE4 => 0xF29D49,
+    ORANGE5 => 0xFFB366,
+    RED1 => 0xA82A2A,
+    RED2 => 0xC23030,
+    RED3 => 0xDB3737,
+    RED4 => 0xF55656,
+    RED5 => 0xFF7373,
+    ROSE1 => 0xA82255,
+    ROSE2 => 0xC22762,
+    ROSE3 => 0xDB2C6F,
+    ROSE4 => 0xF5498B,
+    ROSE5 => 0xFF66A1,
+    SEPIA1 => 0x63411E,
+    SEPIA2 => 0x7D5125,
+    SEPIA3 => 0x96622D,
+    SEPIA4 => 0xB07B46,
+    SEPIA5 => 0xC99765,
+    TURQUOISE1 => 0x008075,
+    TURQUOISE2 => 0x00998C,
+    TURQUOISE3 => 0x00B3A4,
+    TURQUOISE4 => 0x14CCBD,
+    TURQUOISE5 => 0x2EE6D6,
+    VERMILION1 => 0x9E2B0E,
+    VERMILION2 => 0xB83211,
+    VERMILION3 => 0xD13913,
+    VERMILION4 => 0xEB532D,
+    VERMILION5 => 0xFF6E4A,
+    VIOLET1 => 0x5C255C,
+    VIOLET2 => 0x752F75,
+    VIOLET3 => 0x8F398F,
+    VIOLET4 => 0xA854A8,
+    VIOLET5 => 0xC274C2,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every opaque sRGB color
+    fn all_colors() -> impl Iterator<Item = Color> {
+        (0..1u32 << 24).map(Color::from_hex)
+    }
+
+    #[test]
+    fn hsv_round_trips() {
+        for color in all_colors() {
+            let (h, s, v) = color.to_hsv();
+            assert_eq!(Color::from_hsv(h, s, v), color);
+        }
+    }
+
+    #[test]
+    fn hsl_round_trips() {
+        for color in all_colors() {
+            let (h, s, l) = color.to_hsl();
+            assert_eq!(Color::from_hsl(h, s, l), color);
+        }
+    }
+
+    #[test]
+    fn linear_round_trips() {
+        for color in all_colors() {
+            assert_eq!(Color::from_linear(color.to_linear()), color);
+        }
+    }
+
+    #[test]
+    fn oklab_round_trips() {
+        for color in all_colors() {
+            let [r, g, b] = oklab_to_linear(linear_to_oklab(color.to_linear()));
+            assert_eq!(Color::from_linear([r, g, b, 1.0]), color);
+        }
+    }
+
+    #[test]
+    fn oklab_matches_reference_values() {
+        // From the CSS Color Module Level 4 sample code, which uses the same matrices
+        let references = [
+            (Color::WHITE, [1.0, 0.0, 0.0]),
+            (Color::BLACK, [0.0, 0.0, 0.0]),
+            (Color::from_hex(0xFF0000), [0.627955, 0.224863, 0.125846]),
+            (Color::from_hex(0x00FF00), [0.866440, -0.233888, 0.179498]),
+            (Color::from_hex(0x0000FF), [0.452014, -0.032457, -0.311528]),
+        ];
+        for &(color, expected) in &references {
+            let lab = linear_to_oklab(color.to_linear());
+            for (channel, expected) in lab.iter().zip(expected.iter()) {
+                assert!((channel - expected).abs() < 1e-3, "{:?} is {:?} instead of {:?}", color, lab, expected);
+            }
+        }
+    }
+
+    #[derive(Debug, serde::Deserialize)]
+    struct Config {
+        #[serde(with = "serde_string")]
+        color: Color,
+    }
+
+    #[test]
+    fn deserializes_packed_colors_from_toml() {
+        let config: Config = toml::from_str("color = 4278190335").unwrap();
+        assert_eq!(config.color, Color::from_bits(4278190335));
+        assert_eq!(config.color, Color::from_rgba(255, 0, 0, 255));
+
+        assert!(toml::from_str::<Config>("color = -1").is_err());
+        assert!(toml::from_str::<Config>("color = 4294967296").is_err());
+    }
+
+    #[test]
+    fn deserializes_color_strings() {
+        let config: Config = toml::from_str("color = \"#FF0000\"").unwrap();
+        assert_eq!(config.color, Color::from_hex(0xFF0000));
+        let config: Config = serde_json::from_str("{\"color\": 4278190335}").unwrap();
+        assert_eq!(config.color, Color::from_rgba(255, 0, 0, 255));
+    }
+
+    #[test]
+    fn lerp_oklab_keeps_end_points() {
+        let a = Color::from_rgba(255, 0, 0, 0);
+        let b = Color::from_hex(0x2B95D6);
+        assert_eq!(a.lerp_oklab(b, 0.0), a);
+        assert_eq!(a.lerp_oklab(b, 1.0), b);
+    }
 }
@@ -0,0 +1,228 @@
+use crate::draw::Draw;
+use crate::geometry::bounds;
+use crate::types::*;
+
+/// Edges of a 3D box as indices into its eight corners: the bottom face, the top face,
+/// then the pillars connecting them
+const BOX_3D_EDGES: [(usize, usize); 12] = [
+    (0, 1), (1, 2), (2, 3), (3, 0),
+    (4, 5), (5, 6), (6, 7), (7, 4),
+    (0, 4), (1, 5), (2, 6), (3, 7),
+];
+
+/// Width in pixels of the outline around each corner box line
+const CORNER_OUTLINE_WIDTH: f32 = 1.0;
+
+/// Widgets built out of the primitives in [`Draw`]. Implemented for every `Draw`, so
+/// `use crate::composites::DrawComposites` is all that is needed to use them
+pub trait DrawComposites: Draw {
+    /// Draws only the corners of the box from `p1` to `p2`
+    fn draw_corner_box(&self, p1: impl Into<[f32; 2]>, p2: impl Into<[f32; 2]>, options: CornerBoxOptions) {
+        let (min, max) = bounds(&[p1.into(), p2.into()]);
+        let length = options.length.clamp(0.0, 0.5);
+        let horizontal = (max[0] - min[0]) * length;
+        let vertical = (max[1] - min[1]) * length;
+
+        let lines = [
+            ([min[0], min[1]], [min[0] + horizontal, min[1]]),
+            ([min[0], min[1]], [min[0], min[1] + vertical]),
+            ([max[0], min[1]], [max[0] - horizontal, min[1]]),
+            ([max[0], min[1]], [max[0], min[1] + vertical]),
+            ([max[0], max[1]], [max[0] - horizontal, max[1]]),
+            ([max[0], max[1]], [max[0], max[1] - vertical]),
+            ([min[0], max[1]], [min[0] + horizontal, max[1]]),
+            ([min[0], max[1]], [min[0], max[1] - vertical]),
+        ];
+
+        if let Some(outline) = options.outline {
+            let line_options = LineOptions::default().color(outline).width(options.width + 2.0 * CORNER_OUTLINE_WIDTH);
+            for &(a, b) in &lines {
+                // Lengthen the outline past both ends so it also borders the ends and the corner itself
+                let length = ((b[0] - a[0]).powi(2) + (b[1] - a[1]).powi(2)).sqrt();
+                if length <= 0.0 {
+                    continue;
+                }
+                let extend = [(b[0] - a[0]) / length * CORNER_OUTLINE_WIDTH, (b[1] - a[1]) / length * CORNER_OUTLINE_WIDTH];
+                self.draw_line([a[0] - extend[0], a[1] - extend[1]], [b[0] + extend[0], b[1] + extend[1]], line_options.clone());
+            }
+        }
+
+        let line_options = LineOptions::default().color(options.color).width(options.width);
+        for &(a, b) in &lines {
+            self.draw_line(a, b, line_options.clone());
+        }
+    }
+
+    /// Draws the wireframe of a projected 3D box. The first four corners are one face
+    /// and the last four are the opposite face, both in the same winding order
+    fn draw_box_3d(&self, corners: [[f32; 2]; 8], options: Box3dOptions) {
+        let line_options = LineOptions::default().color(options.color).width(options.width);
+        for &(a, b) in &BOX_3D_EDGES {
+            self.draw_line(corners[a], corners[b], line_options.clone());
+        }
+    }
+
+    /// Draws a bar from `p1` to `p2` filled to `value`, from 0.0 (empty) to 1.0 (full)
+    fn draw_bar(&self, p1: impl Into<[f32; 2]>, p2: impl Into<[f32; 2]>, value: f32, options: BarOptions) {
+        let (min, max) = bounds(&[p1.into(), p2.into()]);
+        let value = value.clamp(0.0, 1.0);
+
+        self.draw_box(min, max, BoxOptions::default().color(options.background).filled(true));
+
+        let (fill_min, fill_max) = match options.direction {
+            BarDirection::Vertical => ([min[0], max[1] - (max[1] - min[1]) * value], max),
+            BarDirection::Horizontal => (min, [min[0] + (max[0] - min[0]) * value, max[1]]),
+        };
+        if value > 0.0 {
+            // Draw the fill over the whole bar and clip it so gradients keep their positions as the value changes
+            self.push_clip_rect(fill_min, fill_max);
+            self.draw_box(min, max, BoxOptions::default().color(options.color).fill(options.fill).filled(true));
+            self.pop_clip_rect();
+        }
+
+        if let Some(outline) = options.outline {
+            let offset = options.outline_width / 2.0;
+            self.draw_box(
+                [min[0] - offset, min[1] - offset],
+                [max[0] + offset, max[1] + offset],
+                BoxOptions::default().color(outline).width(options.outline_width),
+            );
+        }
+    }
+}
+
+impl<T: Draw> DrawComposites for T {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::recorder::{DrawCommand, DrawRecorder};
+
+    fn lines(commands: &[DrawCommand]) -> Vec<([f32; 2], [f32; 2], Color, f32)> {
+        commands.iter()
+            .map(|command| match command {
+                DrawCommand::Line { p1, p2, color, width } => (*p1, *p2, *color, *width),
+                command => panic!("Expected only lines, got {:?}", command),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn corner_box_lines_start_at_the_corners() {
+        let recorder = DrawRecorder::new(false);
+        recorder.draw_corner_box([110.0, 80.0], [10.0, 0.0], CornerBoxOptions::default().color(Color::RED1).length(0.2));
+
+        let lines = lines(&recorder.commands());
+        let segments: Vec<_> = lines.iter().map(|&(p1, p2, ..)| (p1, p2)).collect();
+        assert_eq!(segments, vec![
+            ([10.0, 0.0], [30.0, 0.0]),
+            ([10.0, 0.0], [10.0, 16.0]),
+            ([110.0, 0.0], [90.0, 0.0]),
+            ([110.0, 0.0], [110.0, 16.0]),
+            ([110.0, 80.0], [90.0, 80.0]),
+            ([110.0, 80.0], [110.0, 64.0]),
+            ([10.0, 80.0], [30.0, 80.0]),
+            ([10.0, 80.0], [10.0, 64.0]),
+        ]);
+        assert!(lines.iter().all(|&(.., color, width)| color == Color::RED1 && width == 1.0));
+    }
+
+    #[test]
+    fn corner_box_length_is_clamped() {
+        let recorder = DrawRecorder::new(false);
+        recorder.draw_corner_box([0.0, 0.0], [100.0, 50.0], CornerBoxOptions::default().length(2.0));
+        let lines = lines(&recorder.commands());
+        assert_eq!((lines[0].0, lines[0].1), ([0.0, 0.0], [50.0, 0.0]));
+        assert_eq!((lines[1].0, lines[1].1), ([0.0, 0.0], [0.0, 25.0]));
+    }
+
+    #[test]
+    fn corner_box_outline_extends_past_the_line_ends() {
+        let recorder = DrawRecorder::new(false);
+        recorder.draw_corner_box([0.0, 0.0], [100.0, 100.0], CornerBoxOptions::default().width(2.0).outline(Some(Color::BLACK)));
+
+        let lines = lines(&recorder.commands());
+        assert_eq!(lines.len(), 16);
+
+        // Outlines are drawn first so the lines end up on top
+        let (outlines, fills) = lines.split_at(8);
+        assert!(outlines.iter().all(|&(.., color, width)| color == Color::BLACK && width == 4.0));
+        assert!(fills.iter().all(|&(.., color, width)| color == CornerBoxOptions::default().color && width == 2.0));
+        assert_eq!((outlines[0].0, outlines[0].1), ([-1.0, 0.0], [26.0, 0.0]));
+        assert_eq!((outlines[1].0, outlines[1].1), ([0.0, -1.0], [0.0, 26.0]));
+        assert_eq!((outlines[4].0, outlines[4].1), ([101.0, 100.0], [74.0, 100.0]));
+        assert_eq!((outlines[5].0, outlines[5].1), ([100.0, 101.0], [100.0, 74.0]));
+    }
+
+    #[test]
+    fn box_3d_draws_every_edge() {
+        let corners = [
+            [0.0, 0.0], [10.0, 0.0], [10.0, 10.0], [0.0, 10.0],
+            [5.0, 5.0], [15.0, 5.0], [15.0, 15.0], [5.0, 15.0],
+        ];
+        let recorder = DrawRecorder::new(false);
+        recorder.draw_box_3d(corners, Box3dOptions::default().width(2.0));
+
+        let lines = lines(&recorder.commands());
+        assert_eq!(lines.len(), 12);
+
+        // Every corner is shared by exactly three edges, and no edge is drawn twice
+        let mut edges: Vec<(usize, usize)> = lines.iter()
+            .map(|&(p1, p2, ..)| {
+                let a = corners.iter().position(|&corner| corner == p1).unwrap();
+                let b = corners.iter().position(|&corner| corner == p2).unwrap();
+                (a.min(b), a.max(b))
+            })
+            .collect();
+        for corner in 0..8 {
+            assert_eq!(edges.iter().filter(|&&(a, b)| a == corner || b == corner).count(), 3);
+        }
+        edges.sort_unstable();
+        edges.dedup();
+        assert_eq!(edges.len(), 12);
+        assert!(lines.iter().all(|&(.., width)| width == 2.0));
+    }
+
+    fn bar_fill(commands: &[DrawCommand]) -> Option<([f32; 2], [f32; 2])> {
+        commands.iter().find_map(|command| match command {
+            DrawCommand::PushClipRect { p1, p2 } => Some((*p1, *p2)),
+            _ => None,
+        })
+    }
+
+    #[test]
+    fn bar_values_are_clamped() {
+        let recorder = DrawRecorder::new(false);
+        let options = BarOptions::default().outline(None);
+
+        recorder.draw_bar([0.0, 0.0], [10.0, 100.0], 0.25, options.clone());
+        assert_eq!(bar_fill(&recorder.take_commands()), Some(([0.0, 75.0], [10.0, 100.0])));
+
+        recorder.draw_bar([0.0, 0.0], [10.0, 100.0], 1.5, options.clone());
+        assert_eq!(bar_fill(&recorder.take_commands()), Some(([0.0, 0.0], [10.0, 100.0])));
+
+        recorder.draw_bar([0.0, 0.0], [10.0, 100.0], -0.5, options.clone());
+        let commands = recorder.take_commands();
+        assert_eq!(bar_fill(&commands), None);
+        // Only the background is left
+        assert_eq!(commands.len(), 1);
+
+        recorder.draw_bar([0.0, 0.0], [100.0, 10.0], 0.4, options.direction(BarDirection::Horizontal));
+        assert_eq!(bar_fill(&recorder.take_commands()), Some(([0.0, 0.0], [40.0, 10.0])));
+    }
+
+    #[test]
+    fn bar_outline_surrounds_the_bar() {
+        let recorder = DrawRecorder::new(false);
+        recorder.draw_bar([0.0, 0.0], [10.0, 100.0], 0.5, BarOptions::default().outline(Some(Color::BLACK)).outline_width(2.0));
+
+        match recorder.commands().last() {
+            Some(DrawCommand::Box { p1, p2, color, width, filled, .. }) => {
+                assert_eq!((*p1, *p2), ([-1.0, -1.0], [11.0, 101.0]));
+                assert_eq!((*color, *width, *filled), (Color::BLACK, 2.0, false));
+            }
+            command => panic!("Expected the outline last, got {:?}", command),
+        }
+    }
+}
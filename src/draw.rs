@@ -0,0 +1,82 @@
+use crate::types::*;
+use crate::text::{TextLayout, layout_text};
+
+/// A surface that overlay primitives can be drawn on. Implemented by
+/// [`ImguiOverlay`](crate::imgui::overlay::ImguiOverlay) for real frames and by
+/// [`DrawRecorder`](crate::recorder::DrawRecorder) for headless tests
+pub trait Draw {
+    fn draw_line(&self, p1: impl Into<[f32; 2]>, p2: impl Into<[f32; 2]>, options: LineOptions);
+
+    fn draw_box(&self, p1: impl Into<[f32; 2]>, p2: impl Into<[f32; 2]>, options: BoxOptions);
+
+    fn draw_text(&self, origin: impl Into<[f32; 2]>, text: &str, options: TextOptions);
+
+    fn draw_circle(&self, origin: impl Into<[f32; 2]>, radius: f32, options: CircleOptions);
+
+    /// Draws connected line segments through every point without closing the shape
+    fn draw_polyline(&self, points: &[[f32; 2]], options: LineOptions);
+
+    /// Draws a closed shape. Filled polygons may be concave but must not intersect themselves
+    fn draw_polygon(&self, points: &[[f32; 2]], options: PolygonOptions);
+
+    fn draw_quadratic_bezier(&self, p0: impl Into<[f32; 2]>, p1: impl Into<[f32; 2]>, p2: impl Into<[f32; 2]>, options: CurveOptions);
+
+    fn draw_cubic_bezier(&self, p0: impl Into<[f32; 2]>, p1: impl Into<[f32; 2]>, p2: impl Into<[f32; 2]>, p3: impl Into<[f32; 2]>, options: CurveOptions);
+
+    /// Draws an arc from `start_angle` to `end_angle` in radians, measured clockwise from the positive x axis
+    fn draw_arc(&self, center: impl Into<[f32; 2]>, radius: f32, start_angle: f32, end_angle: f32, options: ArcOptions);
+
+    /// Draws `image` stretched to fill the rectangle from `p1` to `p2`
+    fn draw_image(&self, image: &ImageHandle, p1: impl Into<[f32; 2]>, p2: impl Into<[f32; 2]>, options: ImageOptions);
+
+    /// Draws `image` with its center at `center`, rotated clockwise by `angle` radians
+    fn draw_image_rotated(&self, image: &ImageHandle, center: impl Into<[f32; 2]>, size: [f32; 2], angle: f32, options: ImageOptions);
+
+    /// Limits every following draw to the rectangle from `p1` to `p2`, intersected with the current clip rect
+    fn push_clip_rect(&self, p1: impl Into<[f32; 2]>, p2: impl Into<[f32; 2]>);
+
+    /// Restores the clip rect from before the last `push_clip_rect`
+    fn pop_clip_rect(&self);
+
+    /// Returns the size of a single line of `text` when drawn with `font` at `size`
+    fn text_size(&self, text: &str, font: &Font, size: Option<u32>) -> [f32; 2];
+
+    /// Breaks `text` into lines and positions them the same way `draw_text` would
+    fn layout_text(&self, origin: impl Into<[f32; 2]>, text: &str, options: &TextOptions) -> TextLayout {
+        layout_text(origin.into(), text, options, |line| self.text_size(line, &options.font, options.font_size))
+    }
+
+    /// Returns the top left and bottom right corners of `text` as `draw_text` would draw it
+    fn measure_text(&self, origin: impl Into<[f32; 2]>, text: &str, options: &TextOptions) -> ([f32; 2], [f32; 2]) {
+        let layout = self.layout_text(origin, text, options);
+        (layout.min, layout.max)
+    }
+}
+
+/// Rounds a point to the nearest pixel if `align_to_pixel` is set
+pub(crate) fn align_point(mut point: [f32; 2], align_to_pixel: bool) -> [f32; 2] {
+    if align_to_pixel {
+        point[0] = point[0].round();
+        point[1] = point[1].round();
+    }
+    point
+}
+
+/// Rounds every point to the nearest pixel if `align_to_pixel` is set
+pub(crate) fn align_points(points: &[[f32; 2]], align_to_pixel: bool) -> Vec<[f32; 2]> {
+    points.iter().map(|&point| align_point(point, align_to_pixel)).collect()
+}
+
+/// The corners of a `size` rectangle centered on `center` and rotated clockwise by `angle` radians,
+/// starting at the top left and going clockwise
+pub(crate) fn rotated_rect(center: [f32; 2], size: [f32; 2], angle: f32) -> [[f32; 2]; 4] {
+    let (sin, cos) = angle.sin_cos();
+    let [half_width, half_height] = [size[0] / 2.0, size[1] / 2.0];
+    let rotate = |x: f32, y: f32| [center[0] + x * cos - y * sin, center[1] + x * sin + y * cos];
+    [
+        rotate(-half_width, -half_height),
+        rotate(half_width, -half_height),
+        rotate(half_width, half_height),
+        rotate(-half_width, half_height),
+    ]
+}
@@ -0,0 +1,429 @@
+//! Polygon helpers shared by the drawing backends
+
+/// Twice the signed area of the polygon. Positive when the points are clockwise on screen (y down)
+pub fn signed_area(points: &[[f32; 2]]) -> f32 {
+    let mut area = 0.0;
+    for i in 0..points.len() {
+        let a = points[i];
+        let b = points[(i + 1) % points.len()];
+        area += a[0] * b[1] - b[0] * a[1];
+    }
+    area
+}
+
+/// Returns true if every turn of the polygon goes in the same direction and the turns add up to a
+/// single revolution. Self-intersecting polygons like stars turn one way too, but go around more than once
+pub fn is_convex(points: &[[f32; 2]]) -> bool {
+    if points.len() < 4 {
+        return true;
+    }
+
+    let mut sign = 0.0;
+    let mut total_turn = 0.0f64;
+    for i in 0..points.len() {
+        let a = points[i];
+        let b = points[(i + 1) % points.len()];
+        let c = points[(i + 2) % points.len()];
+        let cross = cross(a, b, c);
+        if cross != 0.0 {
+            if sign == 0.0 {
+                sign = cross.signum();
+            } else if cross.signum() != sign {
+                return false;
+            }
+        }
+
+        let (ab, bc) = ([b[0] - a[0], b[1] - a[1]], [c[0] - b[0], c[1] - b[1]]);
+        let dot = ab[0] * bc[0] + ab[1] * bc[1];
+        total_turn += (cross as f64).atan2(dot as f64);
+    }
+    (total_turn.abs() - std::f64::consts::TAU).abs() < 1e-3
+}
+
+/// Splits a simple (non self-intersecting) polygon into triangles using ear clipping.
+/// Returns indices into `points`
+pub fn triangulate(points: &[[f32; 2]]) -> Vec<[usize; 3]> {
+    if points.len() < 3 {
+        return Vec::new();
+    }
+
+    // Work on a clockwise ordering so every ear has a positive cross product
+    let mut remaining: Vec<usize> = (0..points.len()).collect();
+    if signed_area(points) < 0.0 {
+        remaining.reverse();
+    }
+
+    let mut triangles = Vec::with_capacity(points.len() - 2);
+    while remaining.len() > 3 {
+        let len = remaining.len();
+        let ear = (0..len).find(|&i| {
+            let (a, b, c) = (remaining[(i + len - 1) % len], remaining[i], remaining[(i + 1) % len]);
+            is_ear(points, &remaining, a, b, c)
+        });
+
+        match ear {
+            Some(i) => {
+                triangles.push([remaining[(i + len - 1) % len], remaining[i], remaining[(i + 1) % len]]);
+                remaining.remove(i);
+            }
+            // Degenerate or self intersecting input, fall back to a fan so something still gets drawn
+            None => break,
+        }
+    }
+
+    for i in 1..remaining.len() - 1 {
+        triangles.push([remaining[0], remaining[i], remaining[i + 1]]);
+    }
+    triangles
+}
+
+fn is_ear(points: &[[f32; 2]], remaining: &[usize], a: usize, b: usize, c: usize) -> bool {
+    let (pa, pb, pc) = (points[a], points[b], points[c]);
+    if cross(pa, pb, pc) <= 0.0 {
+        return false;
+    }
+
+    remaining.iter()
+        .filter(|&&i| i != a && i != b && i != c)
+        .all(|&i| !in_triangle(points[i], pa, pb, pc))
+}
+
+fn cross(a: [f32; 2], b: [f32; 2], c: [f32; 2]) -> f32 {
+    (b[0] - a[0]) * (c[1] - b[1]) - (b[1] - a[1]) * (c[0] - b[0])
+}
+
+fn in_triangle(p: [f32; 2], a: [f32; 2], b: [f32; 2], c: [f32; 2]) -> bool {
+    cross(a, b, p) >= 0.0 && cross(b, c, p) >= 0.0 && cross(c, a, p) >= 0.0
+}
+
+/// The most segments a single curve is split into, however small the tolerance
+const MAX_SEGMENTS: usize = 512;
+
+fn segments_for(count: f32) -> usize {
+    if count.is_finite() {
+        (count.ceil() as usize).clamp(1, MAX_SEGMENTS)
+    } else {
+        MAX_SEGMENTS
+    }
+}
+
+fn length(v: [f32; 2]) -> f32 {
+    (v[0] * v[0] + v[1] * v[1]).sqrt()
+}
+
+fn second_difference(a: [f32; 2], b: [f32; 2], c: [f32; 2]) -> f32 {
+    length([a[0] - 2.0 * b[0] + c[0], a[1] - 2.0 * b[1] + c[1]])
+}
+
+/// Splits a quadratic bezier into points no further than `tolerance` pixels from the curve
+pub fn flatten_quadratic(p0: [f32; 2], p1: [f32; 2], p2: [f32; 2], tolerance: f32) -> Vec<[f32; 2]> {
+    // The chord error of a quadratic is at most |p0 - 2p1 + p2| * step^2 / 4
+    let segments = segments_for((second_difference(p0, p1, p2) / (4.0 * tolerance)).sqrt());
+
+    (0..=segments)
+        .map(|i| {
+            let t = i as f32 / segments as f32;
+            let u = 1.0 - t;
+            [
+                u * u * p0[0] + 2.0 * u * t * p1[0] + t * t * p2[0],
+                u * u * p0[1] + 2.0 * u * t * p1[1] + t * t * p2[1],
+            ]
+        })
+        .collect()
+}
+
+/// Splits a cubic bezier into points no further than `tolerance` pixels from the curve
+pub fn flatten_cubic(p0: [f32; 2], p1: [f32; 2], p2: [f32; 2], p3: [f32; 2], tolerance: f32) -> Vec<[f32; 2]> {
+    // The second derivative of a cubic is bounded by 6 * max(|p0 - 2p1 + p2|, |p1 - 2p2 + p3|)
+    let dd = second_difference(p0, p1, p2).max(second_difference(p1, p2, p3));
+    let segments = segments_for((3.0 * dd / (4.0 * tolerance)).sqrt());
+
+    (0..=segments)
+        .map(|i| {
+            let t = i as f32 / segments as f32;
+            let u = 1.0 - t;
+            let (a, b, c, d) = (u * u * u, 3.0 * u * u * t, 3.0 * u * t * t, t * t * t);
+            [
+                a * p0[0] + b * p1[0] + c * p2[0] + d * p3[0],
+                a * p0[1] + b * p1[1] + c * p2[1] + d * p3[1],
+            ]
+        })
+        .collect()
+}
+
+/// Points along an arc from `start_angle` to `end_angle` (radians, clockwise on screen from +x)
+/// with chords no further than `tolerance` pixels from the circle
+pub fn flatten_arc(center: [f32; 2], radius: f32, start_angle: f32, end_angle: f32, tolerance: f32) -> Vec<[f32; 2]> {
+    let sweep = end_angle - start_angle;
+    // The sagitta of a chord spanning `step` radians is radius * (1 - cos(step / 2))
+    let step = 2.0 * (1.0 - (tolerance / radius).min(1.0)).acos();
+    let segments = segments_for(sweep.abs() / step);
+
+    (0..=segments)
+        .map(|i| {
+            // `start_angle + sweep` can round away from `end_angle`, so the last point uses it directly
+            let angle = match i == segments {
+                true => end_angle,
+                false => start_angle + sweep * (i as f32 / segments as f32),
+            };
+            [center[0] + radius * angle.cos(), center[1] + radius * angle.sin()]
+        })
+        .collect()
+}
+
+/// The smallest rectangle containing every point, as `(min, max)`
+pub fn bounds(points: &[[f32; 2]]) -> ([f32; 2], [f32; 2]) {
+    let mut min = [f32::INFINITY; 2];
+    let mut max = [f32::NEG_INFINITY; 2];
+    for point in points {
+        min = [min[0].min(point[0]), min[1].min(point[1])];
+        max = [max[0].max(point[0]), max[1].max(point[1])];
+    }
+    (min, max)
+}
+
+/// The outline of a rectangle with rounded corners, clockwise on screen starting at the top left
+pub fn rounded_rect(p1: [f32; 2], p2: [f32; 2], rounding: f32, tolerance: f32) -> Vec<[f32; 2]> {
+    use std::f32::consts::{FRAC_PI_2, PI};
+
+    let (min, max) = bounds(&[p1, p2]);
+    let rounding = rounding.min((max[0] - min[0]) / 2.0).min((max[1] - min[1]) / 2.0);
+    if rounding <= 0.0 {
+        return vec![min, [max[0], min[1]], max, [min[0], max[1]]];
+    }
+
+    let corners = [
+        ([min[0] + rounding, min[1] + rounding], PI),
+        ([max[0] - rounding, min[1] + rounding], PI + FRAC_PI_2),
+        ([max[0] - rounding, max[1] - rounding], 0.0),
+        ([min[0] + rounding, max[1] - rounding], FRAC_PI_2),
+    ];
+    corners.iter()
+        .flat_map(|&(center, start)| flatten_arc(center, rounding, start, start + FRAC_PI_2, tolerance))
+        .collect()
+}
+
+/// The most pieces a single triangle edge is split into by [`subdivide`]
+const MAX_SUBDIVISIONS: usize = 32;
+
+/// Splits every triangle into smaller ones with edges no longer than `max_edge` pixels, so per
+/// vertex colors can approximate fills that aren't linear across a whole triangle
+pub fn subdivide(points: &[[f32; 2]], triangles: &[[usize; 3]], max_edge: f32) -> (Vec<[f32; 2]>, Vec<[usize; 3]>) {
+    let mut out_points = Vec::new();
+    let mut out_triangles = Vec::new();
+
+    for triangle in triangles {
+        let [a, b, c] = [points[triangle[0]], points[triangle[1]], points[triangle[2]]];
+        let longest = length([b[0] - a[0], b[1] - a[1]])
+            .max(length([c[0] - b[0], c[1] - b[1]]))
+            .max(length([a[0] - c[0], a[1] - c[1]]));
+        let segments = segments_for(longest / max_edge).min(MAX_SUBDIVISIONS);
+        let base = out_points.len();
+
+        // Row `i` holds `segments - i + 1` points, walking from edge ab towards c
+        let mut row_start = Vec::with_capacity(segments + 1);
+        for i in 0..=segments {
+            row_start.push(out_points.len() - base);
+            for j in 0..=(segments - i) {
+                let (u, v) = (j as f32 / segments as f32, i as f32 / segments as f32);
+                let w = 1.0 - u - v;
+                out_points.push([
+                    a[0] * w + b[0] * u + c[0] * v,
+                    a[1] * w + b[1] * u + c[1] * v,
+                ]);
+            }
+        }
+
+        for i in 0..segments {
+            for j in 0..(segments - i) {
+                let p = base + row_start[i] + j;
+                let above = base + row_start[i + 1] + j;
+                out_triangles.push([p, p + 1, above]);
+                if j + 1 < segments - i {
+                    out_triangles.push([p + 1, above + 1, above]);
+                }
+            }
+        }
+    }
+
+    (out_points, out_triangles)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn convex_polygons() {
+        assert!(is_convex(&[[0.0, 0.0], [10.0, 0.0], [10.0, 10.0], [0.0, 10.0]]));
+        // Counter clockwise, with a collinear point on one edge
+        assert!(is_convex(&[[0.0, 0.0], [0.0, 10.0], [10.0, 10.0], [10.0, 5.0], [10.0, 0.0]]));
+    }
+
+    #[test]
+    fn concave_polygons() {
+        assert!(!is_convex(&[[0.0, 0.0], [10.0, 0.0], [5.0, 3.0], [10.0, 10.0], [0.0, 10.0]]));
+    }
+
+    #[test]
+    fn stars_are_not_convex() {
+        // A pentagram visits the corners of a pentagon in every other order, so it turns the same
+        // way at every corner but goes around twice
+        let star: Vec<[f32; 2]> = (0..5)
+            .map(|i| {
+                let angle = (i * 2) as f32 * std::f32::consts::TAU / 5.0;
+                [angle.cos() * 10.0, angle.sin() * 10.0]
+            })
+            .collect();
+        assert!(!is_convex(&star));
+    }
+
+    #[test]
+    fn backtracking_is_not_convex() {
+        assert!(!is_convex(&[[0.0, 0.0], [10.0, 0.0], [5.0, 0.0], [5.0, 10.0]]));
+    }
+
+    /// Distance from `p` to the segment from `a` to `b`
+    fn distance_to_segment(p: [f32; 2], a: [f32; 2], b: [f32; 2]) -> f32 {
+        let ab = [b[0] - a[0], b[1] - a[1]];
+        let length_squared = ab[0] * ab[0] + ab[1] * ab[1];
+        let t = match length_squared > 0.0 {
+            true => (((p[0] - a[0]) * ab[0] + (p[1] - a[1]) * ab[1]) / length_squared).clamp(0.0, 1.0),
+            false => 0.0,
+        };
+        length([p[0] - (a[0] + ab[0] * t), p[1] - (a[1] + ab[1] * t)])
+    }
+
+    /// Samples `curve` between the parameters of every pair of flattened points and returns the
+    /// furthest any sample is from the segment between them
+    fn max_error(points: &[[f32; 2]], curve: impl Fn(f32) -> [f32; 2]) -> f32 {
+        let segments = points.len() - 1;
+        let mut error = 0.0f32;
+        for (i, pair) in points.windows(2).enumerate() {
+            for sample in 0..=32 {
+                let t = (i as f32 + sample as f32 / 32.0) / segments as f32;
+                error = error.max(distance_to_segment(curve(t), pair[0], pair[1]));
+            }
+        }
+        error
+    }
+
+    fn quadratic(p0: [f32; 2], p1: [f32; 2], p2: [f32; 2], t: f32) -> [f32; 2] {
+        let u = 1.0 - t;
+        [
+            u * u * p0[0] + 2.0 * u * t * p1[0] + t * t * p2[0],
+            u * u * p0[1] + 2.0 * u * t * p1[1] + t * t * p2[1],
+        ]
+    }
+
+    fn cubic(p0: [f32; 2], p1: [f32; 2], p2: [f32; 2], p3: [f32; 2], t: f32) -> [f32; 2] {
+        let u = 1.0 - t;
+        let (a, b, c, d) = (u * u * u, 3.0 * u * u * t, 3.0 * u * t * t, t * t * t);
+        [
+            a * p0[0] + b * p1[0] + c * p2[0] + d * p3[0],
+            a * p0[1] + b * p1[1] + c * p2[1] + d * p3[1],
+        ]
+    }
+
+    #[test]
+    fn quadratic_beziers_stay_within_tolerance() {
+        let (p0, p1, p2) = ([10.0, 300.0], [150.0, -200.0], [400.0, 250.0]);
+        for &tolerance in &[2.0, 0.5, 0.1] {
+            let points = flatten_quadratic(p0, p1, p2, tolerance);
+            assert_eq!(points[0], p0);
+            assert_eq!(*points.last().unwrap(), p2);
+            assert!(max_error(&points, |t| quadratic(p0, p1, p2, t)) <= tolerance * 1.01, "tolerance {}", tolerance);
+        }
+        assert!(flatten_quadratic(p0, p1, p2, 0.1).len() > flatten_quadratic(p0, p1, p2, 2.0).len());
+    }
+
+    #[test]
+    fn cubic_beziers_stay_within_tolerance() {
+        // Crosses over itself, so the curvature changes a lot along the curve
+        let (p0, p1, p2, p3) = ([0.0, 0.0], [500.0, 400.0], [-100.0, 400.0], [400.0, 0.0]);
+        for &tolerance in &[2.0, 0.5, 0.1] {
+            let points = flatten_cubic(p0, p1, p2, p3, tolerance);
+            assert_eq!(points[0], p0);
+            assert_eq!(*points.last().unwrap(), p3);
+            assert!(max_error(&points, |t| cubic(p0, p1, p2, p3, t)) <= tolerance * 1.01, "tolerance {}", tolerance);
+        }
+    }
+
+    #[test]
+    fn straight_curves_are_a_single_segment() {
+        assert_eq!(flatten_quadratic([0.0, 0.0], [5.0, 5.0], [10.0, 10.0], 0.25).len(), 2);
+        assert_eq!(flatten_cubic([0.0, 0.0], [1.0, 0.0], [2.0, 0.0], [3.0, 0.0], 0.25).len(), 2);
+    }
+
+    #[test]
+    fn arcs_stay_within_tolerance() {
+        let (center, radius) = ([50.0, 80.0], 120.0);
+        for &(start, end) in &[(0.1, 2.3), (-1.0, 4.0), (3.0, -0.5)] {
+            for &tolerance in &[2.0, 0.5, 0.1] {
+                let points = flatten_arc(center, radius, start, end, tolerance);
+                assert_eq!(points[0], [center[0] + radius * start.cos(), center[1] + radius * start.sin()]);
+                assert_eq!(*points.last().unwrap(), [center[0] + radius * end.cos(), center[1] + radius * end.sin()]);
+
+                let error = max_error(&points, |t| {
+                    let angle = start + (end - start) * t;
+                    [center[0] + radius * angle.cos(), center[1] + radius * angle.sin()]
+                });
+                assert!(error <= tolerance * 1.01, "arc {} to {}, tolerance {}: {}", start, end, tolerance, error);
+            }
+        }
+    }
+
+    #[test]
+    fn tiny_tolerances_are_capped() {
+        assert_eq!(flatten_arc([0.0, 0.0], 1000.0, 0.0, std::f32::consts::TAU, 0.0).len(), MAX_SEGMENTS + 1);
+        assert_eq!(flatten_cubic([0.0, 0.0], [500.0, 400.0], [-100.0, 400.0], [400.0, 0.0], 0.0).len(), MAX_SEGMENTS + 1);
+    }
+
+    fn triangle_area(points: &[[f32; 2]], triangles: &[[usize; 3]]) -> f32 {
+        triangles.iter()
+            .map(|&[a, b, c]| signed_area(&[points[a], points[b], points[c]]).abs())
+            .sum()
+    }
+
+    #[test]
+    fn subdivides_by_triangle_size() {
+        let points = [[0.0, 0.0], [40.0, 0.0], [0.0, 40.0], [4.0, 0.0], [0.0, 4.0]];
+        let large = [[0, 1, 2]];
+        let small = [[0, 3, 4]];
+
+        // Edges of 40 and 56.6 pixels with at most 10 pixels per piece need 6 pieces per edge
+        let (out_points, out_triangles) = subdivide(&points, &large, 10.0);
+        assert_eq!(out_triangles.len(), 6 * 6);
+        assert_eq!(out_points.len(), 7 * 8 / 2);
+        assert!((triangle_area(&out_points, &out_triangles) - triangle_area(&points, &large)).abs() < 1e-2);
+        for &[a, b, c] in &out_triangles {
+            for &(p, q) in &[(a, b), (b, c), (c, a)] {
+                let edge = [out_points[q][0] - out_points[p][0], out_points[q][1] - out_points[p][1]];
+                assert!(length(edge) <= 10.0);
+            }
+        }
+
+        let (out_points, out_triangles) = subdivide(&points, &small, 10.0);
+        assert_eq!(out_triangles, vec![[0, 1, 2]]);
+        assert_eq!(out_points, vec![[0.0, 0.0], [4.0, 0.0], [0.0, 4.0]]);
+    }
+
+    #[test]
+    fn subdivision_is_capped() {
+        let points = [[0.0, 0.0], [10000.0, 0.0], [0.0, 10000.0]];
+        let (_, triangles) = subdivide(&points, &[[0, 1, 2]], 1.0);
+        assert_eq!(triangles.len(), MAX_SUBDIVISIONS * MAX_SUBDIVISIONS);
+    }
+
+    #[test]
+    fn triangulates_concave_polygons() {
+        let points = [[0.0, 0.0], [10.0, 0.0], [5.0, 3.0], [10.0, 10.0], [0.0, 10.0]];
+        let triangles = triangulate(&points);
+        assert_eq!(triangles.len(), points.len() - 2);
+
+        let area: f32 = triangles.iter()
+            .map(|&[a, b, c]| signed_area(&[points[a], points[b], points[c]]).abs())
+            .sum();
+        assert!((area - signed_area(&points).abs()).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,309 @@
+use imgui::*;
+use crate::imgui::{RenderContext, RenderState};
+use crate::color::Color;
+use crate::input::Input;
+use super::keybind::{Keybind, keybind_edit_with_id};
+use std::collections::BTreeMap;
+use anyhow::{anyhow, Result};
+
+/// Something the user can bind keys to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Action {
+    /// Identifies the action in code and in saved bindings
+    pub name: String,
+    /// Shown in the bindings table
+    pub label: String,
+    pub default: Keybind,
+    pub keybind: Keybind,
+}
+
+impl Action {
+    pub fn is_default(&self) -> bool {
+        self.keybind == self.default
+    }
+}
+
+/// Two actions that would both be triggered by the same key press
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+    pub first: String,
+    pub second: String,
+}
+
+/// Every action of the overlay with its keybind. Actions keep the order they were registered in
+#[derive(Debug, Clone, Default)]
+pub struct KeybindRegistry {
+    actions: Vec<Action>,
+}
+
+impl KeybindRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an action bound to `default`. Registering a name again replaces the label and default
+    /// but keeps the current keybind
+    pub fn register(&mut self, name: impl Into<String>, label: impl Into<String>, default: Keybind) -> &mut Self {
+        let (name, label) = (name.into(), label.into());
+        match self.actions.iter_mut().find(|action| action.name == name) {
+            Some(action) => {
+                action.label = label;
+                action.default = default;
+            }
+            None => self.actions.push(Action { name, label, keybind: default.clone(), default }),
+        }
+        self
+    }
+
+    pub fn action(&self, name: &str) -> Option<&Action> {
+        self.actions.iter().find(|action| action.name == name)
+    }
+
+    pub fn actions(&self) -> &[Action] {
+        &self.actions
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Keybind> {
+        self.action(name).map(|action| &action.keybind)
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Keybind> {
+        self.actions.iter_mut().find(|action| action.name == name).map(|action| &mut action.keybind)
+    }
+
+    pub fn set(&mut self, name: &str, keybind: Keybind) -> Result<()> {
+        *self.get_mut(name).ok_or_else(|| anyhow!("Unknown action {:?}", name))? = keybind;
+        Ok(())
+    }
+
+    /// Whether the action is active according to the mode of its keybind. Unknown actions are never active
+    pub fn is_active(&mut self, name: &str, input: &Input) -> bool {
+        self.get_mut(name).map_or(false, |keybind| keybind.is_active(input))
+    }
+
+    pub fn is_down(&self, name: &str, input: &Input) -> bool {
+        self.get(name).map_or(false, |keybind| keybind.is_down(input))
+    }
+
+    pub fn reset(&mut self, name: &str) -> Result<()> {
+        let action = self.actions.iter_mut()
+            .find(|action| action.name == name)
+            .ok_or_else(|| anyhow!("Unknown action {:?}", name))?;
+        action.keybind = action.default.clone();
+        Ok(())
+    }
+
+    pub fn reset_all(&mut self) {
+        for action in &mut self.actions {
+            action.keybind = action.default.clone();
+        }
+    }
+
+    /// Every pair of actions whose keybinds conflict
+    pub fn conflicts(&self) -> Vec<Conflict> {
+        let mut conflicts = Vec::new();
+        for (i, first) in self.actions.iter().enumerate() {
+            for second in &self.actions[i + 1..] {
+                if first.keybind.conflicts_with(&second.keybind) {
+                    conflicts.push(Conflict { first: first.name.clone(), second: second.name.clone() });
+                }
+            }
+        }
+        conflicts
+    }
+
+    /// The other actions `keybind` would trigger if it was assigned to `name`
+    pub fn conflicts_with(&self, name: &str, keybind: &Keybind) -> Vec<&Action> {
+        self.actions.iter()
+            .filter(|action| action.name != name && action.keybind.conflicts_with(keybind))
+            .collect()
+    }
+
+    /// The keybind of every action by name, for saving
+    pub fn bindings(&self) -> BTreeMap<String, Keybind> {
+        self.actions.iter()
+            .map(|action| (action.name.clone(), action.keybind.clone()))
+            .collect()
+    }
+
+    /// Assigns saved keybinds. Actions that aren't registered (anymore) are skipped, and actions
+    /// missing from `bindings` keep their keybind
+    pub fn load_bindings(&mut self, bindings: &BTreeMap<String, Keybind>) {
+        for action in &mut self.actions {
+            if let Some(keybind) = bindings.get(&action.name) {
+                action.keybind = keybind.clone();
+            }
+        }
+    }
+}
+
+/// A table with every action of `registry`, a button to rebind each one and buttons to reset them.
+/// Actions that conflict with another one are shown in red, with the others in a tooltip
+pub fn keybind_table(ui: &Ui, render_state: &mut RenderState, render_context: &RenderContext, registry: &mut KeybindRegistry) {
+    let id_token = ui.push_id("keybind_table");
+
+    if ui.button(im_str!("Reset all"), [80.0, 0.0]) {
+        registry.reset_all();
+    }
+    ui.separator();
+
+    ui.columns(3, im_str!("##keybinds"), false);
+    ui.set_column_width(0, 160.0);
+    ui.set_column_width(1, 260.0);
+
+    for i in 0..registry.actions.len() {
+        let conflicts: Vec<String> = registry.conflicts_with(&registry.actions[i].name, &registry.actions[i].keybind)
+            .into_iter()
+            .map(|action| action.label.clone())
+            .collect();
+        let action = &mut registry.actions[i];
+
+        if conflicts.is_empty() {
+            ui.text(&action.label);
+        } else {
+            ui.text_colored(Color::RED4.into(), &action.label);
+            if ui.is_item_hovered() {
+                ui.tooltip_text(format!("Also bound to {}", conflicts.join(", ")));
+            }
+        }
+        ui.next_column();
+
+        let id = format!("keybind_table:{}", action.name);
+        keybind_edit_with_id(ui, render_state, render_context, &id, im_str!(""), &mut action.keybind);
+        ui.next_column();
+
+        if !action.is_default() {
+            let reset_token = ui.push_id(&*id);
+            if ui.button(im_str!("Reset"), [0.0, 0.0]) {
+                action.keybind = action.default.clone();
+            }
+            reset_token.pop(ui);
+        }
+        ui.next_column();
+    }
+
+    ui.columns(1, im_str!(""), false);
+    id_token.pop(ui);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::{Key, MouseButton};
+    use crate::imgui::keybind::{KeybindMode, Modifiers};
+
+    fn registry() -> KeybindRegistry {
+        let mut registry = KeybindRegistry::new();
+        registry
+            .register("toggle_ui", "Toggle UI", Keybind::key(Key::Insert).with_mode(KeybindMode::Toggle))
+            .register("aim", "Aim", Keybind::mouse(MouseButton::X1))
+            .register("zoom", "Zoom", Keybind::key(Key::Z));
+        registry
+    }
+
+    /// A keybind that needs both `key` and `button` held
+    fn key_and_button(key: Key, button: MouseButton) -> Keybind {
+        let mut keybind = Keybind::key(key);
+        keybind.mouse_button = Some(button);
+        keybind
+    }
+
+    #[test]
+    fn register_keeps_order_and_current_keybind() {
+        let mut registry = registry();
+        registry.set("aim", Keybind::key(Key::E)).unwrap();
+        registry.register("aim", "Aim assist", Keybind::mouse(MouseButton::X2));
+
+        let names: Vec<&str> = registry.actions().iter().map(|action| action.name.as_str()).collect();
+        assert_eq!(names, ["toggle_ui", "aim", "zoom"]);
+        let aim = registry.action("aim").unwrap();
+        assert_eq!(aim.label, "Aim assist");
+        assert_eq!(aim.default, Keybind::mouse(MouseButton::X2));
+        assert_eq!(aim.keybind, Keybind::key(Key::E));
+    }
+
+    #[test]
+    fn set_and_reset() {
+        let mut registry = registry();
+        assert!(registry.set("missing", Keybind::key(Key::E)).is_err());
+        assert!(registry.reset("missing").is_err());
+
+        registry.set("aim", Keybind::key(Key::E)).unwrap();
+        registry.set("zoom", Keybind::key(Key::C)).unwrap();
+        assert!(!registry.action("aim").unwrap().is_default());
+
+        registry.reset("aim").unwrap();
+        assert!(registry.action("aim").unwrap().is_default());
+        assert_eq!(registry.get("zoom"), Some(&Keybind::key(Key::C)));
+
+        registry.reset_all();
+        assert!(registry.actions().iter().all(Action::is_default));
+    }
+
+    #[test]
+    fn load_bindings_skips_unknown_names() {
+        let mut registry = registry();
+        let mut bindings = registry.bindings();
+        bindings.insert("aim".to_string(), Keybind::key(Key::E));
+        bindings.insert("removed".to_string(), Keybind::key(Key::R));
+        bindings.remove("zoom");
+        registry.set("zoom", Keybind::key(Key::C)).unwrap();
+
+        registry.load_bindings(&bindings);
+        assert_eq!(registry.get("aim"), Some(&Keybind::key(Key::E)));
+        assert_eq!(registry.get("zoom"), Some(&Keybind::key(Key::C)));
+        assert!(registry.action("removed").is_none());
+        assert_eq!(registry.actions().len(), 3);
+    }
+
+    #[test]
+    fn bindings_round_trip_through_toml() {
+        let registry = registry();
+        let toml = toml::to_string(&registry.bindings()).unwrap();
+        let mut loaded = KeybindRegistry::new();
+        loaded.register("toggle_ui", "Toggle UI", Keybind::unbound()).register("aim", "Aim", Keybind::unbound());
+        loaded.load_bindings(&toml::from_str(&toml).unwrap());
+        assert_eq!(loaded.get("toggle_ui"), registry.get("toggle_ui"));
+        assert_eq!(loaded.get("aim"), registry.get("aim"));
+    }
+
+    #[test]
+    fn actions_follow_input() {
+        let mut registry = registry();
+        let (mut input, fake) = (Input::new(), crate::input::FakeInput::new());
+        fake.press_button(MouseButton::X1);
+        input.poll(&mut fake.clone());
+
+        assert!(registry.is_down("aim", &input));
+        assert!(registry.is_active("aim", &input));
+        assert!(!registry.is_active("zoom", &input));
+        assert!(!registry.is_active("missing", &input));
+    }
+
+    #[test]
+    fn finds_conflicts() {
+        let mut registry = registry();
+        assert!(registry.conflicts().is_empty());
+
+        let ctrl = Modifiers { ctrl: true, ..Modifiers::default() };
+        registry.set("zoom", Keybind::key(Key::Insert).with_modifiers(ctrl)).unwrap();
+        assert_eq!(registry.conflicts(), vec![Conflict { first: "toggle_ui".to_string(), second: "zoom".to_string() }]);
+
+        // A bind that needs a key on top of another one also triggers it
+        let combined = key_and_button(Key::E, MouseButton::X1);
+        let names: Vec<&str> = registry.conflicts_with("zoom", &combined).iter().map(|action| action.name.as_str()).collect();
+        assert_eq!(names, ["aim"]);
+
+        // Binds that are never triggered by keys don't conflict
+        registry.set("zoom", Keybind::mouse(MouseButton::X1).with_mode(KeybindMode::Always)).unwrap();
+        assert!(registry.conflicts().is_empty());
+    }
+
+    #[test]
+    fn different_keys_do_not_conflict() {
+        assert!(!Keybind::key(Key::X).conflicts_with(&Keybind::key(Key::C)));
+        assert!(!Keybind::key(Key::X).conflicts_with(&key_and_button(Key::C, MouseButton::Left)));
+        assert!(!Keybind::unbound().conflicts_with(&Keybind::unbound()));
+        assert!(Keybind::key(Key::X).conflicts_with(&key_and_button(Key::X, MouseButton::Left)));
+    }
+}
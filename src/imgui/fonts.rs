@@ -1,43 +1,514 @@
-use imgui::{Context, FontSource, FontId, FontConfig};
+use imgui::{Context, FontSource, FontId, FontConfig, FontGlyphRanges, Io};
+use imgui::sys;
 use std::collections::HashMap;
+use std::borrow::Cow;
+use std::path::PathBuf;
+use std::sync::Arc;
+use anyhow::{Result, Context as _};
+use log::*;
 use crate::types;
 
-pub fn add_fonts(ctx: &mut Context, font_scale: f32) -> HashMap<types::Font, FontId> {
-    let mut fonts = HashMap::new();
-
-    // First font is default
-    fonts.insert(
-        types::Font::Default,
-        ctx.fonts()
-            .add_font(&[FontSource::DefaultFontData { config: Some(FontConfig {
-                size_pixels: (13.0 * font_scale),
-                ..Default::default()
-            }) }]),
-    );
-    fonts.insert(
-        types::Font::Pixel,
-        ctx.fonts().add_font(&[FontSource::TtfData {
-            data: include_bytes!("../fonts/smallest_pixel-7.ttf"),
-            size_pixels: 10.0 * font_scale,
-            config: None,
-        }]),
-    );
-    fonts.insert(
-        types::Font::Verdana,
-        ctx.fonts().add_font(&[FontSource::TtfData {
-            data: include_bytes!("../fonts/Verdana.ttf"),
-            size_pixels: 13.0 * font_scale,
-            config: None,
-        }]),
-    );
-    fonts.insert(
-        types::Font::Tahoma,
-        ctx.fonts().add_font(&[FontSource::TtfData {
-            data: include_bytes!("../fonts/Tahoma.ttf"),
-            size_pixels: 14.0 * font_scale,
-            config: None,
-        }]),
-    );
+/// Where the TTF data of a font comes from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FontData {
+    /// The font built into imgui (ProggyClean)
+    Default,
+    Static(&'static [u8]),
+    Bytes(Arc<Vec<u8>>),
+    /// Read when the font is added to the atlas
+    File(PathBuf),
+}
+
+impl FontData {
+    /// Returns the TTF data, or `None` for the built in font
+    fn load(&self) -> Result<Option<Cow<[u8]>>> {
+        Ok(match self {
+            FontData::Default => None,
+            FontData::Static(data) => Some(Cow::Borrowed(*data)),
+            FontData::Bytes(data) => Some(Cow::Borrowed(data.as_slice())),
+            FontData::File(path) => {
+                let data = std::fs::read(path).with_context(|| format!("Could not read {}", path.display()))?;
+                Some(Cow::Owned(data))
+            }
+        })
+    }
+}
+
+/// Basic Latin, Latin-1, Latin Extended-A/B, Greek, Cyrillic and general punctuation
+const EUROPEAN_RANGES: &[u32] = &[
+    0x0020, 0x024F,
+    0x0370, 0x03FF,
+    0x0400, 0x052F,
+    0x2000, 0x206F,
+    0,
+];
+
+/// Which characters of a font get rasterized into the atlas. Every extra glyph takes up
+/// texture space, so the CJK ranges should only be used for fonts that need them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GlyphRanges {
+    /// Basic Latin and Latin-1
+    Default,
+    /// Latin, Greek and Cyrillic, enough for most player names
+    European,
+    Cyrillic,
+    Greek,
+    Thai,
+    Vietnamese,
+    Japanese,
+    Korean,
+    /// The 2500 most common ideograms
+    ChineseSimplifiedCommon,
+    ChineseFull,
+    /// Pairs of inclusive codepoint ranges, terminated by a 0. Useful for icon fonts
+    Custom(&'static [u32]),
+}
+
+impl Default for GlyphRanges {
+    fn default() -> Self {
+        GlyphRanges::Default
+    }
+}
+
+impl GlyphRanges {
+    pub fn to_imgui(self) -> FontGlyphRanges {
+        match self {
+            GlyphRanges::Default => FontGlyphRanges::default(),
+            GlyphRanges::European => FontGlyphRanges::from_slice(EUROPEAN_RANGES),
+            GlyphRanges::Cyrillic => FontGlyphRanges::cyrillic(),
+            GlyphRanges::Greek => FontGlyphRanges::greek(),
+            GlyphRanges::Thai => FontGlyphRanges::thai(),
+            GlyphRanges::Vietnamese => FontGlyphRanges::vietnamese(),
+            GlyphRanges::Japanese => FontGlyphRanges::japanese(),
+            GlyphRanges::Korean => FontGlyphRanges::korean(),
+            GlyphRanges::ChineseSimplifiedCommon => FontGlyphRanges::chinese_simplified_common(),
+            GlyphRanges::ChineseFull => FontGlyphRanges::chinese_full(),
+            GlyphRanges::Custom(ranges) => FontGlyphRanges::from_slice(ranges),
+        }
+    }
+}
+
+/// Another font whose glyphs are added to a [`FontDescriptor`], e.g. icons or CJK characters
+/// the main font doesn't have
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergedFont {
+    pub data: FontData,
+    pub glyph_ranges: GlyphRanges,
+}
+
+/// A font to rasterize into the atlas at one or more pixel sizes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FontDescriptor {
+    pub font: types::Font,
+    pub data: FontData,
+    /// Every size in pixels the font is rasterized at. The first one is used when no size is requested
+    pub sizes: Vec<u32>,
+    pub glyph_ranges: GlyphRanges,
+    /// Merged in order, so earlier fonts win when several have the same glyph
+    pub merged: Vec<MergedFont>,
+}
+
+impl FontDescriptor {
+    pub fn new(font: types::Font, data: FontData, sizes: &[u32]) -> Self {
+        Self { font, data, sizes: sizes.to_vec(), glyph_ranges: GlyphRanges::Default, merged: Vec::new() }
+    }
+
+    /// A font read from a TTF file
+    pub fn from_file(key: impl Into<String>, path: impl Into<PathBuf>, sizes: &[u32]) -> Self {
+        Self::new(types::Font::Custom(key.into()), FontData::File(path.into()), sizes)
+    }
+
+    /// A font from TTF data in memory
+    pub fn from_bytes(key: impl Into<String>, data: Vec<u8>, sizes: &[u32]) -> Self {
+        Self::new(types::Font::Custom(key.into()), FontData::Bytes(Arc::new(data)), sizes)
+    }
+
+    pub fn glyph_ranges(mut self, glyph_ranges: GlyphRanges) -> Self {
+        self.glyph_ranges = glyph_ranges;
+        self
+    }
+
+    /// Adds the `glyph_ranges` of another font to this one at every size
+    pub fn merge(mut self, data: FontData, glyph_ranges: GlyphRanges) -> Self {
+        self.merged.push(MergedFont { data, glyph_ranges });
+        self
+    }
+}
+
+/// The fonts in the atlas, looked up by font and size
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Fonts {
+    ids: HashMap<(types::Font, u32), FontId>,
+    default_sizes: HashMap<types::Font, u32>,
+    pending: Vec<FontDescriptor>,
+}
+
+impl Fonts {
+    /// Returns the font at exactly `size`, or at its first size if `size` is `None`
+    pub fn get(&self, font: &types::Font, size: Option<u32>) -> Option<FontId> {
+        let size = size.or_else(|| self.default_sizes.get(font).copied())?;
+        self.ids.get(&(font.clone(), size)).copied()
+    }
+
+    /// Like `get`, but falls back to the closest size of the same font and then to
+    /// `Font::Default` instead of failing
+    pub fn resolve(&self, font: &types::Font, size: Option<u32>) -> Option<FontId> {
+        if let Some(id) = self.get(font, size) {
+            return Some(id);
+        }
+
+        let closest = self.sizes(font).into_iter()
+            .min_by_key(|&available| match size {
+                Some(size) => (available as i64 - size as i64).abs(),
+                None => 0,
+            });
+        if let Some(closest) = closest {
+            return self.get(font, Some(closest));
+        }
+
+        if *font != types::Font::Default {
+            return self.resolve(&types::Font::Default, size);
+        }
+        None
+    }
+
+    /// Every size `font` is loaded at
+    pub fn sizes(&self, font: &types::Font) -> Vec<u32> {
+        let mut sizes: Vec<u32> = self.ids.keys()
+            .filter(|(key, _)| key == font)
+            .map(|&(_, size)| size)
+            .collect();
+        sizes.sort();
+        sizes
+    }
+
+    /// Queues a font to be added to the atlas before the next frame
+    pub fn load(&mut self, descriptor: FontDescriptor) {
+        self.pending.push(descriptor);
+    }
+
+    pub(crate) fn has_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Adds every queued font to the atlas. The renderer's font texture has to be reloaded afterwards
+    pub(crate) fn add_pending(&mut self, ctx: &mut Context, font_scale: f32) {
+        for descriptor in std::mem::take(&mut self.pending) {
+            if let Err(e) = self.add(ctx, descriptor, font_scale) {
+                error!("Could not load font: {:?}", e);
+            }
+        }
+    }
+
+    /// Rasterizes `descriptor` and the fonts merged into it at each of its sizes
+    pub(crate) fn add(&mut self, ctx: &mut Context, descriptor: FontDescriptor, font_scale: f32) -> Result<()> {
+        let data = descriptor.data.load()?;
+        let merged = descriptor.merged.iter()
+            .map(|merged| Ok((merged.data.load()?, merged.glyph_ranges)))
+            .collect::<Result<Vec<_>>>()?;
+
+        for &size in &descriptor.sizes {
+            let size_pixels = size as f32 * font_scale;
+            // imgui merges every source after the first into it
+            let sources: Vec<FontSource> = std::iter::once((&data, descriptor.glyph_ranges))
+                .chain(merged.iter().map(|(data, ranges)| (data, *ranges)))
+                .map(|(data, glyph_ranges)| {
+                    let config = FontConfig { size_pixels, glyph_ranges: glyph_ranges.to_imgui(), ..Default::default() };
+                    match data.as_deref() {
+                        None => FontSource::DefaultFontData { config: Some(config) },
+                        Some(data) => FontSource::TtfData { data, size_pixels, config: Some(config) },
+                    }
+                })
+                .collect();
+            let id = ctx.fonts().add_font(&sources);
+            self.ids.insert((descriptor.font.clone(), size), id);
+        }
+
+        if let Some(&size) = descriptor.sizes.first() {
+            self.default_sizes.entry(descriptor.font.clone()).or_insert(size);
+        }
+        Ok(())
+    }
+}
+
+/// Remembers the size every font in the atlas was added at, so the whole atlas (including fonts
+/// added straight to the context, like the ones from `themes::main_theme`) can be rasterized again
+/// when the DPI changes. Fonts stay the same size on screen because `font_global_scale` is set to
+/// the inverse of the scale
+#[derive(Debug, Clone)]
+pub struct AtlasScale {
+    scale: f32,
+    base_sizes: Vec<f32>,
+}
+
+impl Default for AtlasScale {
+    fn default() -> Self {
+        Self { scale: 1.0, base_sizes: Vec::new() }
+    }
+}
+
+impl AtlasScale {
+    /// The scale fonts added to the atlas should be rasterized at
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    /// Records the unscaled size of fonts added to the atlas since the last call
+    pub fn sync(&mut self, ctx: &mut Context) {
+        unsafe {
+            let configs = &(*atlas(ctx)).ConfigData;
+            for i in self.base_sizes.len()..configs.Size as usize {
+                self.base_sizes.push((*configs.Data.add(i)).SizePixels / self.scale);
+            }
+        }
+    }
 
+    /// Rasterizes every font at `scale` times its base size. The renderer's font texture has to be reloaded afterwards
+    pub fn set_scale(&mut self, ctx: &mut Context, scale: f32) {
+        self.sync(ctx);
+        if scale == self.scale {
+            return;
+        }
+        self.scale = scale;
+
+        unsafe {
+            let atlas = atlas(ctx);
+            let configs = &mut (*atlas).ConfigData;
+            for (i, base_size) in self.base_sizes.iter().enumerate() {
+                (*configs.Data.add(i)).SizePixels = base_size * scale;
+            }
+            // Forces the atlas to be built again the next time the texture data is requested
+            sys::ImFontAtlas_ClearTexData(atlas);
+        }
+        ctx.io_mut().font_global_scale = 1.0 / scale;
+    }
+}
+
+fn atlas(ctx: &mut Context) -> *mut sys::ImFontAtlas {
+    // Safety: `Io` is a transparent mirror of `ImGuiIO`
+    unsafe { (*(ctx.io_mut() as *mut Io as *mut sys::ImGuiIO)).Fonts }
+}
+
+/// The fonts every overlay has available
+pub fn builtin_fonts() -> Vec<FontDescriptor> {
+    vec![
+        // First font is default
+        FontDescriptor::new(types::Font::Default, FontData::Default, &[13]),
+        FontDescriptor::new(types::Font::Pixel, FontData::Static(include_bytes!("../fonts/smallest_pixel-7.ttf")), &[10]),
+        FontDescriptor::new(types::Font::Verdana, FontData::Static(include_bytes!("../fonts/Verdana.ttf")), &[13])
+            .glyph_ranges(GlyphRanges::European),
+        FontDescriptor::new(types::Font::Tahoma, FontData::Static(include_bytes!("../fonts/Tahoma.ttf")), &[14])
+            .glyph_ranges(GlyphRanges::European),
+    ]
+}
+
+pub fn add_fonts(ctx: &mut Context, font_scale: f32) -> Fonts {
+    let mut fonts = Fonts::default();
+    for descriptor in builtin_fonts() {
+        fonts.add(ctx, descriptor, font_scale).expect("Built in fonts can not fail to load");
+    }
     fonts
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::imgui::{lock_context, RenderContext, RenderState};
+    use crate::imgui::image::RgbaImage;
+    use crate::imgui::software::HeadlessImgui;
+    use crate::types::Font;
+    use imgui::{im_str, Ui};
+
+    #[test]
+    fn resolve_falls_back_to_the_closest_size() {
+        let _lock = lock_context();
+        let mut ctx = Context::create();
+        let mut fonts = Fonts::default();
+        let big = Font::Custom("big".to_string());
+        fonts.add(&mut ctx, FontDescriptor::new(big.clone(), FontData::Default, &[20, 30, 12]), 1.0).unwrap();
+
+        assert_eq!(fonts.sizes(&big), vec![12, 20, 30]);
+        let [small, medium, large] = [12, 20, 30].map(|size| fonts.get(&big, Some(size)).unwrap());
+        assert_ne!(small, medium);
+        assert_ne!(medium, large);
+
+        // The first size is the default one
+        assert_eq!(fonts.get(&big, None), Some(medium));
+        assert_eq!(fonts.resolve(&big, None), Some(medium));
+        assert_eq!(fonts.resolve(&big, Some(30)), Some(large));
+
+        assert_eq!(fonts.get(&big, Some(26)), None);
+        assert_eq!(fonts.resolve(&big, Some(26)), Some(large));
+        assert_eq!(fonts.resolve(&big, Some(14)), Some(small));
+        assert_eq!(fonts.resolve(&big, Some(1)), Some(small));
+        assert_eq!(fonts.resolve(&big, Some(100)), Some(large));
+    }
+
+    #[test]
+    fn resolve_falls_back_to_the_default_font() {
+        let _lock = lock_context();
+        let mut ctx = Context::create();
+        let unknown = Font::Custom("unknown".to_string());
+
+        let mut fonts = Fonts::default();
+        fonts.add(&mut ctx, FontDescriptor::new(Font::Default, FontData::Default, &[13, 26]), 1.0).unwrap();
+
+        assert_eq!(fonts.get(&unknown, None), None);
+        assert_eq!(fonts.resolve(&unknown, None), fonts.get(&Font::Default, Some(13)));
+        assert_eq!(fonts.resolve(&unknown, Some(24)), fonts.get(&Font::Default, Some(26)));
+        assert!(fonts.resolve(&unknown, None).is_some());
+
+        // Without a default font there is nothing to fall back to
+        assert_eq!(Fonts::default().resolve(&unknown, Some(13)), None);
+        assert_eq!(Fonts::default().resolve(&Font::Default, None), None);
+    }
+
+    #[test]
+    fn fonts_are_added_between_frames() {
+        let _lock = lock_context();
+        let mut ctx = Context::create();
+        let mut fonts = add_fonts(&mut ctx, 1.0);
+        let key = Font::Custom("pixel".to_string());
+
+        fonts.load(FontDescriptor::new(key.clone(), FontData::Static(include_bytes!("../fonts/smallest_pixel-7.ttf")), &[10, 20]));
+        assert!(fonts.has_pending());
+        assert_eq!(fonts.get(&key, None), None);
+
+        fonts.add_pending(&mut ctx, 1.0);
+        assert!(!fonts.has_pending());
+        assert_eq!(fonts.sizes(&key), vec![10, 20]);
+        assert!(fonts.get(&key, Some(20)).is_some());
+    }
+
+    /// The smallest rectangle containing every pixel that isn't transparent, as `[min_x, min_y, max_x, max_y]`
+    fn ink_bounds(image: &RgbaImage) -> [u32; 4] {
+        let mut bounds = [u32::MAX, u32::MAX, 0, 0];
+        for y in 0..image.height {
+            for x in 0..image.width {
+                if image.get_pixel(x, y)[3] > 0 {
+                    bounds = [bounds[0].min(x), bounds[1].min(y), bounds[2].max(x + 1), bounds[3].max(y + 1)];
+                }
+            }
+        }
+        bounds
+    }
+
+    fn draw_w(ui: &mut Ui, _: &mut RenderState, _: &mut RenderContext) {
+        ui.get_background_draw_list().add_text([4.0, 4.0], [1.0, 1.0, 1.0, 1.0], "W");
+    }
+
+    #[test]
+    fn atlas_scale_rasterizes_glyphs_at_the_new_size() {
+        let _lock = lock_context();
+        let mut headless = HeadlessImgui::new(Context::create(), [32.0, 32.0]).unwrap();
+
+        let mut text_size = [0.0; 2];
+        let small = headless.render_frame(|ui, state, context| {
+            text_size = ui.calc_text_size(im_str!("W"), false, 0.0);
+            draw_w(ui, state, context);
+        }).unwrap();
+
+        headless.set_scale(2.0).unwrap();
+        assert_eq!(headless.atlas_scale.scale(), 2.0);
+        assert_eq!(headless.imgui.io().font_global_scale, 0.5);
+        let default_font = headless.imgui.fonts().fonts()[0];
+        assert_eq!(headless.imgui.fonts().get_font(default_font).unwrap().font_size, 26.0);
+
+        // Text takes up the same space in the ui, but twice as many pixels on screen
+        let mut scaled_text_size = [0.0; 2];
+        let large = headless.render_frame(|ui, state, context| {
+            scaled_text_size = ui.calc_text_size(im_str!("W"), false, 0.0);
+            draw_w(ui, state, context);
+        }).unwrap();
+        assert_eq!(scaled_text_size[1], text_size[1]);
+        assert!((scaled_text_size[0] - text_size[0]).abs() <= 0.5, "{:?} and {:?}", scaled_text_size, text_size);
+        assert_eq!((large.width, large.height), (64, 64));
+
+        let [small, large] = [ink_bounds(&small), ink_bounds(&large)];
+        let (small_height, large_height) = (small[3] - small[1], large[3] - large[1]);
+        assert!(small_height > 0);
+        assert!((large_height as i64 - 2 * small_height as i64).abs() <= 2, "{:?} and {:?}", small, large);
+        assert!((large[0] as i64 - 2 * small[0] as i64).abs() <= 2, "{:?} and {:?}", small, large);
+
+        // Going back restores the original atlas
+        headless.set_scale(1.0).unwrap();
+        assert_eq!(headless.imgui.io().font_global_scale, 1.0);
+        assert_eq!(headless.imgui.fonts().get_font(default_font).unwrap().font_size, 13.0);
+    }
+
+    #[test]
+    fn fonts_added_after_scaling_are_scaled_too() {
+        let _lock = lock_context();
+        let mut ctx = Context::create();
+        let mut fonts = add_fonts(&mut ctx, 1.0);
+        let mut atlas_scale = AtlasScale::default();
+        atlas_scale.set_scale(&mut ctx, 1.5);
+
+        let key = Font::Custom("late".to_string());
+        fonts.add(&mut ctx, FontDescriptor::new(key.clone(), FontData::Default, &[20]), atlas_scale.scale()).unwrap();
+        atlas_scale.sync(&mut ctx);
+        atlas_scale.set_scale(&mut ctx, 1.0);
+
+        let mut atlas = ctx.fonts();
+        atlas.build_rgba32_texture();
+        let font = atlas.get_font(fonts.get(&key, None).unwrap()).unwrap();
+        assert_eq!(font.font_size, 20.0);
+    }
+
+    /// Whether the atlas has a glyph for `c` in `font`, without falling back to the replacement glyph
+    fn has_glyph(ctx: &mut Context, font: FontId, c: char) -> bool {
+        let mut atlas = ctx.fonts();
+        atlas.build_rgba32_texture();
+        let font = atlas.get_font(font).unwrap() as *const imgui::Font as *mut sys::ImFont;
+        unsafe { !sys::ImFont_FindGlyphNoFallback(font, c as sys::ImWchar).is_null() }
+    }
+
+    #[test]
+    fn glyph_ranges_and_merged_fonts_are_rasterized() {
+        const ZHE: &[u32] = &[0x0416, 0x0416, 0];
+
+        let _lock = lock_context();
+        let mut ctx = Context::create();
+        let mut fonts = Fonts::default();
+        let verdana = FontData::Static(include_bytes!("../fonts/Verdana.ttf"));
+        let key = |name: &str| Font::Custom(name.to_string());
+
+        let descriptors = vec![
+            FontDescriptor::new(key("latin"), verdana.clone(), &[13]),
+            FontDescriptor::new(key("european"), verdana.clone(), &[13]).glyph_ranges(GlyphRanges::European),
+            FontDescriptor::new(key("custom"), verdana.clone(), &[13]).glyph_ranges(GlyphRanges::Custom(ZHE)),
+            // The built in font only has Latin glyphs, so the Cyrillic ones have to come from Verdana
+            FontDescriptor::new(key("merged"), FontData::Default, &[13, 20]).merge(verdana, GlyphRanges::Cyrillic),
+        ];
+        for descriptor in descriptors {
+            fonts.add(&mut ctx, descriptor, 1.0).unwrap();
+        }
+
+        let font = |name: &str, size: u32| fonts.get(&key(name), Some(size)).unwrap();
+        assert!(has_glyph(&mut ctx, font("latin", 13), 'A'));
+        assert!(!has_glyph(&mut ctx, font("latin", 13), 'Ж'));
+
+        assert!(has_glyph(&mut ctx, font("european", 13), 'A'));
+        assert!(has_glyph(&mut ctx, font("european", 13), 'Ж'));
+        assert!(has_glyph(&mut ctx, font("european", 13), 'Ω'));
+
+        assert!(has_glyph(&mut ctx, font("custom", 13), 'Ж'));
+        assert!(!has_glyph(&mut ctx, font("custom", 13), 'A'));
+
+        for &size in &[13, 20] {
+            assert!(has_glyph(&mut ctx, font("merged", size), 'A'));
+            assert!(has_glyph(&mut ctx, font("merged", size), 'Ж'));
+            assert!(!has_glyph(&mut ctx, font("merged", size), 'Ω'));
+        }
+    }
+
+    #[test]
+    fn missing_font_files_are_errors() {
+        let _lock = lock_context();
+        let mut ctx = Context::create();
+        let mut fonts = Fonts::default();
+        let result = fonts.add(&mut ctx, FontDescriptor::from_file("missing", "/does/not/exist.ttf", &[13]), 1.0);
+        assert!(result.is_err());
+        assert!(fonts.sizes(&Font::Custom("missing".to_string())).is_empty());
+    }
+}
@@ -0,0 +1,253 @@
+use anyhow::*;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::Path;
+use crate::types::ImageHandle;
+
+/// An 8 bit per channel RGBA image stored row by row
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RgbaImage {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+}
+
+impl RgbaImage {
+    /// Creates a fully transparent image. Panics if the size in bytes doesn't fit in a `usize`
+    pub fn new(width: u32, height: u32) -> Self {
+        let len = (width as usize).checked_mul(height as usize)
+            .and_then(|pixels| pixels.checked_mul(4))
+            .unwrap_or_else(|| panic!("A {}x{} image is too large", width, height));
+        Self { width, height, data: vec![0; len] }
+    }
+
+    pub fn get_pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let i = self.index(x, y);
+        [self.data[i], self.data[i + 1], self.data[i + 2], self.data[i + 3]]
+    }
+
+    pub fn put_pixel(&mut self, x: u32, y: u32, pixel: [u8; 4]) {
+        let i = self.index(x, y);
+        self.data[i..i + 4].copy_from_slice(&pixel);
+    }
+
+    /// Returns the number of pixels where any channel differs from `other` by more than `tolerance`
+    pub fn diff(&self, other: &RgbaImage, tolerance: u8) -> Result<usize> {
+        if self.width != other.width || self.height != other.height {
+            bail!("Image sizes differ: {}x{} and {}x{}", self.width, self.height, other.width, other.height);
+        }
+
+        Ok(self.data.chunks_exact(4)
+            .zip(other.data.chunks_exact(4))
+            .filter(|(a, b)| a.iter().zip(b.iter()).any(|(a, b)| (*a as i16 - *b as i16).abs() > tolerance as i16))
+            .count())
+    }
+
+    /// Decodes a png, converting it to 8 bit RGBA
+    pub fn from_png(bytes: &[u8]) -> Result<Self> {
+        let mut decoder = png::Decoder::new(bytes);
+        // Expands palettes and low bit depths to 8 bits, and 16 bit channels down to 8
+        decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+        let (info, mut reader) = decoder.read_info()?;
+
+        let mut buffer = vec![0; info.buffer_size()];
+        reader.next_frame(&mut buffer)?;
+
+        let data = match info.color_type {
+            png::ColorType::RGBA => buffer,
+            png::ColorType::RGB => expand_to_rgba(&buffer, 3, |p| [p[0], p[1], p[2], 0xFF]),
+            png::ColorType::GrayscaleAlpha => expand_to_rgba(&buffer, 2, |p| [p[0], p[0], p[0], p[1]]),
+            png::ColorType::Grayscale => expand_to_rgba(&buffer, 1, |p| [p[0], p[0], p[0], 0xFF]),
+            color_type => bail!("Unsupported png color type: {:?}", color_type),
+        };
+
+        Ok(Self { width: info.width, height: info.height, data })
+    }
+
+    /// Reads a png file, converting it to 8 bit RGBA
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_png(&std::fs::read(path)?)
+    }
+
+    /// Encodes the image as a png and writes it to `writer`
+    pub fn write_png(&self, writer: impl Write) -> Result<()> {
+        let mut encoder = png::Encoder::new(writer, self.width, self.height);
+        encoder.set_color(png::ColorType::RGBA);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()?.write_image_data(&self.data)?;
+        Ok(())
+    }
+
+    pub fn save_png(&self, path: impl AsRef<Path>) -> Result<()> {
+        self.write_png(std::io::BufWriter::new(std::fs::File::create(path)?))
+    }
+
+    fn index(&self, x: u32, y: u32) -> usize {
+        (y as usize * self.width as usize + x as usize) * 4
+    }
+}
+
+/// Converts pixels of `channels` bytes each into a single RGBA buffer
+fn expand_to_rgba(buffer: &[u8], channels: usize, convert: impl Fn(&[u8]) -> [u8; 4]) -> Vec<u8> {
+    let mut data = Vec::with_capacity(buffer.len() / channels * 4);
+    for pixel in buffer.chunks_exact(channels) {
+        data.extend_from_slice(&convert(pixel));
+    }
+    data
+}
+
+/// Images loaded from the ui, looked up by name. Textures can only be created between frames, so
+/// `load` queues the image and `get` returns its handle from the next frame on
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Images {
+    handles: HashMap<String, ImageHandle>,
+    pending: Vec<(String, RgbaImage)>,
+}
+
+impl Images {
+    /// Queues `image` to be uploaded before the next frame. An image with the same name is replaced
+    pub fn load(&mut self, name: impl Into<String>, image: RgbaImage) {
+        self.pending.push((name.into(), image));
+    }
+
+    /// Decodes a png and queues it like `load`
+    pub fn load_png(&mut self, name: impl Into<String>, bytes: &[u8]) -> Result<()> {
+        self.load(name, RgbaImage::from_png(bytes)?);
+        Ok(())
+    }
+
+    /// Reads a png file and queues it like `load`
+    pub fn load_file(&mut self, name: impl Into<String>, path: impl AsRef<Path>) -> Result<()> {
+        self.load(name, RgbaImage::open(path)?);
+        Ok(())
+    }
+
+    /// The uploaded image called `name`, or `None` while it is still queued
+    pub fn get(&self, name: &str) -> Option<ImageHandle> {
+        self.handles.get(name).copied()
+    }
+
+    pub(crate) fn has_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Uploads every queued image with `upload`. Returns the handles of the images they replaced,
+    /// whose textures should be removed from the renderer
+    #[must_use]
+    pub(crate) fn upload_pending(&mut self, mut upload: impl FnMut(RgbaImage) -> Result<ImageHandle>) -> Vec<ImageHandle> {
+        let mut replaced = Vec::new();
+        for (name, image) in std::mem::take(&mut self.pending) {
+            match upload(image) {
+                Result::Ok(handle) => replaced.extend(self.handles.insert(name, handle)),
+                Err(e) => log::error!("Could not load image {:?}: {:?}", name, e),
+            }
+        }
+        replaced
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn png_round_trip() {
+        let mut image = RgbaImage::new(3, 2);
+        image.put_pixel(0, 0, [255, 0, 0, 255]);
+        image.put_pixel(2, 1, [10, 20, 30, 40]);
+
+        let mut png = Vec::new();
+        image.write_png(&mut png).unwrap();
+        let decoded = RgbaImage::from_png(&png).unwrap();
+
+        assert_eq!(decoded, image);
+        assert_eq!(decoded.diff(&image, 0).unwrap(), 0);
+    }
+
+    #[test]
+    fn diff_counts_pixels_outside_tolerance() {
+        let image = RgbaImage::new(2, 2);
+        let mut other = image.clone();
+        other.put_pixel(0, 0, [3, 0, 0, 0]);
+        other.put_pixel(1, 1, [0, 0, 0, 10]);
+
+        assert_eq!(image.diff(&other, 5).unwrap(), 1);
+        assert_eq!(image.diff(&other, 10).unwrap(), 0);
+        assert!(image.diff(&RgbaImage::new(1, 2), 0).is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "too large")]
+    fn new_panics_instead_of_overflowing() {
+        RgbaImage::new(u32::MAX, u32::MAX);
+    }
+
+    #[test]
+    fn images_are_available_after_upload() {
+        let mut images = Images::default();
+        images.load("dot", RgbaImage::new(2, 3));
+        assert!(images.has_pending());
+        assert_eq!(images.get("dot"), None);
+
+        let mut uploaded = Vec::new();
+        let replaced = images.upload_pending(|image| {
+            uploaded.push(image.clone());
+            Ok(ImageHandle { texture_id: imgui::TextureId::from(uploaded.len()), width: image.width, height: image.height })
+        });
+
+        assert!(replaced.is_empty());
+        assert!(!images.has_pending());
+        assert_eq!(uploaded, vec![RgbaImage::new(2, 3)]);
+        let handle = images.get("dot").unwrap();
+        assert_eq!((handle.width, handle.height), (2, 3));
+    }
+
+    #[test]
+    fn reloading_an_image_returns_the_replaced_handle() {
+        let mut images = Images::default();
+        let mut next_id = 0;
+        let mut upload = |image: RgbaImage| {
+            next_id += 1;
+            Ok(ImageHandle { texture_id: imgui::TextureId::from(next_id), width: image.width, height: image.height })
+        };
+
+        images.load("dot", RgbaImage::new(1, 1));
+        assert!(images.upload_pending(&mut upload).is_empty());
+        let first = images.get("dot").unwrap();
+
+        images.load("dot", RgbaImage::new(2, 2));
+        images.load("other", RgbaImage::new(1, 1));
+        assert_eq!(images.upload_pending(&mut upload), vec![first]);
+        assert_eq!(images.get("dot").unwrap().width, 2);
+    }
+
+    #[test]
+    fn failed_uploads_keep_the_old_image() {
+        let mut images = Images::default();
+        images.load("dot", RgbaImage::new(1, 1));
+        let _ = images.upload_pending(|image| Ok(ImageHandle { texture_id: imgui::TextureId::from(1), width: image.width, height: image.height }));
+
+        images.load("dot", RgbaImage::new(2, 2));
+        assert!(images.upload_pending(|_| bail!("no gpu")).is_empty());
+        assert_eq!(images.get("dot").unwrap().width, 1);
+    }
+
+    #[test]
+    fn decodes_rgb_and_grayscale_pngs() {
+        fn encode(color: png::ColorType, data: &[u8]) -> Vec<u8> {
+            let mut png = Vec::new();
+            let mut encoder = png::Encoder::new(&mut png, 2, 1);
+            encoder.set_color(color);
+            encoder.set_depth(png::BitDepth::Eight);
+            encoder.write_header().unwrap().write_image_data(data).unwrap();
+            png
+        }
+
+        let rgb = RgbaImage::from_png(&encode(png::ColorType::RGB, &[1, 2, 3, 4, 5, 6])).unwrap();
+        assert_eq!(rgb.data, vec![1, 2, 3, 255, 4, 5, 6, 255]);
+        let gray = RgbaImage::from_png(&encode(png::ColorType::Grayscale, &[7, 8])).unwrap();
+        assert_eq!(gray.data, vec![7, 7, 7, 255, 8, 8, 8, 255]);
+        let gray_alpha = RgbaImage::from_png(&encode(png::ColorType::GrayscaleAlpha, &[7, 1, 8, 2])).unwrap();
+        assert_eq!(gray_alpha.data, vec![7, 7, 7, 1, 8, 8, 8, 2]);
+    }
+}
@@ -1,155 +1,506 @@
 use imgui::{ImStr, StyleColor, StyleVar};
-use crate::imgui::RenderState;
+use crate::imgui::{RenderContext, RenderState, StateKey};
+use crate::input::Input;
 use imgui::*;
-use winapi::um::winuser::GetAsyncKeyState;
-use crate::imgui::util::get_keys_down;
-use winutil::{InputEventListener, VK_ESCAPE};
-use winutil::Event::KeyDown;
+use serde::{Serialize, Deserialize};
+use std::hash::{Hash, Hasher};
+pub use crate::keys::{Key, MouseButton, is_key_down, is_mouse_button_down};
 
 enum KeybindSelectState {
     Idle,
-    Listening(InputEventListener),
+    Listening,
 }
 
-pub fn keybind_select(ui: &::imgui::Ui, render_state: &mut RenderState, title: &ImStr, key: &mut i32) {
+/// A button that sets `key` to the Windows virtual key code of the next key or mouse button
+/// pressed. Escape cancels. Like imgui ids, the state is kept per `title`
+pub fn keybind_select(ui: &::imgui::Ui, render_state: &mut RenderState, render_context: &RenderContext, title: &ImStr, key: &mut i32) {
     use KeybindSelectState::*;
-    let state = render_state.get(&format!("keybind:{:X}", key as *mut _ as usize), KeybindSelectState::Idle);
+    let state_key = StateKey::dynamic(format!("keybind_select:{}", title.to_str()));
+    let state = render_state.get_or_replace_with(&state_key, || KeybindSelectState::Idle);
 
-    let title = if let Listening { .. } = state {
+    let title = if let Listening = state {
         im_str!("Press key").to_owned()
     } else {
-        im_str!("{}: {}", title, vkey_to_string(*key))
+        im_str!("{}: {}", title, vkey_name(*key))
     };
 
     if ui.button(&title, [160.0, 20.0]) {
         *state = match *state {
-            Idle => Listening(InputEventListener::new()),
-            Listening { .. } => Idle
+            Idle => Listening,
+            Listening => Idle
         }
     }
-    if let Listening(lis) = state {
-        let mut handled = false;
-        for ev in lis {
-            if let KeyDown(k) = ev {
-                if k == VK_ESCAPE {
-                    handled = true;
-                    break;
-                }
-                *key = k;
-                handled = true;
-                break;
+    // Clicking the button again cancels instead of binding the left mouse button
+    let hovered = ui.is_item_hovered();
+
+    if let Listening = state {
+        let input = &render_context.input;
+        let pressed_key = input.keys_pressed().filter(|key| key.to_vkey().is_some()).min();
+        let pressed_button = MouseButton::ALL.iter().copied()
+            .find(|&button| input.is_mouse_pressed(button) && (!hovered || button != MouseButton::Left));
+
+        match (pressed_key, pressed_button) {
+            (Some(Key::Escape), _) => *state = Idle,
+            (Some(pressed), _) => {
+                *key = pressed.to_vkey().unwrap_or(*key);
+                *state = Idle;
             }
+            (None, Some(button)) => {
+                *key = button.to_vkey();
+                *state = Idle;
+            }
+            (None, None) => {}
+        }
+    }
+}
+
+/// Modifier keys that have to be held for a [`Keybind`]. Holding extra modifiers doesn't
+/// stop a keybind, so binds keep working while e.g. crouching with ctrl
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Modifiers {
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+}
+
+impl Modifiers {
+    /// The modifiers held this frame
+    pub fn current(input: &Input) -> Self {
+        input.modifiers()
+    }
+
+    /// Whether every modifier in `self` is also in `held`
+    pub fn satisfied_by(&self, held: Modifiers) -> bool {
+        (!self.ctrl || held.ctrl) && (!self.shift || held.shift) && (!self.alt || held.alt)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !self.ctrl && !self.shift && !self.alt
+    }
+}
+
+/// When a [`Keybind`] counts as active
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum KeybindMode {
+    /// While the keys are held
+    Hold,
+    /// Flips every time the keys are pressed
+    Toggle,
+    Always,
+    Never,
+}
+
+impl KeybindMode {
+    pub const ALL: [KeybindMode; 4] = [KeybindMode::Hold, KeybindMode::Toggle, KeybindMode::Always, KeybindMode::Never];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            KeybindMode::Hold => "Hold",
+            KeybindMode::Toggle => "Toggle",
+            KeybindMode::Always => "Always",
+            KeybindMode::Never => "Never",
+        }
+    }
+}
+
+/// A key and/or mouse button with modifiers. When both a key and a mouse button are set, both
+/// have to be held
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Keybind {
+    pub key: Option<Key>,
+    pub mouse_button: Option<MouseButton>,
+    pub mode: KeybindMode,
+    // Tables have to come after plain values in TOML
+    #[serde(default)]
+    pub modifiers: Modifiers,
+    #[serde(skip)]
+    was_down: bool,
+    #[serde(skip)]
+    toggled: bool,
+}
+
+impl Default for Keybind {
+    fn default() -> Self {
+        Self::unbound()
+    }
+}
+
+impl Keybind {
+    /// A keybind for `key` that is active while held
+    pub fn key(key: Key) -> Self {
+        Self { key: Some(key), ..Self::unbound() }
+    }
+
+    /// A keybind for `button` that is active while held
+    pub fn mouse(button: MouseButton) -> Self {
+        Self { mouse_button: Some(button), ..Self::unbound() }
+    }
+
+    /// A keybind without any keys, which is never active in `Hold` or `Toggle` mode
+    pub fn unbound() -> Self {
+        Self { key: None, mouse_button: None, mode: KeybindMode::Hold, modifiers: Modifiers::default(), was_down: false, toggled: false }
+    }
+
+    pub fn with_modifiers(mut self, modifiers: Modifiers) -> Self {
+        self.modifiers = modifiers;
+        self
+    }
+
+    pub fn with_mode(mut self, mode: KeybindMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn is_bound(&self) -> bool {
+        self.key.is_some() || self.mouse_button.is_some()
+    }
+
+    /// Whether pressing one of the keybinds would also trigger the other. Holding extra keys or
+    /// modifiers doesn't stop a keybind, so `X` conflicts with `Ctrl+X` and with `X+Mouse1`
+    pub fn conflicts_with(&self, other: &Keybind) -> bool {
+        let uses_keys = |keybind: &Keybind| keybind.is_bound() && matches!(keybind.mode, KeybindMode::Hold | KeybindMode::Toggle);
+        uses_keys(self) && uses_keys(other) && (self.is_part_of(other) || other.is_part_of(self))
+    }
+
+    /// Whether everything `self` needs held is also held for `other`
+    fn is_part_of(&self, other: &Keybind) -> bool {
+        self.key.map_or(true, |key| other.key == Some(key))
+            && self.mouse_button.map_or(true, |button| other.mouse_button == Some(button))
+            && self.modifiers.satisfied_by(other.modifiers)
+    }
+
+    /// Whether the keys and modifiers are held this frame, regardless of the mode
+    pub fn is_down(&self, input: &Input) -> bool {
+        self.is_bound()
+            && self.key.map_or(true, |key| input.is_key_down(key))
+            && self.mouse_button.map_or(true, |button| input.is_mouse_down(button))
+            && self.modifiers.satisfied_by(input.modifiers())
+    }
+
+    /// Whether the keybind is active according to its mode, usually with `RenderContext::input`.
+    /// Should be called every frame so toggles see every press. Calling it more than once a frame is fine
+    pub fn is_active(&mut self, input: &Input) -> bool {
+        let down = self.is_down(input);
+        self.update(down)
+    }
+
+    /// Like `is_active`, with whether the keys are held given instead of checked
+    pub fn update(&mut self, down: bool) -> bool {
+        if down && !self.was_down {
+            self.toggled = !self.toggled;
+        }
+        self.was_down = down;
+
+        match self.mode {
+            KeybindMode::Hold => down,
+            KeybindMode::Toggle => self.toggled,
+            KeybindMode::Always => true,
+            KeybindMode::Never => false,
+        }
+    }
+}
+
+/// Only the binding is compared, not whether it is currently toggled
+impl PartialEq for Keybind {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key && self.mouse_button == other.mouse_button && self.modifiers == other.modifiers && self.mode == other.mode
+    }
+}
+
+impl Eq for Keybind {}
+
+impl Hash for Keybind {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key.hash(state);
+        self.mouse_button.hash(state);
+        self.modifiers.hash(state);
+        self.mode.hash(state);
+    }
+}
+
+/// Formats like `Ctrl+Shift+X`, or `None` when nothing is bound
+impl std::fmt::Display for Keybind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !self.is_bound() {
+            return write!(f, "None");
+        }
+
+        let mut parts = Vec::new();
+        if self.modifiers.ctrl {
+            parts.push("Ctrl".to_string());
+        }
+        if self.modifiers.shift {
+            parts.push("Shift".to_string());
         }
+        if self.modifiers.alt {
+            parts.push("Alt".to_string());
+        }
+        if let Some(key) = self.key {
+            parts.push(key.display_name().into_owned());
+        }
+        if let Some(button) = self.mouse_button {
+            parts.push(button.name().to_string());
+        }
+        write!(f, "{}", parts.join("+"))
+    }
+}
 
-        if handled {
-            *state = Idle;
+enum KeybindEditState {
+    Idle,
+    Listening(KeybindListener),
+}
+
+/// What a [`KeybindListener`] heard
+#[derive(Debug, Clone, PartialEq)]
+enum Listened {
+    Cancelled,
+    Bound(Keybind),
+}
+
+/// Waits for a key or mouse button to be pressed while listening for a new keybind
+#[derive(Debug, Clone, Default, PartialEq)]
+struct KeybindListener {
+    /// Which keys and buttons were already down, so only new presses get bound
+    keys: Vec<Key>,
+    buttons: Vec<MouseButton>,
+    /// A modifier that went down, which is bound on its own if it is released before any other key
+    modifier: Option<Key>,
+}
+
+impl KeybindListener {
+    fn new(keys: Vec<Key>, buttons: Vec<MouseButton>) -> Self {
+        Self { keys, buttons, modifier: None }
+    }
+
+    /// Checks the keys and buttons held this frame. Escape cancels, other keys are bound with
+    /// `modifiers`. The left mouse button is skipped while `ignore_left` is set, so clicking the
+    /// widget itself doesn't bind it
+    fn update(&mut self, keys: Vec<Key>, buttons: Vec<MouseButton>, modifiers: Modifiers, ignore_left: bool) -> Option<Listened> {
+        let pressed_key = keys.iter().copied()
+            .find(|key| !key.is_modifier() && !self.keys.contains(key));
+        let pressed_button = buttons.iter().copied()
+            .find(|&button| !self.buttons.contains(&button) && (!ignore_left || button != MouseButton::Left));
+        if self.modifier.is_none() {
+            self.modifier = keys.iter().copied().find(|key| key.is_modifier() && !self.keys.contains(key));
+        }
+        let released_modifier = self.modifier.filter(|modifier| !keys.contains(modifier));
+        self.keys = keys;
+        self.buttons = buttons;
+
+        match (pressed_key, pressed_button, released_modifier) {
+            (Some(Key::Escape), _, _) => Some(Listened::Cancelled),
+            (Some(key), _, _) => Some(Listened::Bound(Keybind::key(key).with_modifiers(modifiers))),
+            (None, Some(button), _) => Some(Listened::Bound(Keybind::mouse(button).with_modifiers(modifiers))),
+            (None, None, Some(modifier)) => Some(Listened::Bound(Keybind::key(modifier).with_modifiers(modifiers))),
+            (None, None, None) => None,
         }
     }
 }
 
-/// Translate keycode into text, and also filter out keys not usable (arrows, escape)
-fn vkey_to_string(key: i32) -> String {
-    use winapi::um::winuser::*;
-    let mut text = match key {
-        VK_LBUTTON => "LBUTTON",
-        VK_RBUTTON => "RBUTTON",
-        VK_MBUTTON => "MBUTTON",
-        VK_XBUTTON1 => "XBUTTON1",
-        VK_XBUTTON2 => "XBUTTON2",
-        VK_BACK => "BACK",
-        VK_TAB => "TAB",
-        VK_CLEAR => "CLEAR",
-        VK_RETURN => "RETURN",
-        VK_SHIFT => "SHIFT",
-        VK_CONTROL => "CONTROL",
-        VK_MENU => "MENU",
-        VK_PAUSE => "PAUSE",
-        VK_CAPITAL => "CAPITAL",
-        VK_SPACE => "SPACE",
-        VK_PRIOR => "PGUP",
-        VK_NEXT => "PGDOWN",
-        VK_END => "END",
-        VK_HOME => "HOME",
-        VK_PRINT => "PRINT",
-        VK_EXECUTE => "EXECUTE",
-        VK_SNAPSHOT => "SNAPSHOT",
-        VK_INSERT => "INSERT",
-        VK_DELETE => "DELETE",
-        VK_HELP => "HELP",
-        0x30 => "0",
-        0x31 => "1",
-        0x32 => "2",
-        0x33 => "3",
-        0x34 => "4",
-        0x35 => "5",
-        0x36 => "6",
-        0x37 => "7",
-        0x38 => "8",
-        0x39 => "9",
-        0x41 => "A",
-        0x42 => "B",
-        0x43 => "C",
-        0x44 => "D",
-        0x45 => "E",
-        0x46 => "F",
-        0x47 => "G",
-        0x48 => "H",
-        0x49 => "I",
-        0x4A => "J",
-        0x4B => "K",
-        0x4C => "L",
-        0x4D => "M",
-        0x4E => "N",
-        0x4F => "O",
-        0x50 => "P",
-        0x51 => "Q",
-        0x52 => "R",
-        0x53 => "S",
-        0x54 => "T",
-        0x55 => "U",
-        0x56 => "V",
-        0x57 => "W",
-        0x58 => "X",
-        0x59 => "Y",
-        0x5A => "Z",
-        VK_LWIN => "LWIN",
-        VK_RWIN => "RWIN",
-        VK_APPS => "APPS",
-        VK_SLEEP => "SLEEP",
-        VK_NUMPAD0 => "NUMPAD0",
-        VK_NUMPAD1 => "NUMPAD1",
-        VK_NUMPAD2 => "NUMPAD2",
-        VK_NUMPAD3 => "NUMPAD3",
-        VK_NUMPAD4 => "NUMPAD4",
-        VK_NUMPAD5 => "NUMPAD5",
-        VK_NUMPAD6 => "NUMPAD6",
-        VK_NUMPAD7 => "NUMPAD7",
-        VK_NUMPAD8 => "NUMPAD8",
-        VK_NUMPAD9 => "NUMPAD9",
-        VK_MULTIPLY => "MULTIPLY",
-        VK_ADD => "ADD",
-        VK_SEPARATOR => "SEPARATOR",
-        VK_SUBTRACT => "SUBTRACT",
-        VK_DECIMAL => "DECIMAL",
-        VK_DIVIDE => "DIVIDE",
-        VK_F1 => "F1",
-        VK_F2 => "F2",
-        VK_F3 => "F3",
-        VK_F4 => "F4",
-        VK_F5 => "F5",
-        VK_F6 => "F6",
-        VK_F7 => "F7",
-        VK_F8 => "F8",
-        VK_F9 => "F9",
-        VK_F10 => "F10",
-        VK_F11 => "F11",
-        VK_F12 => "F12",
-        VK_NUMLOCK => "NUMLOCK",
-        VK_SCROLL => "SCROLL",
-        _ => "",  // we can't have a ref making a number to a string so we have to do it somwhere else
-    }.to_string();
-    if text.is_empty() {
-        text = key.to_string();
-    }
-    text
-}
\ No newline at end of file
+/// A button that binds the next key or mouse button pressed (with the modifiers held at the time),
+/// and a combo box for the mode. A modifier on its own is bound when it is released. Escape cancels.
+/// The button only shows the keybind if `title` is empty. Like imgui ids, the state is kept per
+/// `title` within the current id stack, so use `push_id` around keybinds with the same title
+pub fn keybind_edit(ui: &::imgui::Ui, render_state: &mut RenderState, render_context: &RenderContext, title: &ImStr, keybind: &mut Keybind) {
+    // Safety: widgets are only built inside a window, which is what imgui hashes the id stack of
+    let id = unsafe { sys::igGetIDStr(title.as_ptr()) };
+    keybind_edit_with_id(ui, render_state, render_context, &format!("keybind_edit:{:08X}", id), title, keybind);
+}
+
+/// `keybind_edit` with the state stored under `id` instead, e.g. for a list of keybinds that can
+/// be reordered
+pub fn keybind_edit_with_id(
+    ui: &::imgui::Ui,
+    render_state: &mut RenderState,
+    render_context: &RenderContext,
+    id: &str,
+    title: &ImStr,
+    keybind: &mut Keybind,
+) {
+    use KeybindEditState::*;
+    let state = render_state.get_or_replace_with(&StateKey::dynamic(id), || KeybindEditState::Idle);
+    let id_token = ui.push_id(id);
+    // Input events tell apart keys that share a virtual key code, like enter and numpad enter
+    let input = &render_context.input;
+
+    let label = match state {
+        Listening(..) => im_str!("Press key").to_owned(),
+        Idle if title.to_str().is_empty() => im_str!("{}", keybind),
+        Idle => im_str!("{}: {}", title, keybind),
+    };
+    if ui.button(&label, [160.0, 20.0]) {
+        *state = match state {
+            Idle => Listening(KeybindListener::new(keys_down(input), mouse_buttons_down(input))),
+            Listening(..) => Idle,
+        };
+    }
+    let hovered = ui.is_item_hovered();
+
+    if let Listening(listener) = state {
+        match listener.update(keys_down(input), mouse_buttons_down(input), input.modifiers(), hovered) {
+            Some(Listened::Cancelled) => *state = Idle,
+            Some(Listened::Bound(bound)) => {
+                *keybind = bound.with_mode(keybind.mode);
+                *state = Idle;
+            }
+            None => {}
+        }
+    }
+
+    ui.same_line(0.0);
+    ui.set_next_item_width(80.0);
+    ComboBox::new(im_str!("##mode"))
+        .preview_value(&ImString::new(keybind.mode.name()))
+        .build(ui, || {
+            for &mode in &KeybindMode::ALL {
+                if Selectable::new(&ImString::new(mode.name())).selected(mode == keybind.mode).build(ui) {
+                    keybind.mode = mode;
+                }
+            }
+        });
+
+    id_token.pop(ui);
+}
+
+/// Keys held or pressed this frame, so keys tapped within a single frame aren't missed. Sorted, so
+/// the same key wins when several go down at once
+fn keys_down(input: &Input) -> Vec<Key> {
+    let mut keys: Vec<Key> = input.keys_down().chain(input.keys_pressed()).collect();
+    keys.sort();
+    keys.dedup();
+    keys
+}
+
+fn mouse_buttons_down(input: &Input) -> Vec<MouseButton> {
+    MouseButton::ALL.iter().copied()
+        .filter(|&button| input.is_mouse_down(button) || input.is_mouse_pressed(button))
+        .collect()
+}
+
+/// The name of a Windows virtual key code, or the number if no key has it
+fn vkey_name(vkey: i32) -> String {
+    if let Some(button) = MouseButton::from_vkey(vkey) {
+        return button.name().to_string();
+    }
+    match Key::from_vkey(vkey) {
+        Some(key) => key.display_name().into_owned(),
+        None => vkey.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Whether `keybind` is active in each frame, given whether its keys are held in that frame
+    fn run(keybind: &mut Keybind, frames: &[bool]) -> Vec<bool> {
+        frames.iter().map(|&down| keybind.update(down)).collect()
+    }
+
+    #[test]
+    fn hold_is_active_while_down() {
+        let mut keybind = Keybind::key(Key::X);
+        assert_eq!(run(&mut keybind, &[false, true, true, false]), vec![false, true, true, false]);
+    }
+
+    #[test]
+    fn toggle_flips_on_every_press() {
+        let mut keybind = Keybind::key(Key::X).with_mode(KeybindMode::Toggle);
+        assert_eq!(
+            run(&mut keybind, &[true, true, false, false, true, false]),
+            vec![true, true, true, true, false, false],
+        );
+    }
+
+    #[test]
+    fn always_and_never_ignore_the_keys() {
+        let frames = [false, true, false];
+        assert_eq!(run(&mut Keybind::key(Key::X).with_mode(KeybindMode::Always), &frames), vec![true; 3]);
+        assert_eq!(run(&mut Keybind::key(Key::X).with_mode(KeybindMode::Never), &frames), vec![false; 3]);
+    }
+
+    #[test]
+    fn fake_input_drives_keybinds() {
+        let (mut input, fake) = (Input::new(), crate::input::FakeInput::new());
+        let mut keybind = Keybind::key(Key::F).with_modifiers(Modifiers { shift: true, ..Modifiers::default() });
+        let mut frame = |keybind: &mut Keybind| {
+            input.end_frame();
+            input.poll(&mut fake.clone());
+            keybind.is_active(&input)
+        };
+
+        fake.press(Key::F);
+        assert!(!frame(&mut keybind));
+        fake.press(Key::RShift);
+        assert!(frame(&mut keybind));
+        fake.release(Key::F);
+        assert!(!frame(&mut keybind));
+    }
+
+    #[test]
+    fn serde_round_trip() {
+        let keybind = Keybind::key(Key::F5)
+            .with_modifiers(Modifiers { ctrl: true, shift: false, alt: true })
+            .with_mode(KeybindMode::Toggle);
+        let json = serde_json::to_string(&keybind).unwrap();
+        assert_eq!(serde_json::from_str::<Keybind>(&json).unwrap(), keybind);
+
+        let mouse = Keybind::mouse(MouseButton::X2);
+        let toml = toml::to_string(&mouse).unwrap();
+        assert_eq!(toml::from_str::<Keybind>(&toml).unwrap(), mouse);
+    }
+
+    #[test]
+    fn toggle_state_is_not_serialized() {
+        let mut keybind = Keybind::key(Key::X).with_mode(KeybindMode::Toggle);
+        keybind.update(true);
+        let json = serde_json::to_string(&keybind).unwrap();
+        let mut loaded: Keybind = serde_json::from_str(&json).unwrap();
+        assert!(!loaded.update(false));
+    }
+
+    #[test]
+    fn listener_binds_new_keys_with_modifiers() {
+        let ctrl = Modifiers { ctrl: true, ..Modifiers::default() };
+        let mut listener = KeybindListener::new(vec![Key::A], Vec::new());
+        assert_eq!(listener.update(vec![Key::A], Vec::new(), Modifiers::default(), false), None);
+        assert_eq!(listener.update(vec![Key::A, Key::LControl], Vec::new(), ctrl, false), None);
+        assert_eq!(
+            listener.update(vec![Key::A, Key::LControl, Key::X], Vec::new(), ctrl, false),
+            Some(Listened::Bound(Keybind::key(Key::X).with_modifiers(ctrl))),
+        );
+    }
+
+    #[test]
+    fn listener_binds_a_released_lone_modifier() {
+        let mut listener = KeybindListener::new(Vec::new(), Vec::new());
+        let shift = Modifiers { shift: true, ..Modifiers::default() };
+        assert_eq!(listener.update(vec![Key::LShift], Vec::new(), shift, false), None);
+        assert_eq!(
+            listener.update(Vec::new(), Vec::new(), Modifiers::default(), false),
+            Some(Listened::Bound(Keybind::key(Key::LShift))),
+        );
+    }
+
+    #[test]
+    fn listener_ignores_clicks_on_the_widget() {
+        let mut listener = KeybindListener::new(Vec::new(), Vec::new());
+        assert_eq!(listener.update(Vec::new(), vec![MouseButton::Left], Modifiers::default(), true), None);
+        assert_eq!(
+            listener.update(Vec::new(), vec![MouseButton::Left, MouseButton::X1], Modifiers::default(), true),
+            Some(Listened::Bound(Keybind::mouse(MouseButton::X1))),
+        );
+
+        let mut listener = KeybindListener::new(Vec::new(), Vec::new());
+        assert_eq!(
+            listener.update(Vec::new(), vec![MouseButton::Left], Modifiers::default(), false),
+            Some(Listened::Bound(Keybind::mouse(MouseButton::Left))),
+        );
+    }
+
+    #[test]
+    fn listener_cancels_on_escape() {
+        let mut listener = KeybindListener::new(Vec::new(), Vec::new());
+        assert_eq!(listener.update(vec![Key::Escape], Vec::new(), Modifiers::default(), false), Some(Listened::Cancelled));
+    }
+}
@@ -1,9 +1,24 @@
 mod fonts;
 mod renderer;
+mod state;
 pub mod themes;
+pub mod theme_editor;
 mod clipboard;
 pub mod keybind;
+pub mod bindings;
 pub mod util;
 pub mod overlay;
+pub mod software;
+pub mod image;
+pub mod transition;
+mod text_effects;
 
-pub use renderer::*;
\ No newline at end of file
+pub use renderer::*;
+pub use state::{RenderState, StateKey};
+pub use fonts::{Fonts, FontData, FontDescriptor, GlyphRanges, MergedFont};
+/// imgui only allows one context to exist at a time, so tests that create one hold this lock
+#[cfg(test)]
+pub(crate) fn lock_context() -> std::sync::MutexGuard<'static, ()> {
+    static LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+    LOCK.lock().unwrap_or_else(|e| e.into_inner())
+}
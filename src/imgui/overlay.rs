@@ -2,6 +2,19 @@ use std::collections::HashMap;
 use crate::imgui::RenderContext;
 use ::imgui::*;
 use crate::types::*;
+use crate::draw::{Draw, align_point, align_points, rotated_rect};
+use crate::geometry::{is_convex, triangulate, flatten_quadratic, flatten_cubic, flatten_arc, bounds, rounded_rect, subdivide};
+use crate::color::Color;
+use crate::text::{text_effect, TextEffect, GlyphEffect};
+use super::text_effects::{EffectGlyphKey, EffectKind, MAX_EFFECT_RADIUS};
+
+/// Longest edge in pixels of the triangles that gradients which aren't linear are split into
+const GRADIENT_STEP: f32 = 8.0;
+/// Tolerance used for curves the user doesn't control, such as the corners of rounded boxes
+const CURVE_TOLERANCE: f32 = 0.25;
+
+/// Name of the window every overlay draws into
+const OVERLAY_WINDOW: &str = "##overlay";
 
 /// Represents a frame that be can be drawn on
 pub struct ImguiOverlay<'a, 'b, 'ui> {
@@ -22,11 +35,20 @@ impl<'a, 'b, 'ui> ImguiOverlay<'a, 'b, 'ui> {
     ) -> Self {
         let style_token = ui.push_style_vars(&[StyleVar::WindowBorderSize(0.0), StyleVar::WindowPadding([0.0, 0.0])]);
         let color_token = ui.push_style_color(StyleColor::WindowBg, [0.0, 0.0, 0.0, 0.0]);
-        let window_token = Window::new(im_str!("##overlay"))
+        let window_token = Window::new(&ImString::new(OVERLAY_WINDOW))
             .flags(WindowFlags::NO_TITLE_BAR | WindowFlags::NO_INPUTS)
             .position([0.0, 0.0], Condition::Always)
             .size(ui.io().display_size, Condition::Always)
             .begin(&ui).unwrap();
+
+        // The renderer keeps these in place while the rest of the ui slides
+        let draw_list = unsafe { sys::igGetWindowDrawList() } as *const sys::ImDrawList;
+        let mut overlay_draw_lists = context.overlay_draw_lists.borrow_mut();
+        if !overlay_draw_lists.contains(&draw_list) {
+            overlay_draw_lists.push(draw_list);
+        }
+        drop(overlay_draw_lists);
+
         Self { context, ui, style_token, color_token, window_token, align_to_pixel }
     }
 
@@ -53,92 +75,389 @@ impl ImguiOverlay<'_, '_, '_> {
         self.ui.get_window_draw_list()
     }
 
-    pub fn draw_line(&self, mut p1: impl Into<[f32; 2]>, mut p2: impl Into<[f32; 2]>, options: LineOptions) {
-        let mut p1 = p1.into();
-        let mut p2 = p2.into();
-        if self.align_to_pixel {
-            p1[0] = p1[0].round();
-            p1[1] = p1[1].round();
-            p2[0] = p2[0].round();
-            p2[1] = p2[1].round();
+    /// The raw draw list of the overlay window, for primitives `DrawListMut` doesn't expose
+    fn get_raw_draw_list(&self) -> *mut sys::ImDrawList {
+        unsafe { sys::igGetWindowDrawList() }
+    }
+
+    /// Writes untextured triangles straight into the draw list with a color per point.
+    /// Unlike `AddConvexPolyFilled` this has no anti aliased fringe, so it can fill any triangulation
+    fn fill_triangles(&self, points: &[[f32; 2]], triangles: &[[usize; 3]], color: impl Fn(usize) -> Color) {
+        if triangles.is_empty() {
+            return;
+        }
+
+        let draw_list = self.get_raw_draw_list();
+        unsafe {
+            sys::ImDrawList_PrimReserve(draw_list, (triangles.len() * 3) as _, points.len() as _);
+
+            let uv = (*(*draw_list)._Data).TexUvWhitePixel;
+            let base = (*draw_list)._VtxCurrentIdx;
+            for triangle in triangles {
+                for &i in triangle {
+                    sys::ImDrawList_PrimWriteIdx(draw_list, (base + i as u32) as _);
+                }
+            }
+            for (i, point) in points.iter().enumerate() {
+                sys::ImDrawList_PrimWriteVtx(draw_list, (*point).into(), uv, color(i).into());
+            }
+        }
+    }
+
+    /// Fills a closed shape, using imgui's anti aliased fill when the shape is convex
+    fn fill_polygon(&self, points: &[[f32; 2]], color: Color) {
+        if is_convex(points) {
+            let points: Vec<sys::ImVec2> = points.iter().map(|&point| point.into()).collect();
+            unsafe {
+                sys::ImDrawList_AddConvexPolyFilled(self.get_raw_draw_list(), points.as_ptr(), points.len() as _, color.into());
+            }
+        } else {
+            self.fill_triangles(points, &triangulate(points), |_| color);
+        }
+    }
+
+    /// Fills a closed shape with a color per point picked by `fill`
+    fn fill_gradient(&self, points: &[[f32; 2]], color: Color, fill: &Fill) {
+        let (min, max) = bounds(points);
+        let size = [(max[0] - min[0]).max(f32::EPSILON), (max[1] - min[1]).max(f32::EPSILON)];
+
+        let triangles = triangulate(points);
+        let (points, triangles) = match fill.needs_subdivision() {
+            true => subdivide(points, &triangles, GRADIENT_STEP),
+            false => (points.to_vec(), triangles),
+        };
+        self.fill_triangles(&points, &triangles, |i| {
+            let relative = [(points[i][0] - min[0]) / size[0], (points[i][1] - min[1]) / size[1]];
+            fill.color_at(color, relative)
+        });
+    }
+
+    /// Draws `text` with `effect` behind it. The text goes into the upper of two draw list channels
+    /// first, so the effect can be drawn below it from the positions and texture coordinates of its glyphs
+    fn add_text_with_effect(&self, position: [f32; 2], text: &str, color: Color, effect: Option<TextEffect>) {
+        let effect = match effect {
+            Some(effect) => effect,
+            None => {
+                self.get_draw_list().add_text(position, color, text);
+                return;
+            }
+        };
+
+        let draw_list = self.get_raw_draw_list();
+        unsafe {
+            sys::ImDrawList_ChannelsSplit(draw_list, 2);
+            sys::ImDrawList_ChannelsSetCurrent(draw_list, 1);
+            let vtx_start = (*draw_list).VtxBuffer.Size as usize;
+            self.get_draw_list().add_text(position, color, text);
+            let vtx_count = (*draw_list).VtxBuffer.Size as usize - vtx_start;
+            let vertices = std::slice::from_raw_parts((*draw_list).VtxBuffer.Data.add(vtx_start), vtx_count).to_vec();
+
+            sys::ImDrawList_ChannelsSetCurrent(draw_list, 0);
+            self.add_glyph_effect(draw_list, &vertices, effect);
+            sys::ImDrawList_ChannelsMerge(draw_list);
+        }
+    }
+
+    /// Draws `effect` for every glyph quad in `vertices`. Outlines and blurs come from
+    /// [`TextEffects`](super::text_effects::TextEffects), and are left out for a frame while it
+    /// builds the glyphs it doesn't have yet
+    unsafe fn add_glyph_effect(&self, draw_list: *mut sys::ImDrawList, vertices: &[sys::ImDrawVert], effect: TextEffect) {
+        let atlas = (*sys::igGetIO()).Fonts;
+        let atlas_size = [(*atlas).TexWidth as f32, (*atlas).TexHeight as f32];
+        let mut text_effects = self.context.text_effects.borrow_mut();
+
+        let mut texture_id = None;
+        let mut missing = false;
+        let mut quads = Vec::with_capacity(vertices.len() / 4);
+        // imgui writes every glyph as a quad of top left, top right, bottom right and bottom left
+        for quad in vertices.chunks_exact(4) {
+            let (pos_min, pos_max) = ([quad[0].pos.x, quad[0].pos.y], [quad[2].pos.x, quad[2].pos.y]);
+            let (uv_min, uv_max) = ([quad[0].uv.x, quad[0].uv.y], [quad[2].uv.x, quad[2].uv.y]);
+            let radius = match effect.glyphs {
+                GlyphEffect::Plain => {
+                    quads.push((pos_min, pos_max, uv_min, uv_max));
+                    continue;
+                }
+                GlyphEffect::Outline(radius) | GlyphEffect::Blur(radius) => radius,
+            };
+
+            // Texels of the atlas per pixel, which differs from 1 when the atlas is scaled
+            let scale = (uv_max[0] - uv_min[0]) * atlas_size[0] / (pos_max[0] - pos_min[0]).max(f32::EPSILON);
+            let radius = ((radius * scale).round() as u32).clamp(1, MAX_EFFECT_RADIUS);
+            let key = EffectGlyphKey {
+                rect: [
+                    (uv_min[0] * atlas_size[0]).round() as u32,
+                    (uv_min[1] * atlas_size[1]).round() as u32,
+                    (uv_max[0] * atlas_size[0]).round() as u32,
+                    (uv_max[1] * atlas_size[1]).round() as u32,
+                ],
+                effect: match effect.glyphs {
+                    GlyphEffect::Outline(_) => EffectKind::Outline(radius),
+                    _ => EffectKind::Blur(radius),
+                },
+            };
+            // Keeps going after a missing glyph so every missing glyph gets queued this frame
+            match text_effects.get(key) {
+                Some(glyph) => {
+                    let padding = glyph.padding as f32 / scale;
+                    texture_id = Some(glyph.texture_id);
+                    quads.push((
+                        [pos_min[0] - padding, pos_min[1] - padding],
+                        [pos_max[0] + padding, pos_max[1] + padding],
+                        glyph.uv_min,
+                        glyph.uv_max,
+                    ));
+                }
+                None => missing = true,
+            }
+        }
+        if missing || quads.is_empty() {
+            return;
+        }
+
+        if let Some(texture_id) = texture_id {
+            sys::ImDrawList_PushTextureID(draw_list, texture_id.id() as *mut _);
+        }
+        let [dx, dy] = effect.offset;
+        let color: u32 = effect.color.into();
+        sys::ImDrawList_PrimReserve(draw_list, (quads.len() * 6) as _, (quads.len() * 4) as _);
+        for (pos_min, pos_max, uv_min, uv_max) in quads {
+            sys::ImDrawList_PrimRectUV(
+                draw_list,
+                [pos_min[0] + dx, pos_min[1] + dy].into(),
+                [pos_max[0] + dx, pos_max[1] + dy].into(),
+                uv_min.into(),
+                uv_max.into(),
+                color,
+            );
+        }
+        if texture_id.is_some() {
+            sys::ImDrawList_PopTextureID(draw_list);
+        }
+    }
+
+    fn add_polyline(&self, points: &[[f32; 2]], color: Color, closed: bool, thickness: f32) {
+        let points: Vec<sys::ImVec2> = points.iter().map(|&point| point.into()).collect();
+        unsafe {
+            sys::ImDrawList_AddPolyline(self.get_raw_draw_list(), points.as_ptr(), points.len() as _, color.into(), closed, thickness);
         }
+    }
+}
+
+impl Draw for ImguiOverlay<'_, '_, '_> {
+    fn draw_line(&self, p1: impl Into<[f32; 2]>, p2: impl Into<[f32; 2]>, options: LineOptions) {
+        let p1 = align_point(p1.into(), self.align_to_pixel);
+        let p2 = align_point(p2.into(), self.align_to_pixel);
 
         self.get_draw_list()
-            .add_line(p1.into(), p2.into(), options.color)
+            .add_line(p1, p2, options.color)
             .thickness(options.width)
             .build()
     }
 
-    pub fn draw_box(&self, mut p1: impl Into<[f32; 2]>, mut p2: impl Into<[f32; 2]>, options: BoxOptions) {
-        let mut p1 = p1.into();
-        let mut p2 = p2.into();
-        if self.align_to_pixel {
-            p1[0] = p1[0].round();
-            p1[1] = p1[1].round();
-            p2[0] = p2[0].round();
-            p2[1] = p2[1].round();
+    fn draw_box(&self, p1: impl Into<[f32; 2]>, p2: impl Into<[f32; 2]>, options: BoxOptions) {
+        let p1 = align_point(p1.into(), self.align_to_pixel);
+        let p2 = align_point(p2.into(), self.align_to_pixel);
+
+        if options.filled && !options.fill.is_solid() {
+            let points = rounded_rect(p1, p2, options.rounding, CURVE_TOLERANCE);
+            self.fill_gradient(&points, options.color, &options.fill);
+            return;
         }
 
         self.get_draw_list()
-            .add_rect(p1.into(), p2.into(), options.color)
+            .add_rect(p1, p2, options.color)
             .thickness(options.width)
             .rounding(options.rounding)
             .filled(options.filled)
             .build()
     }
 
-    pub fn draw_text(&self, mut origin: impl Into<[f32; 2]>, text: &str, options: TextOptions) {
-        let mut origin = origin.into();
-        if self.align_to_pixel {
-            origin[0] = origin[0].round();
-            origin[1] = origin[1].round();
+    fn draw_text(&self, origin: impl Into<[f32; 2]>, text: &str, options: TextOptions) {
+        let origin = align_point(origin.into(), self.align_to_pixel);
+
+        let layout = self.layout_text(origin, text, &options);
+
+        // Missing fonts fall back to whatever font is current instead of failing the whole frame
+        let font_token = self.context.fonts.resolve(&options.font, options.font_size).map(|font| self.ui.push_font(font));
+
+        let effect = text_effect(&options, self.ui.current_font_size());
+        for line in &layout.lines {
+            let position = align_point(line.position, self.align_to_pixel);
+            self.add_text_with_effect(position, &line.text, options.color, effect);
         }
 
-        let text = unsafe { ImStr::from_ptr_unchecked(ImString::new(text).as_ptr()) };
+        if let Some(font_token) = font_token {
+            font_token.pop(&self.ui);
+        }
+    }
 
-        let font = *self.context.fonts.get(&options.font).unwrap();
+    fn draw_circle(&self, origin: impl Into<[f32; 2]>, radius: f32, options: CircleOptions) {
+        let origin = align_point(origin.into(), self.align_to_pixel);
 
-        let font_token = self.ui.push_font(font);
+        self.get_draw_list()
+            .add_circle(origin, radius, options.color)
+            .num_segments(options.segments)
+            .thickness(options.width)
+            .filled(options.filled)
+            .build()
+    }
 
-        let x = match options.centered_horizontal {
-            false => origin[0],
-            true => origin[0] - (self.ui.calc_text_size(text, false, 0.0)[0] / 2.0),
-        };
-        let y = match options.centered_vertical {
-            false => origin[1],
-            true => origin[1] - (self.ui.calc_text_size(text, false, 0.0)[0] / 2.0),
-        };
+    fn draw_polyline(&self, points: &[[f32; 2]], options: LineOptions) {
+        let points = align_points(points, self.align_to_pixel);
+        self.add_polyline(&points, options.color, false, options.width);
+    }
 
-        let draw_list = self.get_draw_list();
+    fn draw_polygon(&self, points: &[[f32; 2]], options: PolygonOptions) {
+        let points = align_points(points, self.align_to_pixel);
 
-        let draw = |color, offset: (f32, f32)| {
-            draw_list.add_text([x + offset.0, y + offset.1], color, text);
-        };
+        match options.filled {
+            true if !options.fill.is_solid() => self.fill_gradient(&points, options.color, &options.fill),
+            true => self.fill_polygon(&points, options.color),
+            false => self.add_polyline(&points, options.color, true, options.width),
+        }
+    }
 
-        let shadow_color = options.shadow_color;
-        match options.style {
-            TextStyle::Shadow => {
-                draw(shadow_color, (1.0, 1.0));
-            }
-            TextStyle::Outlined => {
-                draw(shadow_color, (1.0, 1.0));
-                draw(shadow_color, (1.0, -1.0));
-                draw(shadow_color, (-1.0, 1.0));
-                draw(shadow_color, (-1.0, -1.0));
-                draw(shadow_color, (0.0, 1.0));
-                draw(shadow_color, (0.0, -1.0));
-                draw(shadow_color, (1.0, 0.0));
-                draw(shadow_color, (-1.0, 0.0));
+    fn draw_quadratic_bezier(&self, p0: impl Into<[f32; 2]>, p1: impl Into<[f32; 2]>, p2: impl Into<[f32; 2]>, options: CurveOptions) {
+        let p0 = align_point(p0.into(), self.align_to_pixel);
+        let p1 = align_point(p1.into(), self.align_to_pixel);
+        let p2 = align_point(p2.into(), self.align_to_pixel);
+
+        let points = flatten_quadratic(p0, p1, p2, options.tolerance);
+        self.add_polyline(&points, options.color, false, options.width);
+    }
+
+    fn draw_cubic_bezier(&self, p0: impl Into<[f32; 2]>, p1: impl Into<[f32; 2]>, p2: impl Into<[f32; 2]>, p3: impl Into<[f32; 2]>, options: CurveOptions) {
+        let p0 = align_point(p0.into(), self.align_to_pixel);
+        let p1 = align_point(p1.into(), self.align_to_pixel);
+        let p2 = align_point(p2.into(), self.align_to_pixel);
+        let p3 = align_point(p3.into(), self.align_to_pixel);
+
+        let points = flatten_cubic(p0, p1, p2, p3, options.tolerance);
+        self.add_polyline(&points, options.color, false, options.width);
+    }
+
+    fn draw_arc(&self, center: impl Into<[f32; 2]>, radius: f32, start_angle: f32, end_angle: f32, options: ArcOptions) {
+        let center = align_point(center.into(), self.align_to_pixel);
+
+        let mut points = flatten_arc(center, radius, start_angle, end_angle, options.tolerance);
+        match options.filled {
+            true => {
+                points.push(center);
+                self.fill_polygon(&points, options.color);
             }
-            TextStyle::None => {}
+            false => self.add_polyline(&points, options.color, false, options.width),
         }
+    }
 
-        draw(options.color, (0.0, 0.0));
+    fn draw_image(&self, image: &ImageHandle, p1: impl Into<[f32; 2]>, p2: impl Into<[f32; 2]>, options: ImageOptions) {
+        let p1 = align_point(p1.into(), self.align_to_pixel);
+        let p2 = align_point(p2.into(), self.align_to_pixel);
 
-        font_token.pop(&self.ui);
+        let draw_list = self.get_draw_list();
+        if options.rounding > 0.0 {
+            draw_list
+                .add_image_rounded(image.texture_id, p1, p2, options.rounding)
+                .uv_min(options.uv_min)
+                .uv_max(options.uv_max)
+                .col(options.tint)
+                .build()
+        } else {
+            draw_list
+                .add_image(image.texture_id, p1, p2)
+                .uv_min(options.uv_min)
+                .uv_max(options.uv_max)
+                .col(options.tint)
+                .build()
+        }
+    }
+
+    fn draw_image_rotated(&self, image: &ImageHandle, center: impl Into<[f32; 2]>, size: [f32; 2], angle: f32, options: ImageOptions) {
+        let center = align_point(center.into(), self.align_to_pixel);
+        let [p1, p2, p3, p4] = rotated_rect(center, size, angle);
+        let [u1, v1] = options.uv_min;
+        let [u2, v2] = options.uv_max;
+
+        self.get_draw_list()
+            .add_image_quad(image.texture_id, p1, p2, p3, p4)
+            .uv([u1, v1], [u2, v1], [u2, v2], [u1, v2])
+            .col(options.tint)
+            .build()
     }
 
-    fn draw_circle(&self, _origin: [f32; 2], _radius: f32, _options: CircleOptions) {
-        unimplemented!()
+    fn push_clip_rect(&self, p1: impl Into<[f32; 2]>, p2: impl Into<[f32; 2]>) {
+        let p1 = align_point(p1.into(), self.align_to_pixel);
+        let p2 = align_point(p2.into(), self.align_to_pixel);
+
+        unsafe { sys::ImDrawList_PushClipRect(self.get_raw_draw_list(), p1.into(), p2.into(), true) }
+    }
+
+    fn pop_clip_rect(&self) {
+        unsafe { sys::ImDrawList_PopClipRect(self.get_raw_draw_list()) }
+    }
+
+    fn text_size(&self, text: &str, font: &Font, size: Option<u32>) -> [f32; 2] {
+        let font_token = self.context.fonts.resolve(font, size).map(|font| self.ui.push_font(font));
+        let size = self.ui.calc_text_size(&ImString::new(text), false, 0.0);
+        if let Some(font_token) = font_token {
+            font_token.pop(&self.ui);
+        }
+        size
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::imgui::lock_context;
+    use crate::imgui::image::RgbaImage;
+    use crate::imgui::software::HeadlessImgui;
+
+    fn draw_outlined_text(headless: &mut HeadlessImgui, thickness: f32) -> RgbaImage {
+        headless.render_frame(|ui, _, context| {
+            ImguiOverlay::build(ui, context, true, |overlay| {
+                overlay.draw_text([24.0, 20.0], "I", TextOptions {
+                    color: Color::WHITE,
+                    style: TextStyle::Outlined,
+                    shadow_color: Color::from_rgb(255, 0, 0),
+                    outline_thickness: Some(thickness),
+                    ..Default::default()
+                });
+            });
+        }).unwrap()
+    }
+
+    #[test]
+    fn thick_outlines_have_no_gaps() {
+        let _lock = lock_context();
+        let mut headless = HeadlessImgui::new(Context::create(), [64.0, 64.0]).unwrap();
+
+        // The outlined glyph is built between frames, so the first frame only has the text
+        let first = draw_outlined_text(&mut headless, 6.0);
+        assert!(first.data.chunks_exact(4).all(|pixel| pixel[0] == pixel[1]), "the first frame has an outline");
+
+        let image = draw_outlined_text(&mut headless, 6.0);
+        let ink: Vec<(i32, i32)> = (0..image.height)
+            .flat_map(|y| (0..image.width).map(move |x| (x, y)))
+            .filter(|&(x, y)| image.get_pixel(x, y)[1] > 128)
+            .map(|(x, y)| (x as i32, y as i32))
+            .collect();
+        assert!(!ink.is_empty());
+
+        // Every pixel up to a pixel short of the thickness away from the text is fully covered
+        for y in 0..image.height {
+            for x in 0..image.width {
+                let distance = ink.iter()
+                    .map(|&(ink_x, ink_y)| (((ink_x - x as i32).pow(2) + (ink_y - y as i32).pow(2)) as f32).sqrt())
+                    .fold(f32::MAX, f32::min);
+                if distance > 0.0 && distance <= 5.0 {
+                    let pixel = image.get_pixel(x, y);
+                    assert!(pixel[0] == 255 && pixel[3] == 255, "pixel ({}, {}) is {:?}", x, y, pixel);
+                }
+                if distance >= 8.0 {
+                    assert_eq!(image.get_pixel(x, y), [0, 0, 0, 0], "pixel ({}, {})", x, y);
+                }
+            }
+        }
     }
 }
@@ -1,20 +1,31 @@
 use crate::window;
 use imgui_winit_support::{WinitPlatform, HiDpiMode};
-use super::fonts::add_fonts;
+use super::fonts::{add_fonts, Fonts, FontDescriptor, AtlasScale};
 use winit::event_loop::EventLoop;
-use imgui_glium_renderer::Renderer;
-use imgui::{Context, Ui, FontSource, FontConfig, FontId};
+use imgui_glium_renderer::{Renderer, RendererError};
+use imgui::{Context, Ui, FontSource, FontConfig};
 use std::time::{Instant, Duration};
 use glutin::event::{Event, WindowEvent};
-use glium::Surface;
+use glium::{Frame, Surface};
 use glutin::event_loop::ControlFlow;
 use crate::window::WindowController;
 use std::marker::PhantomData;
-use std::collections::HashMap;
-use std::cell::RefCell;
-use crate::types::Font;
 use std::ptr::null;
 use std::mem;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use anyhow::Result;
+use glium::texture::{RawImage2d, Texture2d};
+use imgui_glium_renderer::Texture;
+use super::image::{Images, RgbaImage};
+use super::text_effects::TextEffects;
+use std::cell::RefCell;
+use super::themes::{Theme, ThemeWatcher};
+use super::transition::Transition;
+use crate::types::TransitionOptions;
+use crate::input::{Input, InputSource};
+use imgui::{DrawData, sys};
+use crate::types::ImageHandle;
 
 pub struct Imgui {
     pub event_loop: EventLoop<()>,
@@ -23,7 +34,13 @@ pub struct Imgui {
     pub platform: WinitPlatform,
     pub renderer: Renderer,
     pub controller: WindowController,
-    pub fonts: HashMap<Font, FontId>
+    pub fonts: Fonts,
+    pub atlas_scale: AtlasScale,
+    pub theme_watcher: Option<ThemeWatcher>,
+    /// How windows appear and disappear when `RenderContext::ui_open` changes
+    pub transition: TransitionOptions,
+    /// Sources of input events besides the event loop, polled before every frame
+    pub input_sources: Vec<Box<dyn InputSource>>,
 }
 
 impl Imgui {
@@ -47,8 +64,10 @@ impl Imgui {
 
         let hidpi_factor = platform.hidpi_factor();
 
-        // imgui.io_mut().font_global_scale = (hidpi_factor) as f32;
         let fonts = add_fonts(&mut imgui, 1.0);
+        // Rasterize fonts at the real pixel size of the monitor so they aren't blurry
+        let mut atlas_scale = AtlasScale::default();
+        atlas_scale.set_scale(&mut imgui, hidpi_factor as f32);
         let renderer = Renderer::init(&mut imgui, &display).expect("Unable to create imgui renderer");
 
         Self {
@@ -58,10 +77,48 @@ impl Imgui {
             platform,
             renderer,
             controller,
-            fonts
+            fonts,
+            atlas_scale,
+            theme_watcher: None,
+            transition: TransitionOptions::default(),
+            input_sources: default_input_sources(),
         }
     }
 
+    /// Adds a font to the atlas and rebuilds the font texture
+    pub fn load_font(&mut self, descriptor: FontDescriptor) -> Result<()> {
+        self.fonts.add(&mut self.imgui, descriptor, self.atlas_scale.scale())?;
+        self.atlas_scale.sync(&mut self.imgui);
+        self.renderer.reload_font_texture(&mut self.imgui)?;
+        Ok(())
+    }
+
+    /// Uploads `image` to the GPU so it can be drawn with `draw_image`. Once the overlay runs, images
+    /// are loaded with `RenderContext::images` instead
+    pub fn load_image(&mut self, image: RgbaImage) -> Result<ImageHandle> {
+        upload_image(&self.display, &mut self.renderer, image)
+    }
+
+    /// Decodes a png and uploads it to the GPU
+    pub fn load_image_png(&mut self, bytes: &[u8]) -> Result<ImageHandle> {
+        self.load_image(RgbaImage::from_png(bytes)?)
+    }
+
+    /// Reads a png file and uploads it to the GPU
+    pub fn load_image_file(&mut self, path: impl AsRef<Path>) -> Result<ImageHandle> {
+        self.load_image(RgbaImage::open(path)?)
+    }
+
+    /// Applies the theme file at `path` when the overlay starts and again every time the file is saved
+    pub fn watch_theme(&mut self, path: impl Into<PathBuf>) {
+        self.theme_watcher = Some(ThemeWatcher::new(path));
+    }
+
+    /// Adds events from `source` to `RenderContext::input`, e.g. a `FakeInput` to script the ui
+    pub fn add_input_source(&mut self, source: impl InputSource + 'static) {
+        self.input_sources.push(Box::new(source));
+    }
+
     /// Runs the ui with a state that gets inited with Default
     pub fn run(self, mut run_ui: impl FnMut(&mut Ui, &mut RenderState, &mut RenderContext) + 'static) -> ! {
         let Imgui {
@@ -72,15 +129,21 @@ impl Imgui {
             mut renderer,
             mut controller,
             fonts,
+            mut atlas_scale,
+            mut theme_watcher,
+            transition,
+            mut input_sources,
             ..
         } = self;
 
-        let mut render_context = RenderContext { ui_open: true, bypass_screenshots: true, fonts };
+        let mut render_context = RenderContext::new(fonts);
+        render_context.bypass_screenshots = true;
         let mut render_context_init = false;
-        let mut fade_start = None;
-        let fade_time = Duration::from_millis(1000);
+        let mut transition = Transition::new(transition, render_context.ui_open);
+        let mut clickthrough = None;
 
         let mut state = RenderState::new();
+        let mut theme: Option<Theme> = None;
 
         let mut last_frame = Instant::now();
 
@@ -100,17 +163,79 @@ impl Imgui {
             Event::RedrawRequested(_) => {
                 controller.update();
 
-                // imgui.style_mut().alpha = fade_animation(&fade_start, fade_time, render_context.ui_open);
+                let mut theme_changed = false;
+                match theme_watcher.as_mut().and_then(|watcher| watcher.poll()) {
+                    Some(Ok(new_theme)) => {
+                        new_theme.apply(&mut imgui);
+                        new_theme.load_fonts(&mut render_context.fonts);
+                        theme = Some(new_theme);
+                        theme_changed = true;
+                    }
+                    Some(Err(e)) => log::error!("Could not load theme: {:?}", e),
+                    None => {}
+                }
+                for theme in render_context.pending_themes.drain(..) {
+                    theme.apply(&mut imgui);
+                }
+
+                // Fonts can only be added to the atlas between frames
+                let fonts_changed = render_context.fonts.has_pending();
+                if fonts_changed {
+                    render_context.fonts.add_pending(&mut imgui, atlas_scale.scale());
+                    atlas_scale.sync(&mut imgui);
+                    if let Err(e) = renderer.reload_font_texture(&mut imgui) {
+                        log::error!("Could not reload the font texture: {:?}", e);
+                    }
+                    if let Some(handle) = render_context.text_effects.get_mut().clear() {
+                        renderer.textures().remove(handle.texture_id);
+                    }
+                }
+                if let Some(theme) = theme.as_ref().filter(|_| theme_changed || fonts_changed) {
+                    theme.apply_font(&mut imgui, &render_context.fonts);
+                }
+
+                // Textures are uploaded between frames, like fonts
+                if render_context.images.has_pending() {
+                    let replaced = render_context.images.upload_pending(|image| upload_image(&display, &mut renderer, image));
+                    for handle in replaced {
+                        renderer.textures().remove(handle.texture_id);
+                    }
+                }
+                let text_effects = render_context.text_effects.get_mut();
+                if text_effects.has_pending() {
+                    let mut atlas = imgui.fonts();
+                    let texture = atlas.build_alpha8_texture();
+                    let replaced = text_effects.build_pending(texture.data, texture.width, |image| upload_image(&display, &mut renderer, image));
+                    if let Some(handle) = replaced {
+                        renderer.textures().remove(handle.texture_id);
+                    }
+                }
+
+                for source in &mut input_sources {
+                    render_context.input.poll(source.as_mut());
+                }
+
+                transition.set_open(render_context.ui_open);
+                transition.update(Duration::from_secs_f32(imgui.io().delta_time));
+                render_context.ui_visible = transition.is_visible();
+
+                let base_alpha = imgui.style().alpha;
+                let alpha = base_alpha * transition.alpha();
+                imgui.style_mut().alpha = alpha;
+                render_context.style_alpha = base_alpha;
 
                 let mut ui = imgui.frame();
 
-                let old_render_context = render_context.clone();
+                let old_bypass_screenshots = render_context.bypass_screenshots;
                 run_ui(&mut ui, &mut state, &mut render_context);
-                if old_render_context.ui_open != render_context.ui_open || !render_context_init {
-                    fade_start = Some(Instant::now());
-                    controller.clickthrough(!render_context.ui_open);
+                render_context.input.end_frame();
+
+                let should_clickthrough = transition.is_clickthrough();
+                if clickthrough != Some(should_clickthrough) {
+                    controller.clickthrough(should_clickthrough);
+                    clickthrough = Some(should_clickthrough);
                 }
-                if old_render_context.bypass_screenshots != render_context.bypass_screenshots || !render_context_init {
+                if old_bypass_screenshots != render_context.bypass_screenshots || !render_context_init {
                     controller.hide_screenshots(render_context.bypass_screenshots);
                 }
                 render_context_init = true;
@@ -123,17 +248,39 @@ impl Imgui {
                 platform.prepare_render(&ui, gl_window.window());
 
                 let draw_data = ui.render();
-
-                renderer
-                    .render(&mut target, draw_data)
+                let overlays = render_context.overlay_draw_lists.get_mut();
+                render_with_offset(&mut renderer, &mut target, draw_data, overlays, transition.offset())
                     .expect("Rendering failed");
+                overlays.clear();
                 target.finish().expect("Failed to swap buffers");
+
+                // Leave the alpha alone if the ui changed it during the frame
+                if imgui.style().alpha == alpha {
+                    imgui.style_mut().alpha = base_alpha;
+                }
             }
             Event::WindowEvent {
                 event: WindowEvent::CloseRequested,
                 ..
             } => *control_flow = ControlFlow::Exit,
+            event @ Event::WindowEvent {
+                event: WindowEvent::ScaleFactorChanged { .. },
+                ..
+            } => {
+                let gl_window = display.gl_window();
+                platform.handle_event(imgui.io_mut(), gl_window.window(), &event);
+
+                // Rebuild the atlas so text stays crisp after moving to a monitor with a different DPI
+                atlas_scale.set_scale(&mut imgui, platform.hidpi_factor() as f32);
+                if let Err(e) = renderer.reload_font_texture(&mut imgui) {
+                    log::error!("Could not reload the font texture: {:?}", e);
+                }
+                if let Some(handle) = render_context.text_effects.get_mut().clear() {
+                    renderer.textures().remove(handle.texture_id);
+                }
+            }
             event => {
+                render_context.input.handle_winit_event(&event);
                 let gl_window = display.gl_window();
                 platform.handle_event(imgui.io_mut(), gl_window.window(), &event);
             }
@@ -142,56 +289,113 @@ impl Imgui {
 }
 
 /// Context that is passed to the callback in the render loop
-#[derive(Clone, Eq, PartialEq)]
+#[derive(Clone, PartialEq)]
 pub struct RenderContext {
     pub bypass_screenshots: bool,
     pub ui_open: bool,
-    pub fonts: HashMap<Font, FontId>
+    /// True while the ui is open or still transitioning out. Windows should be drawn while this is set
+    pub ui_visible: bool,
+    pub fonts: Fonts,
+    /// Images queued here are uploaded before the next frame
+    pub images: Images,
+    /// Keyboard and mouse state of this frame
+    pub input: Input,
+    /// Alpha of the style before the transition fades it. `Style::alpha` is multiplied by the fade
+    /// during the frame, so this is the value to show and save
+    pub style_alpha: f32,
+    /// Themes queued with `apply_theme`
+    pub(crate) pending_themes: Vec<Theme>,
+    /// Outlined and blurred glyphs for text styles, built before the next frame like images
+    pub(crate) text_effects: RefCell<TextEffects>,
+    /// Draw lists of the overlay windows this frame, which stay in place while the ui slides
+    pub(crate) overlay_draw_lists: RefCell<Vec<*const sys::ImDrawList>>,
 }
 
-#[derive(Default, Debug)]
-pub struct RenderState(HashMap<String, Box<dyn std::any::Any>>);
-
-impl RenderState {
-    pub fn new() -> Self {
-        Self(HashMap::new())
+impl RenderContext {
+    pub fn new(fonts: Fonts) -> Self {
+        Self {
+            bypass_screenshots: false,
+            ui_open: true,
+            ui_visible: true,
+            fonts,
+            images: Images::default(),
+            input: Input::new(),
+            style_alpha: 1.0,
+            pending_themes: Vec::new(),
+            text_effects: RefCell::new(TextEffects::default()),
+            overlay_draw_lists: RefCell::new(Vec::new()),
+        }
     }
 
-    pub fn set<T: 'static>(&mut self, key: &str, value: T) {
-        self.0.insert(key.to_string(), Box::new(value));
+    /// Applies the settings `theme` has before the next frame. The style can't change while a frame
+    /// is being built, since imgui keeps reading it
+    pub fn apply_theme(&mut self, theme: Theme) {
+        self.pending_themes.push(theme);
     }
+}
 
-    pub fn get<'a, T: 'static>(&mut self, key: &str, default: T) -> &'a mut T {
-        self.get_or_else(key, move || default)
-    }
+/// Raw input on Windows doesn't report the side mouse buttons, so they are polled
+#[cfg(windows)]
+fn default_input_sources() -> Vec<Box<dyn InputSource>> {
+    vec![Box::new(crate::input::SideButtons::default())]
+}
 
-    pub fn get_or_else<'a, T: 'static>(&mut self, key: &str, default: impl FnOnce() -> T) -> &'a mut T {
-        let n = self.0.entry(key.to_string())
-            .or_insert_with(|| Box::new(default()))
-            .downcast_mut::<T>()
-            .unwrap();
-        unsafe { (n as *mut T).as_mut().unwrap() }
-    }
+#[cfg(not(windows))]
+fn default_input_sources() -> Vec<Box<dyn InputSource>> {
+    Vec::new()
 }
 
-// Animation for fading in a window. Returns a f32 from 0.0 to 1.0 of the alpha the window should be
-fn fade_animation(fade_start: &Option<Instant>, fade_time: Duration, ui_open: bool) -> f32 {
-    // Fade in window
-    let alpha = match fade_start {
-        Some(start) => {
-            let elapsed = start.elapsed();
-            if elapsed > fade_time {
-                if ui_open { 1.0 } else { 0.0 }
-            } else {
-                let mut alpha = elapsed.as_secs_f32() / fade_time.as_secs_f32();
-                if ui_open {
-                    alpha = 1.0 - alpha;
-                }
-                alpha
-            }
-        }
-        None => if ui_open { 1.0 } else { 0.0 }
+fn upload_image(display: &glium::Display, renderer: &mut Renderer, image: RgbaImage) -> Result<ImageHandle> {
+    let (width, height) = (image.width, image.height);
+    let raw = RawImage2d::from_raw_rgba(image.data, (width, height));
+    let texture = Texture {
+        texture: Rc::new(Texture2d::new(display, raw)?),
+        sampler: Default::default(),
     };
+    let texture_id = renderer.textures().insert(texture);
+    Ok(ImageHandle { texture_id, width, height })
+}
 
-    f32::clamp(alpha, 0.0, 1.0)
-}
\ No newline at end of file
+/// Renders `draw_data` with every window except the overlays moved by `offset`, for sliding
+/// transitions. The draw lists are left alone, instead the projection of the windows is moved
+fn render_with_offset(
+    renderer: &mut Renderer,
+    target: &mut Frame,
+    draw_data: &DrawData,
+    overlays: &[*const sys::ImDrawList],
+    offset: [f32; 2],
+) -> Result<(), RendererError> {
+    if offset == [0.0, 0.0] || draw_data.draw_lists_count() == 0 {
+        return renderer.render(target, draw_data);
+    }
+
+    // Safety: `DrawData` is a transparent mirror of `ImDrawData`, and its lists stay alive until the next frame
+    let draw_data = unsafe { &*(draw_data as *const DrawData as *const sys::ImDrawData) };
+    let lists = unsafe { std::slice::from_raw_parts(draw_data.CmdLists, draw_data.CmdListsCount as usize) };
+    let is_overlay = |list: &*mut sys::ImDrawList| overlays.contains(&(*list as *const _));
+
+    // Consecutive lists that move the same way are rendered together, so they keep imgui's order
+    let mut start = 0;
+    while start < lists.len() {
+        let overlay = is_overlay(&lists[start]);
+        let end = lists[start..].iter()
+            .position(|list| is_overlay(list) != overlay)
+            .map_or(lists.len(), |count| start + count);
+
+        let mut part = sys::ImDrawData {
+            CmdLists: lists[start..end].as_ptr() as *mut _,
+            CmdListsCount: (end - start) as _,
+            TotalIdxCount: lists[start..end].iter().map(|&list| unsafe { (*list).IdxBuffer.Size }).sum(),
+            TotalVtxCount: lists[start..end].iter().map(|&list| unsafe { (*list).VtxBuffer.Size }).sum(),
+            ..*draw_data
+        };
+        if !overlay {
+            // Looking from further back moves what is drawn forwards
+            part.DisplayPos.x -= offset[0];
+            part.DisplayPos.y -= offset[1];
+        }
+        renderer.render(target, unsafe { &*(&part as *const sys::ImDrawData as *const DrawData) })?;
+        start = end;
+    }
+    Ok(())
+}
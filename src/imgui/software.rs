@@ -0,0 +1,361 @@
+use anyhow::*;
+use imgui::{Context, DrawCmd, DrawCmdParams, DrawData, DrawVert, TextureId, Textures, Ui};
+use std::cell::RefCell;
+use std::path::Path;
+use std::time::Duration;
+use super::fonts::{add_fonts, AtlasScale, Fonts};
+use super::image::{Images, RgbaImage};
+use super::text_effects::TextEffects;
+use super::{RenderContext, RenderState};
+use crate::types::ImageHandle;
+use crate::input::{Input, InputSource};
+
+/// A texture owned by the [`SoftwareRenderer`]
+pub type SoftwareTexture = RgbaImage;
+
+/// Rasterizes imgui draw data on the CPU so frames can be compared without a GPU
+pub struct SoftwareRenderer {
+    textures: Textures<SoftwareTexture>,
+}
+
+impl SoftwareRenderer {
+    /// Builds the font atlas of `ctx` and uploads it as a texture
+    pub fn init(ctx: &mut Context) -> Result<Self> {
+        let mut renderer = Self { textures: Textures::new() };
+        renderer.reload_font_texture(ctx)?;
+        Ok(renderer)
+    }
+
+    pub fn reload_font_texture(&mut self, ctx: &mut Context) -> Result<()> {
+        let mut fonts = ctx.fonts();
+        let texture = fonts.build_rgba32_texture();
+        let image = RgbaImage { width: texture.width, height: texture.height, data: texture.data.to_vec() };
+        fonts.tex_id = self.textures.insert(image);
+        Ok(())
+    }
+
+    pub fn textures(&mut self) -> &mut Textures<SoftwareTexture> {
+        &mut self.textures
+    }
+
+    /// Stores `image` as a texture that can be drawn with `draw_image`
+    pub fn load_image(&mut self, image: RgbaImage) -> ImageHandle {
+        let (width, height) = (image.width, image.height);
+        ImageHandle { texture_id: self.textures.insert(image), width, height }
+    }
+
+    /// Decodes a png and stores it as a texture
+    pub fn load_image_png(&mut self, bytes: &[u8]) -> Result<ImageHandle> {
+        Ok(self.load_image(RgbaImage::from_png(bytes)?))
+    }
+
+    /// Reads a png file and stores it as a texture
+    pub fn load_image_file(&mut self, path: impl AsRef<Path>) -> Result<ImageHandle> {
+        Ok(self.load_image(RgbaImage::open(path)?))
+    }
+
+    /// Renders `draw_data` into a new image the size of the framebuffer
+    pub fn render(&self, draw_data: &DrawData) -> Result<RgbaImage> {
+        let width = (draw_data.display_size[0] * draw_data.framebuffer_scale[0]) as u32;
+        let height = (draw_data.display_size[1] * draw_data.framebuffer_scale[1]) as u32;
+        let mut target = RgbaImage::new(width, height);
+        self.render_into(&mut target, draw_data)?;
+        Ok(target)
+    }
+
+    /// Renders `draw_data` on top of the contents of `target`
+    pub fn render_into(&self, target: &mut RgbaImage, draw_data: &DrawData) -> Result<()> {
+        let [clip_off_x, clip_off_y] = draw_data.display_pos;
+        let [scale_x, scale_y] = draw_data.framebuffer_scale;
+        let transform = |pos: [f32; 2]| [(pos[0] - clip_off_x) * scale_x, (pos[1] - clip_off_y) * scale_y];
+
+        for draw_list in draw_data.draw_lists() {
+            let vtx_buffer = draw_list.vtx_buffer();
+            let idx_buffer = draw_list.idx_buffer();
+
+            for command in draw_list.commands() {
+                match command {
+                    DrawCmd::Elements {
+                        count,
+                        cmd_params: DrawCmdParams { clip_rect, texture_id, vtx_offset, idx_offset },
+                    } => {
+                        let texture = self.lookup_texture(texture_id)?;
+                        let [min_x, min_y] = transform([clip_rect[0], clip_rect[1]]);
+                        let [max_x, max_y] = transform([clip_rect[2], clip_rect[3]]);
+                        let clip = [min_x.max(0.0), min_y.max(0.0), max_x.min(target.width as f32), max_y.min(target.height as f32)];
+                        if clip[0] >= clip[2] || clip[1] >= clip[3] {
+                            continue;
+                        }
+
+                        for triangle in idx_buffer[idx_offset..idx_offset + count].chunks_exact(3) {
+                            let vertex = |i: usize| {
+                                let vertex = vtx_buffer[vtx_offset + triangle[i] as usize];
+                                DrawVert { pos: transform(vertex.pos), ..vertex }
+                            };
+                            rasterize_triangle(target, texture, clip, [vertex(0), vertex(1), vertex(2)]);
+                        }
+                    }
+                    DrawCmd::ResetRenderState => {}
+                    DrawCmd::RawCallback { callback, raw_cmd } => unsafe {
+                        callback(draw_list.raw(), raw_cmd)
+                    },
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn lookup_texture(&self, texture_id: TextureId) -> Result<&SoftwareTexture> {
+        self.textures.get(texture_id).ok_or_else(|| anyhow!("Bad texture ID: {:?}", texture_id))
+    }
+}
+
+fn edge(a: [f32; 2], b: [f32; 2], p: [f32; 2]) -> f32 {
+    (b[0] - a[0]) * (p[1] - a[1]) - (b[1] - a[1]) * (p[0] - a[0])
+}
+
+/// Whether pixels exactly on the edge from `a` to `b` belong to the triangle (top-left rule)
+fn is_top_left(a: [f32; 2], b: [f32; 2]) -> bool {
+    (a[1] == b[1] && b[0] < a[0]) || b[1] > a[1]
+}
+
+fn rasterize_triangle(target: &mut RgbaImage, texture: &SoftwareTexture, clip: [f32; 4], mut vertices: [DrawVert; 3]) {
+    let mut area = edge(vertices[0].pos, vertices[1].pos, vertices[2].pos);
+    if area == 0.0 {
+        return;
+    }
+    // imgui doesn't guarantee a winding order, so make every triangle counter clockwise
+    if area < 0.0 {
+        vertices.swap(1, 2);
+        area = -area;
+    }
+    let [v0, v1, v2] = vertices;
+    let (p0, p1, p2) = (v0.pos, v1.pos, v2.pos);
+
+    let min_x = p0[0].min(p1[0]).min(p2[0]).max(clip[0]).floor() as u32;
+    let min_y = p0[1].min(p1[1]).min(p2[1]).max(clip[1]).floor() as u32;
+    let max_x = (p0[0].max(p1[0]).max(p2[0]).min(clip[2]).ceil() as u32).min(target.width);
+    let max_y = (p0[1].max(p1[1]).max(p2[1]).min(clip[3]).ceil() as u32).min(target.height);
+
+    let top_left = [is_top_left(p1, p2), is_top_left(p2, p0), is_top_left(p0, p1)];
+
+    for y in min_y..max_y {
+        for x in min_x..max_x {
+            let p = [x as f32 + 0.5, y as f32 + 0.5];
+            let weights = [edge(p1, p2, p), edge(p2, p0, p), edge(p0, p1, p)];
+            let inside = weights.iter().zip(top_left.iter())
+                .all(|(&w, &top_left)| w > 0.0 || (w == 0.0 && top_left));
+            if !inside {
+                continue;
+            }
+
+            let [w0, w1, w2] = [weights[0] / area, weights[1] / area, weights[2] / area];
+            let interpolate = |a: f32, b: f32, c: f32| a * w0 + b * w1 + c * w2;
+
+            let uv = [
+                interpolate(v0.uv[0], v1.uv[0], v2.uv[0]),
+                interpolate(v0.uv[1], v1.uv[1], v2.uv[1]),
+            ];
+            let texel = sample(texture, uv);
+
+            let mut source = [0.0; 4];
+            for i in 0..4 {
+                let color = interpolate(v0.col[i] as f32, v1.col[i] as f32, v2.col[i] as f32) / 255.0;
+                source[i] = color * texel[i];
+            }
+
+            let blended = blend(source, target.get_pixel(x, y));
+            target.put_pixel(x, y, blended);
+        }
+    }
+}
+
+/// Nearest neighbour texture lookup returning channels in `0.0..=1.0`
+fn sample(texture: &SoftwareTexture, uv: [f32; 2]) -> [f32; 4] {
+    let x = ((uv[0] * texture.width as f32) as i64).clamp(0, texture.width as i64 - 1) as u32;
+    let y = ((uv[1] * texture.height as f32) as i64).clamp(0, texture.height as i64 - 1) as u32;
+    let texel = texture.get_pixel(x, y);
+    [
+        texel[0] as f32 / 255.0,
+        texel[1] as f32 / 255.0,
+        texel[2] as f32 / 255.0,
+        texel[3] as f32 / 255.0,
+    ]
+}
+
+/// Same blend state as imgui_glium_renderer: straight alpha for color, `One, OneMinusSrcAlpha` for alpha
+fn blend(source: [f32; 4], destination: [u8; 4]) -> [u8; 4] {
+    let alpha = source[3];
+    let mut out = [0; 4];
+    for i in 0..3 {
+        let dst = destination[i] as f32 / 255.0;
+        out[i] = ((source[i] * alpha + dst * (1.0 - alpha)) * 255.0 + 0.5) as u8;
+    }
+    let dst_alpha = destination[3] as f32 / 255.0;
+    out[3] = ((alpha + dst_alpha * (1.0 - alpha)).min(1.0) * 255.0 + 0.5) as u8;
+    out
+}
+
+/// Renders frames offscreen with the [`SoftwareRenderer`]. Mirrors [`Imgui`](super::Imgui)
+/// so the same ui closures can be used in tests
+pub struct HeadlessImgui {
+    pub imgui: Context,
+    pub renderer: SoftwareRenderer,
+    pub fonts: Fonts,
+    pub atlas_scale: AtlasScale,
+    pub images: Images,
+    pub state: RenderState,
+    pub input: Input,
+    /// Polled before every frame, e.g. a `FakeInput` to script the ui
+    pub input_sources: Vec<Box<dyn InputSource>>,
+    text_effects: TextEffects,
+}
+
+impl HeadlessImgui {
+    pub fn new(mut imgui: Context, display_size: [f32; 2]) -> Result<Self> {
+        imgui.set_ini_filename(None);
+        imgui.io_mut().display_size = display_size;
+
+        let fonts = add_fonts(&mut imgui, 1.0);
+        let mut atlas_scale = AtlasScale::default();
+        atlas_scale.sync(&mut imgui);
+        let renderer = SoftwareRenderer::init(&mut imgui)?;
+
+        Ok(Self {
+            imgui,
+            renderer,
+            fonts,
+            atlas_scale,
+            images: Images::default(),
+            state: RenderState::new(),
+            input: Input::new(),
+            input_sources: Vec::new(),
+            text_effects: TextEffects::default(),
+        })
+    }
+
+    /// Renders frames for a monitor with `scale` times as many pixels, the same way `Imgui` handles
+    /// a DPI change. Windows keep their size, but the fonts are rasterized at the new scale
+    pub fn set_scale(&mut self, scale: f32) -> Result<()> {
+        self.atlas_scale.set_scale(&mut self.imgui, scale);
+        self.imgui.io_mut().display_framebuffer_scale = [scale, scale];
+        self.renderer.reload_font_texture(&mut self.imgui)?;
+        self.clear_text_effects();
+        Ok(())
+    }
+
+    /// Forgets the text effects built from the old font atlas
+    fn clear_text_effects(&mut self) {
+        if let Some(handle) = self.text_effects.clear() {
+            self.renderer.textures().remove(handle.texture_id);
+        }
+    }
+
+    /// Runs the ui for a single 60 fps frame and rasterizes the result
+    pub fn render_frame(&mut self, run_ui: impl FnOnce(&mut Ui, &mut RenderState, &mut RenderContext)) -> Result<RgbaImage> {
+        if self.fonts.has_pending() {
+            self.fonts.add_pending(&mut self.imgui, self.atlas_scale.scale());
+            self.atlas_scale.sync(&mut self.imgui);
+            self.renderer.reload_font_texture(&mut self.imgui)?;
+            self.clear_text_effects();
+        }
+        let renderer = &mut self.renderer;
+        for handle in self.images.upload_pending(|image| Ok(renderer.load_image(image))) {
+            renderer.textures().remove(handle.texture_id);
+        }
+        if self.text_effects.has_pending() {
+            let mut atlas = self.imgui.fonts();
+            let texture = atlas.build_alpha8_texture();
+            let replaced = self.text_effects.build_pending(texture.data, texture.width, |image| Ok(renderer.load_image(image)));
+            if let Some(handle) = replaced {
+                renderer.textures().remove(handle.texture_id);
+            }
+        }
+
+        for source in &mut self.input_sources {
+            self.input.poll(source.as_mut());
+        }
+
+        let mut render_context = RenderContext::new(self.fonts.clone());
+        render_context.images = std::mem::take(&mut self.images);
+        render_context.input = std::mem::take(&mut self.input);
+        render_context.text_effects = RefCell::new(std::mem::take(&mut self.text_effects));
+        render_context.style_alpha = self.imgui.style().alpha;
+
+        self.imgui.io_mut().update_delta_time(Duration::from_secs_f32(1.0 / 60.0));
+        let mut ui = self.imgui.frame();
+        run_ui(&mut ui, &mut self.state, &mut render_context);
+        let draw_data = ui.render();
+        let image = self.renderer.render(draw_data);
+
+        for theme in render_context.pending_themes.drain(..) {
+            theme.apply(&mut self.imgui);
+        }
+
+        // Keep fonts and images queued by the ui for the next frame
+        self.fonts = render_context.fonts;
+        self.images = render_context.images;
+        self.input = render_context.input;
+        self.text_effects = render_context.text_effects.into_inner();
+        self.input.end_frame();
+        image
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::imgui::lock_context;
+
+    fn vertex(pos: [f32; 2], col: [u8; 4]) -> DrawVert {
+        DrawVert { pos, uv: [0.0, 0.0], col }
+    }
+
+    #[test]
+    fn shared_edges_are_only_drawn_once() {
+        let white = RgbaImage { width: 1, height: 1, data: vec![255; 4] };
+        let mut target = RgbaImage::new(8, 8);
+        let clip = [0.0, 0.0, 8.0, 8.0];
+        let color = [255, 255, 255, 128];
+
+        // Two triangles covering the square from (2, 2) to (6, 6), split along the diagonal
+        rasterize_triangle(&mut target, &white, clip, [vertex([2.0, 2.0], color), vertex([6.0, 2.0], color), vertex([6.0, 6.0], color)]);
+        rasterize_triangle(&mut target, &white, clip, [vertex([2.0, 2.0], color), vertex([6.0, 6.0], color), vertex([2.0, 6.0], color)]);
+
+        for y in 0..8 {
+            for x in 0..8 {
+                let inside = (2..6).contains(&x) && (2..6).contains(&y);
+                let expected = if inside { [128, 128, 128, 128] } else { [0, 0, 0, 0] };
+                assert_eq!(target.get_pixel(x, y), expected, "pixel ({}, {})", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn renders_a_frame() {
+        let _lock = lock_context();
+        let mut headless = HeadlessImgui::new(Context::create(), [64.0, 64.0]).unwrap();
+        let image = headless.render_frame(|ui, _, _| {
+            ui.get_background_draw_list()
+                .add_rect([10.0, 10.0], [30.0, 20.0], [1.0, 0.0, 0.0, 1.0])
+                .filled(true)
+                .build();
+        }).unwrap();
+
+        assert_eq!((image.width, image.height), (64, 64));
+        assert_eq!(image.get_pixel(10, 10), [255, 0, 0, 255]);
+        assert_eq!(image.get_pixel(29, 19), [255, 0, 0, 255]);
+        assert_eq!(image.get_pixel(30, 19), [0, 0, 0, 0]);
+        assert_eq!(image.get_pixel(29, 20), [0, 0, 0, 0]);
+        assert_eq!(image.get_pixel(0, 0), [0, 0, 0, 0]);
+
+        let mut expected = RgbaImage::new(64, 64);
+        for y in 10..20 {
+            for x in 10..30 {
+                expected.put_pixel(x, y, [255, 0, 0, 255]);
+            }
+        }
+        assert_eq!(image.diff(&expected, 0).unwrap(), 0);
+    }
+}
@@ -0,0 +1,206 @@
+use std::any::{Any, type_name};
+use std::borrow::Cow;
+use std::collections::{hash_map, HashMap};
+use std::fmt;
+use std::marker::PhantomData;
+use anyhow::{anyhow, Result};
+
+/// Names a value in [`RenderState`] together with its type, so every access with the same key
+/// agrees on the type
+pub struct StateKey<T> {
+    name: Cow<'static, str>,
+    _type: PhantomData<T>,
+}
+
+impl<T> StateKey<T> {
+    /// A key with a fixed name, usable as a `const`
+    pub const fn new(name: &'static str) -> Self {
+        Self { name: Cow::Borrowed(name), _type: PhantomData }
+    }
+
+    /// A key built at runtime, e.g. one per widget id
+    pub fn dynamic(name: impl Into<String>) -> Self {
+        Self { name: Cow::Owned(name.into()), _type: PhantomData }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl<T> Clone for StateKey<T> {
+    fn clone(&self) -> Self {
+        Self { name: self.name.clone(), _type: PhantomData }
+    }
+}
+
+impl<T> fmt::Debug for StateKey<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "StateKey<{}>({:?})", type_name::<T>(), self.name)
+    }
+}
+
+struct Entry {
+    value: Box<dyn Any>,
+    type_name: &'static str,
+}
+
+impl fmt::Debug for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.type_name)
+    }
+}
+
+impl Entry {
+    fn new<T: 'static>(value: T) -> Self {
+        Self { value: Box::new(value), type_name: type_name::<T>() }
+    }
+}
+
+/// Values that live across frames, like the state of widgets. Accessing a key with a different type
+/// than the value it holds is an error instead of a panic
+#[derive(Default, Debug)]
+pub struct RenderState(HashMap<String, Entry>);
+
+impl RenderState {
+    pub fn new() -> Self {
+        Self(HashMap::new())
+    }
+
+    /// Stores `value` under `key`, replacing whatever was there before regardless of its type
+    pub fn insert<T: 'static>(&mut self, key: &StateKey<T>, value: T) {
+        self.0.insert(key.name().to_string(), Entry::new(value));
+    }
+
+    pub fn contains<T: 'static>(&self, key: &StateKey<T>) -> bool {
+        self.0.contains_key(key.name())
+    }
+
+    pub fn get<T: 'static>(&self, key: &StateKey<T>) -> Result<Option<&T>> {
+        match self.0.get(key.name()) {
+            Some(entry) => entry.value.downcast_ref::<T>().map(Some).ok_or_else(|| type_mismatch(key, entry.type_name)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn get_mut<T: 'static>(&mut self, key: &StateKey<T>) -> Result<Option<&mut T>> {
+        match self.0.get_mut(key.name()) {
+            Some(entry) => {
+                let type_name = entry.type_name;
+                entry.value.downcast_mut::<T>().map(Some).ok_or_else(|| type_mismatch(key, type_name))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the value under `key`, storing `default()` first if there is none
+    pub fn get_or_insert_with<T: 'static>(&mut self, key: &StateKey<T>, default: impl FnOnce() -> T) -> Result<&mut T> {
+        let entry = self.0.entry(key.name().to_string()).or_insert_with(|| Entry::new(default()));
+        let type_name = entry.type_name;
+        entry.value.downcast_mut::<T>().ok_or_else(|| type_mismatch(key, type_name))
+    }
+
+    /// Like `get_or_insert_with`, but a value of another type is logged and replaced with `default()`
+    /// instead of failing, so widgets keep working after a key was reused for something else
+    pub fn get_or_replace_with<T: 'static>(&mut self, key: &StateKey<T>, default: impl FnOnce() -> T) -> &mut T {
+        let entry = match self.0.entry(key.name().to_string()) {
+            hash_map::Entry::Occupied(entry) => {
+                let entry = entry.into_mut();
+                if !entry.value.is::<T>() {
+                    log::warn!("Replacing state: {:#}", type_mismatch(key, entry.type_name));
+                    *entry = Entry::new(default());
+                }
+                entry
+            }
+            hash_map::Entry::Vacant(entry) => entry.insert(Entry::new(default())),
+        };
+        entry.value.downcast_mut::<T>().expect("the entry holds a T")
+    }
+
+    pub fn get_or_default<T: Default + 'static>(&mut self, key: &StateKey<T>) -> Result<&mut T> {
+        self.get_or_insert_with(key, T::default)
+    }
+
+    /// Runs `f` with the value under `key`, storing `default()` first if there is none
+    pub fn with<T: 'static, R>(&mut self, key: &StateKey<T>, default: impl FnOnce() -> T, f: impl FnOnce(&mut T) -> R) -> Result<R> {
+        self.get_or_insert_with(key, default).map(f)
+    }
+
+    /// Takes the value out. A value of another type is left in place
+    pub fn remove<T: 'static>(&mut self, key: &StateKey<T>) -> Result<Option<T>> {
+        match self.0.remove(key.name()) {
+            Some(Entry { value, type_name }) => match value.downcast::<T>() {
+                Ok(value) => Ok(Some(*value)),
+                Err(value) => {
+                    self.0.insert(key.name().to_string(), Entry { value, type_name });
+                    Err(type_mismatch(key, type_name))
+                }
+            },
+            None => Ok(None),
+        }
+    }
+}
+
+fn type_mismatch<T>(key: &StateKey<T>, found: &'static str) -> anyhow::Error {
+    anyhow!("State {:?} holds a {} but was accessed as a {}", key.name(), found, type_name::<T>())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COUNT: StateKey<u32> = StateKey::new("count");
+    const COUNT_AS_STRING: StateKey<String> = StateKey::new("count");
+
+    #[test]
+    fn stores_values_by_key() {
+        let mut state = RenderState::new();
+        assert_eq!(state.get(&COUNT).unwrap(), None);
+        assert!(!state.contains(&COUNT));
+
+        *state.get_or_default(&COUNT).unwrap() += 2;
+        state.with(&COUNT, || 0, |count| *count += 1).unwrap();
+        assert_eq!(state.get(&COUNT).unwrap(), Some(&3));
+        assert!(state.contains(&COUNT));
+
+        let key = StateKey::dynamic(format!("widget:{}", 7));
+        state.insert(&key, 1.5f32);
+        assert_eq!(state.get(&StateKey::<f32>::new("widget:7")).unwrap(), Some(&1.5));
+    }
+
+    #[test]
+    fn mismatched_types_are_errors() {
+        let mut state = RenderState::new();
+        state.insert(&COUNT, 3);
+
+        let error = state.get(&COUNT_AS_STRING).unwrap_err().to_string();
+        assert!(error.contains("\"count\"") && error.contains("u32") && error.contains("String"), "{}", error);
+        assert!(state.get_mut(&COUNT_AS_STRING).is_err());
+        assert!(state.get_or_insert_with(&COUNT_AS_STRING, String::new).is_err());
+        assert!(state.with(&COUNT_AS_STRING, String::new, |_| ()).is_err());
+    }
+
+    #[test]
+    fn remove_leaves_mismatched_values_in_place() {
+        let mut state = RenderState::new();
+        state.insert(&COUNT, 3);
+
+        assert!(state.remove(&COUNT_AS_STRING).is_err());
+        assert_eq!(state.get(&COUNT).unwrap(), Some(&3));
+
+        assert_eq!(state.remove(&COUNT).unwrap(), Some(3));
+        assert_eq!(state.remove(&COUNT).unwrap(), None);
+        assert!(!state.contains(&COUNT));
+    }
+
+    #[test]
+    fn get_or_replace_with_replaces_mismatched_values() {
+        let mut state = RenderState::new();
+        state.insert(&COUNT, 3);
+
+        assert_eq!(state.get_or_replace_with(&COUNT, || 0), &mut 3);
+        state.get_or_replace_with(&COUNT_AS_STRING, || "three".to_string()).push('!');
+        assert_eq!(state.get(&COUNT_AS_STRING).unwrap().map(String::as_str), Some("three!"));
+        assert!(state.get(&COUNT).is_err());
+    }
+}
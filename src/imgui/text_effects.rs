@@ -0,0 +1,329 @@
+use std::collections::HashMap;
+use anyhow::Result;
+use imgui::TextureId;
+use crate::text::GlyphBitmap;
+use crate::types::ImageHandle;
+use super::image::RgbaImage;
+
+/// Width of the texture the effect glyphs are packed into
+const TEXTURE_WIDTH: u32 = 1024;
+/// Height the texture grows by when it runs out of rows
+const TEXTURE_HEIGHT_STEP: u32 = 256;
+/// Once the texture would grow past this height every glyph is packed again from scratch
+const MAX_TEXTURE_HEIGHT: u32 = 4096;
+/// Empty texels between glyphs, so filtering never picks up a neighbour
+const GLYPH_GAP: u32 = 1;
+/// Largest outline or blur radius in texels, the rest is cut off
+pub const MAX_EFFECT_RADIUS: u32 = 64;
+
+/// What is done to a glyph, with its radius in texels of the font atlas
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EffectKind {
+    Outline(u32),
+    Blur(u32),
+}
+
+impl EffectKind {
+    pub fn radius(&self) -> u32 {
+        match *self {
+            EffectKind::Outline(radius) | EffectKind::Blur(radius) => radius,
+        }
+    }
+}
+
+/// A glyph of the font atlas with an effect applied to it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EffectGlyphKey {
+    /// Texels the glyph covers in the font atlas, `[x0, y0, x1, y1]`
+    pub rect: [u32; 4],
+    pub effect: EffectKind,
+}
+
+/// Where a glyph with an effect is in the effect texture
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EffectGlyph {
+    pub texture_id: TextureId,
+    pub uv_min: [f32; 2],
+    pub uv_max: [f32; 2],
+    /// Texels the effect adds around the glyph on every side
+    pub padding: u32,
+}
+
+/// Outlined and blurred glyphs of the font atlas, packed into a texture of their own. Like
+/// images, the texture can only change between frames, so `get` queues glyphs it doesn't have
+/// yet and the text is drawn without its effect until the next frame
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextEffects {
+    glyphs: HashMap<EffectGlyphKey, [u32; 4]>,
+    pending: Vec<EffectGlyphKey>,
+    image: RgbaImage,
+    texture: Option<ImageHandle>,
+    cursor: [u32; 2],
+    row_height: u32,
+}
+
+impl Default for TextEffects {
+    fn default() -> Self {
+        Self {
+            glyphs: HashMap::new(),
+            pending: Vec::new(),
+            image: RgbaImage::new(TEXTURE_WIDTH, 0),
+            texture: None,
+            cursor: [0, 0],
+            row_height: 0,
+        }
+    }
+}
+
+impl TextEffects {
+    /// The glyph described by `key`, or `None` after queueing it to be built before the next frame
+    pub fn get(&mut self, key: EffectGlyphKey) -> Option<EffectGlyph> {
+        let texture = self.texture.filter(|_| self.glyphs.contains_key(&key));
+        let (texture, rect) = match texture {
+            Some(texture) => (texture, self.glyphs[&key]),
+            None => {
+                if !self.pending.contains(&key) {
+                    self.pending.push(key);
+                }
+                return None;
+            }
+        };
+
+        let size = [texture.width as f32, texture.height as f32];
+        Some(EffectGlyph {
+            texture_id: texture.texture_id,
+            uv_min: [rect[0] as f32 / size[0], rect[1] as f32 / size[1]],
+            uv_max: [rect[2] as f32 / size[0], rect[3] as f32 / size[1]],
+            padding: key.effect.radius(),
+        })
+    }
+
+    pub(crate) fn has_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Applies the effects of every queued glyph to the alpha font atlas `atlas`, which is
+    /// `atlas_width` texels wide, and uploads the texture again with `upload`. Returns the texture
+    /// it replaced, which should be removed from the renderer
+    #[must_use]
+    pub(crate) fn build_pending(
+        &mut self,
+        atlas: &[u8],
+        atlas_width: u32,
+        upload: impl FnOnce(RgbaImage) -> Result<ImageHandle>,
+    ) -> Option<ImageHandle> {
+        for key in std::mem::take(&mut self.pending) {
+            let [x0, y0, x1, y1] = key.rect;
+            if self.glyphs.contains_key(&key) || x0 > x1 || y0 > y1 || x1 > atlas_width
+                || y1 as usize * atlas_width as usize > atlas.len() {
+                continue;
+            }
+
+            let glyph = GlyphBitmap::from_atlas(atlas, atlas_width, key.rect);
+            let glyph = match key.effect {
+                EffectKind::Outline(radius) => glyph.outlined(radius),
+                EffectKind::Blur(radius) => glyph.blurred(radius),
+            };
+            let position = match self.allocate(glyph.width, glyph.height) {
+                Some(position) => position,
+                None => {
+                    log::warn!("A {}x{} text effect glyph does not fit in its texture", glyph.width, glyph.height);
+                    continue;
+                }
+            };
+
+            for y in 0..glyph.height {
+                for x in 0..glyph.width {
+                    self.image.put_pixel(position[0] + x, position[1] + y, [0xFF, 0xFF, 0xFF, glyph.get(x, y)]);
+                }
+            }
+            self.glyphs.insert(key, [position[0], position[1], position[0] + glyph.width, position[1] + glyph.height]);
+        }
+
+        match upload(self.image.clone()) {
+            Result::Ok(handle) => self.texture.replace(handle),
+            Err(e) => {
+                log::error!("Could not upload the text effect texture: {:?}", e);
+                // The texture no longer matches the packed glyphs
+                self.clear()
+            }
+        }
+    }
+
+    /// Forgets every glyph, for when the font atlas was built again. Returns the texture to remove
+    /// from the renderer
+    #[must_use]
+    pub(crate) fn clear(&mut self) -> Option<ImageHandle> {
+        let texture = self.texture.take();
+        *self = Self::default();
+        texture
+    }
+
+    /// Finds room for a `width`x`height` glyph, growing the texture or starting over when it is full
+    fn allocate(&mut self, width: u32, height: u32) -> Option<[u32; 2]> {
+        if width > TEXTURE_WIDTH || height > MAX_TEXTURE_HEIGHT {
+            return None;
+        }
+        if self.cursor[0] + width > TEXTURE_WIDTH {
+            self.cursor = [0, self.cursor[1] + self.row_height + GLYPH_GAP];
+            self.row_height = 0;
+        }
+        if self.cursor[1] + height > MAX_TEXTURE_HEIGHT {
+            // Glyphs still in use are queued again on the next frame
+            self.glyphs.clear();
+            self.image = RgbaImage::new(TEXTURE_WIDTH, 0);
+            self.cursor = [0, 0];
+            self.row_height = 0;
+        }
+
+        let position = self.cursor;
+        self.cursor[0] += width + GLYPH_GAP;
+        self.row_height = self.row_height.max(height);
+
+        let bottom = position[1] + height;
+        if bottom > self.image.height {
+            self.grow((bottom.div_ceil(TEXTURE_HEIGHT_STEP) * TEXTURE_HEIGHT_STEP).min(MAX_TEXTURE_HEIGHT));
+        }
+        Some(position)
+    }
+
+    /// Adds rows to the bottom of the texture. They are white so filtering at the edge of a glyph
+    /// only fades its alpha instead of darkening it
+    fn grow(&mut self, height: u32) {
+        let mut rows = RgbaImage::new(TEXTURE_WIDTH, height - self.image.height);
+        for pixel in rows.data.chunks_exact_mut(4) {
+            pixel.copy_from_slice(&[0xFF, 0xFF, 0xFF, 0]);
+        }
+        self.image.data.extend_from_slice(&rows.data);
+        self.image.height = height;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::bail;
+
+    /// An 8x4 atlas with a 2x2 glyph at `[2, 1, 4, 3]`
+    fn atlas() -> Vec<u8> {
+        let mut atlas = vec![0; 8 * 4];
+        for &(x, y) in &[(2, 1), (3, 1), (2, 2), (3, 2)] {
+            atlas[y * 8 + x] = 0xFF;
+        }
+        atlas
+    }
+
+    fn upload(image: RgbaImage) -> Result<ImageHandle> {
+        Ok(ImageHandle { texture_id: TextureId::new(7), width: image.width, height: image.height })
+    }
+
+    const OUTLINE: EffectGlyphKey = EffectGlyphKey { rect: [2, 1, 4, 3], effect: EffectKind::Outline(2) };
+
+    #[test]
+    fn glyphs_are_queued_until_built() {
+        let mut effects = TextEffects::default();
+        assert_eq!(effects.get(OUTLINE), None);
+        assert_eq!(effects.get(OUTLINE), None);
+        assert!(effects.has_pending());
+
+        assert_eq!(effects.build_pending(&atlas(), 8, upload), None);
+        assert!(!effects.has_pending());
+
+        let glyph = effects.get(OUTLINE).unwrap();
+        assert_eq!(glyph.texture_id, TextureId::new(7));
+        assert_eq!(glyph.padding, 2);
+        assert_eq!(glyph.uv_min, [0.0, 0.0]);
+        assert_eq!(glyph.uv_max, [6.0 / TEXTURE_WIDTH as f32, 6.0 / TEXTURE_HEIGHT_STEP as f32]);
+    }
+
+    #[test]
+    fn packed_glyphs_are_white_with_the_effect_as_alpha() {
+        let mut effects = TextEffects::default();
+        effects.get(OUTLINE);
+        let mut uploaded = None;
+        let _ = effects.build_pending(&atlas(), 8, |image| {
+            uploaded = Some(image.clone());
+            upload(image)
+        });
+
+        let image = uploaded.unwrap();
+        assert_eq!((image.width, image.height), (TEXTURE_WIDTH, TEXTURE_HEIGHT_STEP));
+        // The glyph, its outline, and the empty texels around it
+        assert_eq!(image.get_pixel(2, 2), [0xFF, 0xFF, 0xFF, 0xFF]);
+        assert_eq!(image.get_pixel(1, 2), [0xFF, 0xFF, 0xFF, 0xFF]);
+        assert_eq!(image.get_pixel(7, 2), [0xFF, 0xFF, 0xFF, 0]);
+    }
+
+    #[test]
+    fn each_effect_is_packed_separately() {
+        let mut effects = TextEffects::default();
+        let blur = EffectGlyphKey { effect: EffectKind::Blur(2), ..OUTLINE };
+        effects.get(OUTLINE);
+        effects.get(blur);
+        let _ = effects.build_pending(&atlas(), 8, upload);
+
+        let (outline, blur) = (effects.get(OUTLINE).unwrap(), effects.get(blur).unwrap());
+        assert!(blur.uv_min[0] >= outline.uv_max[0]);
+    }
+
+    #[test]
+    fn rebuilding_replaces_the_texture() {
+        let mut effects = TextEffects::default();
+        effects.get(OUTLINE);
+        let _ = effects.build_pending(&atlas(), 8, upload);
+
+        effects.get(EffectGlyphKey { effect: EffectKind::Outline(1), ..OUTLINE });
+        let replaced = effects.build_pending(&atlas(), 8, |image| {
+            Ok(ImageHandle { texture_id: TextureId::new(8), ..upload(image)? })
+        });
+        assert_eq!(replaced.map(|handle| handle.texture_id), Some(TextureId::new(7)));
+        assert_eq!(effects.get(OUTLINE).unwrap().texture_id, TextureId::new(8));
+    }
+
+    #[test]
+    fn clearing_forgets_every_glyph() {
+        let mut effects = TextEffects::default();
+        effects.get(OUTLINE);
+        let _ = effects.build_pending(&atlas(), 8, upload);
+
+        assert_eq!(effects.clear().map(|handle| handle.texture_id), Some(TextureId::new(7)));
+        assert_eq!(effects.get(OUTLINE), None);
+        assert_eq!(effects.clear(), None);
+    }
+
+    #[test]
+    fn failed_uploads_start_over() {
+        let mut effects = TextEffects::default();
+        effects.get(OUTLINE);
+        let _ = effects.build_pending(&atlas(), 8, |_| bail!("No texture"));
+        assert_eq!(effects.get(OUTLINE), None);
+        assert!(effects.has_pending());
+    }
+
+    #[test]
+    fn glyphs_outside_the_atlas_are_skipped() {
+        let mut effects = TextEffects::default();
+        let key = EffectGlyphKey { rect: [6, 2, 10, 6], ..OUTLINE };
+        effects.get(key);
+        let _ = effects.build_pending(&atlas(), 8, upload);
+        assert!(!effects.has_pending());
+        assert_eq!(effects.get(key), None);
+    }
+
+    #[test]
+    fn full_textures_start_over() {
+        let mut effects = TextEffects::default();
+        let big = vec![0xFF; 1000 * 1000];
+        let keys: Vec<_> = (0..5u32)
+            .map(|i| EffectGlyphKey { rect: [0, 0, 1000, 1000 - i], effect: EffectKind::Outline(1) })
+            .collect();
+        for &key in &keys {
+            effects.get(key);
+        }
+        let _ = effects.build_pending(&big, 1000, upload);
+
+        // Only four rows of glyphs fit, so the fifth starts the texture over
+        assert_eq!(effects.get(keys[0]), None);
+        assert!(effects.get(keys[4]).is_some());
+    }
+}
@@ -0,0 +1,189 @@
+use imgui::*;
+use crate::imgui::{RenderContext, RenderState, StateKey};
+use super::themes::Theme;
+
+struct ThemeEditorState {
+    path: ImString,
+    function_name: ImString,
+    filter: ImString,
+    /// The style when the editor was first shown, for reverting
+    original: Theme,
+    status: Option<String>,
+}
+
+const STATE: StateKey<ThemeEditorState> = StateKey::new("theme_editor");
+
+/// A window that edits every color and setting of the current style. Changes show up from the next
+/// frame on, and can be saved as a theme file or copied as Rust code, see [`Theme::to_rust`]
+pub fn theme_editor(ui: &Ui, render_state: &mut RenderState, render_context: &mut RenderContext, opened: &mut bool) {
+    // The style can't be changed while imgui reads it, so a copy is edited and applied between frames
+    let mut style = ui.clone_style();
+    // Edit and save the alpha the style has outside of transitions, not the faded one
+    style.alpha = render_context.style_alpha;
+    let mut changed = false;
+    let mut loaded = None;
+
+    let state = render_state.get_or_replace_with(&STATE, || ThemeEditorState {
+        path: text_buffer("theme.toml", 256),
+        function_name: text_buffer("custom_theme", 64),
+        filter: ImString::with_capacity(64),
+        original: Theme::capture(&style),
+        status: None,
+    });
+
+    Window::new(im_str!("Theme Editor"))
+        .size([420.0, 520.0], Condition::FirstUseEver)
+        .opened(opened)
+        .build(ui, || {
+            ui.input_text(im_str!("File"), &mut state.path).build();
+            if ui.button(im_str!("Save"), [80.0, 0.0]) {
+                state.status = Some(match Theme::capture(&style).save(state.path.to_str()) {
+                    Ok(()) => format!("Saved {}", state.path.to_str()),
+                    Err(e) => format!("{:#}", e),
+                });
+            }
+            ui.same_line(0.0);
+            if ui.button(im_str!("Load"), [80.0, 0.0]) {
+                state.status = Some(match Theme::load(state.path.to_str()) {
+                    Ok(theme) => {
+                        loaded = Some(theme);
+                        format!("Loaded {}", state.path.to_str())
+                    }
+                    Err(e) => format!("{:#}", e),
+                });
+            }
+            ui.same_line(0.0);
+            if ui.button(im_str!("Revert"), [80.0, 0.0]) {
+                loaded = Some(state.original.clone());
+                state.status = None;
+            }
+
+            ui.input_text(im_str!("Function"), &mut state.function_name).build();
+            if ui.button(im_str!("Copy as Rust"), [120.0, 0.0]) {
+                let code = Theme::capture(&style).to_rust(state.function_name.to_str());
+                ui.set_clipboard_text(&ImString::new(code));
+                state.status = Some("Copied to clipboard".to_string());
+            }
+
+            if let Some(status) = &state.status {
+                ui.text_wrapped(&ImString::new(status));
+            }
+            ui.separator();
+
+            TabBar::new(im_str!("##theme_editor_tabs")).build(ui, || {
+                TabItem::new(im_str!("Colors")).build(ui, || {
+                    ui.input_text(im_str!("Filter"), &mut state.filter).build();
+                    let filter = state.filter.to_str().to_lowercase();
+
+                    ChildWindow::new(im_str!("##colors")).build(ui, || {
+                        for i in 0..StyleColor::COUNT {
+                            let name = unsafe { std::ffi::CStr::from_ptr(sys::igGetStyleColorName(i as _)) }.to_string_lossy();
+                            if !name.to_lowercase().contains(&filter) {
+                                continue;
+                            }
+                            changed |= ColorEdit::new(&ImString::new(name), &mut style.colors[i])
+                                .alpha_bar(true)
+                                .build(ui);
+                        }
+                    });
+                });
+
+                TabItem::new(im_str!("Sizes")).build(ui, || {
+                    ChildWindow::new(im_str!("##sizes")).build(ui, || {
+                        changed |= float_slider(ui, "Alpha", &mut style.alpha, 0.2, 1.0);
+                        changed |= vec2_slider(ui, "Window padding", &mut style.window_padding, 0.0, 20.0);
+                        changed |= float_slider(ui, "Window rounding", &mut style.window_rounding, 0.0, 12.0);
+                        changed |= float_slider(ui, "Window border", &mut style.window_border_size, 0.0, 1.0);
+                        changed |= vec2_slider(ui, "Window min size", &mut style.window_min_size, 1.0, 200.0);
+                        changed |= vec2_slider(ui, "Window title align", &mut style.window_title_align, 0.0, 1.0);
+                        changed |= float_slider(ui, "Child rounding", &mut style.child_rounding, 0.0, 12.0);
+                        changed |= float_slider(ui, "Child border", &mut style.child_border_size, 0.0, 1.0);
+                        changed |= float_slider(ui, "Popup rounding", &mut style.popup_rounding, 0.0, 12.0);
+                        changed |= float_slider(ui, "Popup border", &mut style.popup_border_size, 0.0, 1.0);
+                        changed |= vec2_slider(ui, "Frame padding", &mut style.frame_padding, 0.0, 20.0);
+                        changed |= float_slider(ui, "Frame rounding", &mut style.frame_rounding, 0.0, 12.0);
+                        changed |= float_slider(ui, "Frame border", &mut style.frame_border_size, 0.0, 1.0);
+                        changed |= vec2_slider(ui, "Item spacing", &mut style.item_spacing, 0.0, 20.0);
+                        changed |= vec2_slider(ui, "Item inner spacing", &mut style.item_inner_spacing, 0.0, 20.0);
+                        changed |= vec2_slider(ui, "Cell padding", &mut style.cell_padding, 0.0, 20.0);
+                        changed |= float_slider(ui, "Indent spacing", &mut style.indent_spacing, 0.0, 30.0);
+                        changed |= float_slider(ui, "Scrollbar size", &mut style.scrollbar_size, 1.0, 20.0);
+                        changed |= float_slider(ui, "Scrollbar rounding", &mut style.scrollbar_rounding, 0.0, 12.0);
+                        changed |= float_slider(ui, "Grab min size", &mut style.grab_min_size, 1.0, 20.0);
+                        changed |= float_slider(ui, "Grab rounding", &mut style.grab_rounding, 0.0, 12.0);
+                        changed |= float_slider(ui, "Tab rounding", &mut style.tab_rounding, 0.0, 12.0);
+                        changed |= float_slider(ui, "Tab border", &mut style.tab_border_size, 0.0, 1.0);
+                        changed |= float_slider(ui, "Tab close button min width", &mut style.tab_min_width_for_close_button, 0.0, 100.0);
+                        changed |= vec2_slider(ui, "Touch extra padding", &mut style.touch_extra_padding, 0.0, 10.0);
+                        changed |= float_slider(ui, "Columns min spacing", &mut style.columns_min_spacing, 0.0, 20.0);
+                        changed |= float_slider(ui, "Log slider deadzone", &mut style.log_slider_deadzone, 0.0, 12.0);
+                        changed |= direction_combo(ui, "Window menu button", &mut style.window_menu_button_position, &WINDOW_MENU_BUTTON_POSITIONS);
+                        changed |= direction_combo(ui, "Color button", &mut style.color_button_position, &COLOR_BUTTON_POSITIONS);
+                        changed |= vec2_slider(ui, "Button text align", &mut style.button_text_align, 0.0, 1.0);
+                        changed |= vec2_slider(ui, "Selectable text align", &mut style.selectable_text_align, 0.0, 1.0);
+                        changed |= vec2_slider(ui, "Display window padding", &mut style.display_window_padding, 0.0, 30.0);
+                        changed |= vec2_slider(ui, "Display safe area padding", &mut style.display_safe_area_padding, 0.0, 30.0);
+                        changed |= float_slider(ui, "Mouse cursor scale", &mut style.mouse_cursor_scale, 0.5, 3.0);
+
+                        ui.separator();
+                        changed |= ui.checkbox(im_str!("Anti aliased lines"), &mut style.anti_aliased_lines);
+                        changed |= ui.checkbox(im_str!("Anti aliased lines use texture"), &mut style.anti_aliased_lines_use_tex);
+                        changed |= ui.checkbox(im_str!("Anti aliased fill"), &mut style.anti_aliased_fill);
+                        changed |= float_slider(ui, "Curve tessellation tolerance", &mut style.curve_tessellation_tol, 0.1, 10.0);
+                        changed |= float_slider(ui, "Circle segment max error", &mut style.circle_segment_max_error, 0.1, 10.0);
+                    });
+                });
+            });
+        });
+
+    if let Some(theme) = loaded {
+        render_context.apply_theme(theme);
+    } else if changed {
+        render_context.apply_theme(Theme::capture(&style));
+    }
+}
+
+/// Where imgui can put the collapse button of a window
+const WINDOW_MENU_BUTTON_POSITIONS: [(&str, Direction); 3] = [
+    ("None", Direction::None),
+    ("Left", Direction::Left),
+    ("Right", Direction::Right),
+];
+
+/// Which side of a color edit its preview button goes
+const COLOR_BUTTON_POSITIONS: [(&str, Direction); 2] = [
+    ("Left", Direction::Left),
+    ("Right", Direction::Right),
+];
+
+fn direction_combo(ui: &Ui, label: &str, value: &mut Direction, directions: &[(&str, Direction)]) -> bool {
+    let names: Vec<ImString> = directions.iter().map(|&(name, _)| ImString::new(name)).collect();
+    let names: Vec<&ImStr> = names.iter().map(|name| name.as_ref()).collect();
+    let mut index = directions.iter().position(|&(_, direction)| direction == *value).unwrap_or(0);
+
+    let changed = ComboBox::new(&ImString::new(label)).build_simple_string(ui, &mut index, &names);
+    if changed {
+        *value = directions[index].1;
+    }
+    changed
+}
+
+fn text_buffer(text: &str, capacity: usize) -> ImString {
+    let mut buffer = ImString::with_capacity(capacity);
+    buffer.push_str(text);
+    buffer
+}
+
+fn float_slider(ui: &Ui, label: &str, value: &mut f32, min: f32, max: f32) -> bool {
+    Slider::new(&ImString::new(label))
+        .range(min..=max)
+        .display_format(im_str!("%.1f"))
+        .build(ui, value)
+}
+
+fn vec2_slider(ui: &Ui, label: &str, value: &mut [f32; 2], min: f32, max: f32) -> bool {
+    Slider::new(&ImString::new(label))
+        .range(min..=max)
+        .display_format(im_str!("%.2f"))
+        .build_array(ui, value)
+}
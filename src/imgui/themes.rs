@@ -1,5 +1,16 @@
-use imgui::{FontSource, FontConfig};
+use imgui::{FontSource, FontConfig, StyleColor, Style, Direction};
+use crate::color::Color;
+use crate::palette::ColorRoles;
+use crate::types;
+use super::fonts::{GlyphRanges, Fonts, FontDescriptor, FontData};
 use imgui::sys::*;
+use imgui::sys;
+use serde::{Serialize, Deserialize};
+use std::collections::BTreeMap;
+use std::ffi::CStr;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+use anyhow::{Result, Context as _};
 
 pub fn main_theme(imgui: &mut imgui::Context) {
     imgui.fonts().add_font(&[
@@ -8,6 +19,7 @@ pub fn main_theme(imgui: &mut imgui::Context) {
             size_pixels: 15.0,
             config: Some(FontConfig{
                 name: Some("Ruda Bold".to_string()),
+                glyph_ranges: GlyphRanges::European.to_imgui(),
                 ..Default::default()
             }),
         }
@@ -18,6 +30,7 @@ pub fn main_theme(imgui: &mut imgui::Context) {
             size_pixels: 15.0,
             config: Some(FontConfig{
                 name: Some("Apercu Medium".to_string()),
+                glyph_ranges: GlyphRanges::European.to_imgui(),
                 ..Default::default()
             }),
         }
@@ -28,6 +41,7 @@ pub fn main_theme(imgui: &mut imgui::Context) {
             size_pixels: 15.0,
             config: Some(FontConfig{
                 name: Some("Apercu Bold".to_string()),
+                glyph_ranges: GlyphRanges::European.to_imgui(),
                 ..Default::default()
             }),
         }
@@ -40,117 +54,631 @@ pub fn main_theme(imgui: &mut imgui::Context) {
     // style.alpha = 1.0;
 }
 
-// https://user-images.githubusercontent.com/1657728/61432156-a1179d00-a983-11e9-87ed-f6711d7610be.png
+/// Colors of the [`dark_blue`] theme, after
+/// https://user-images.githubusercontent.com/1657728/61432156-a1179d00-a983-11e9-87ed-f6711d7610be.png
+pub const DARK_BLUE: ColorRoles = ColorRoles {
+    accent: Color::from_hex(0x478FFF),
+    background: Color::from_hex(0x1C262B),
+    text: Color::from_hex(0xF2F5FA),
+};
+
+/// Colors of the [`dark_red`] theme, after
+/// https://user-images.githubusercontent.com/1434736/104601393-7ee70400-567a-11eb-923d-6e9693f9c8.png
+pub const DARK_RED: ColorRoles = ColorRoles {
+    accent: Color::from_hex(0xFF3030),
+    background: Color::from_hex(0x141414),
+    text: Color::from_hex(0xBFBFBF),
+};
+
 pub fn dark_blue(imgui: &mut imgui::Context) {
-    let style = imgui.style_mut();
-    let colors = &mut style.colors;
-    colors[ImGuiCol_Text as usize] = [0.95, 0.96, 0.98, 1.00];
-    colors[ImGuiCol_TextDisabled as usize] = [0.36, 0.42, 0.47, 1.00];
-    colors[ImGuiCol_WindowBg as usize] = [0.11, 0.15, 0.17, 1.00];
-    colors[ImGuiCol_ChildBg as usize] = [0.15, 0.18, 0.22, 1.00];
-    colors[ImGuiCol_PopupBg as usize] = [0.08, 0.08, 0.08, 0.94];
-    colors[ImGuiCol_Border as usize] = [0.08, 0.10, 0.12, 1.00];
-    colors[ImGuiCol_BorderShadow as usize] = [0.00, 0.00, 0.00, 0.00];
-    colors[ImGuiCol_FrameBg as usize] = [0.20, 0.25, 0.29, 1.00];
-    colors[ImGuiCol_FrameBgHovered as usize] = [0.12, 0.20, 0.28, 1.00];
-    colors[ImGuiCol_FrameBgActive as usize] = [0.09, 0.12, 0.14, 1.00];
-    colors[ImGuiCol_TitleBg as usize] = [0.09, 0.12, 0.14, 0.65];
-    colors[ImGuiCol_TitleBgActive as usize] = [0.08, 0.10, 0.12, 1.00];
-    colors[ImGuiCol_TitleBgCollapsed as usize] = [0.00, 0.00, 0.00, 0.51];
-    colors[ImGuiCol_MenuBarBg as usize] = [0.15, 0.18, 0.22, 1.00];
-    colors[ImGuiCol_ScrollbarBg as usize] = [0.02, 0.02, 0.02, 0.39];
-    colors[ImGuiCol_ScrollbarGrab as usize] = [0.20, 0.25, 0.29, 1.00];
-    colors[ImGuiCol_ScrollbarGrabHovered as usize] = [0.18, 0.22, 0.25, 1.00];
-    colors[ImGuiCol_ScrollbarGrabActive as usize] = [0.09, 0.21, 0.31, 1.00];
-    colors[ImGuiCol_CheckMark as usize] = [0.28, 0.56, 1.00, 1.00];
-    colors[ImGuiCol_SliderGrab as usize] = [0.28, 0.56, 1.00, 1.00];
-    colors[ImGuiCol_SliderGrabActive as usize] = [0.37, 0.61, 1.00, 1.00];
-    colors[ImGuiCol_Button as usize] = [0.20, 0.25, 0.29, 1.00];
-    colors[ImGuiCol_ButtonHovered as usize] = [0.28, 0.56, 1.00, 1.00];
-    colors[ImGuiCol_ButtonActive as usize] = [0.06, 0.53, 0.98, 1.00];
-    colors[ImGuiCol_Header as usize] = [0.20, 0.25, 0.29, 0.55];
-    colors[ImGuiCol_HeaderHovered as usize] = [0.26, 0.59, 0.98, 0.80];
-    colors[ImGuiCol_HeaderActive as usize] = [0.26, 0.59, 0.98, 1.00];
-    colors[ImGuiCol_Separator as usize] = [0.20, 0.25, 0.29, 1.00];
-    colors[ImGuiCol_SeparatorHovered as usize] = [0.10, 0.40, 0.75, 0.78];
-    colors[ImGuiCol_SeparatorActive as usize] = [0.10, 0.40, 0.75, 1.00];
-    colors[ImGuiCol_ResizeGrip as usize] = [0.26, 0.59, 0.98, 0.25];
-    colors[ImGuiCol_ResizeGripHovered as usize] = [0.26, 0.59, 0.98, 0.67];
-    colors[ImGuiCol_ResizeGripActive as usize] = [0.26, 0.59, 0.98, 0.95];
-    colors[ImGuiCol_Tab as usize] = [0.11, 0.15, 0.17, 1.00];
-    colors[ImGuiCol_TabHovered as usize] = [0.26, 0.59, 0.98, 0.80];
-    colors[ImGuiCol_TabActive as usize] = [0.20, 0.25, 0.29, 1.00];
-    colors[ImGuiCol_TabUnfocused as usize] = [0.11, 0.15, 0.17, 1.00];
-    colors[ImGuiCol_TabUnfocusedActive as usize] = [0.11, 0.15, 0.17, 1.00];
-    colors[ImGuiCol_PlotLines as usize] = [0.61, 0.61, 0.61, 1.00];
-    colors[ImGuiCol_PlotLinesHovered as usize] = [1.00, 0.43, 0.35, 1.00];
-    colors[ImGuiCol_PlotHistogram as usize] = [0.90, 0.70, 0.00, 1.00];
-    colors[ImGuiCol_PlotHistogramHovered as usize] = [1.00, 0.60, 0.00, 1.00];
-    colors[ImGuiCol_TextSelectedBg as usize] = [0.26, 0.59, 0.98, 0.35];
-    colors[ImGuiCol_DragDropTarget as usize] = [1.00, 1.00, 0.00, 0.90];
-    colors[ImGuiCol_NavHighlight as usize] = [0.26, 0.59, 0.98, 1.00];
-    colors[ImGuiCol_NavWindowingHighlight as usize] = [1.00, 1.00, 1.00, 0.70];
-    colors[ImGuiCol_NavWindowingDimBg as usize] = [0.80, 0.80, 0.80, 0.20];
-    colors[ImGuiCol_ModalWindowDimBg as usize] = [0.80, 0.80, 0.80, 0.35];
-}
-
-// https://user-images.githubusercontent.com/1434736/104601393-7ee70400-567a-11eb-923d-6e9693f9c8.png
+    apply_colors(imgui, &DARK_BLUE);
+}
+
 pub fn dark_red(imgui: &mut imgui::Context) {
-    let style = imgui.style_mut();
+    apply_colors(imgui, &DARK_RED);
+}
+
+/// Generates every style color from a few semantic roles, so windows can share the palette the overlays draw with
+pub fn style_colors(roles: &ColorRoles) -> [[f32; 4]; StyleColor::COUNT] {
+    let ColorRoles { accent, background, text } = *roles;
+
+    // Frames stand out from the background by being lighter on dark themes and darker on light ones
+    let dark = background.to_hsl().2 < 50.0;
+    let raise = |amount: f32| if dark { background.lighten(amount) } else { background.darken(amount) };
+    let sink = |amount: f32| if dark { background.darken(amount) } else { background.lighten(amount) };
+    let alpha = |color: Color, alpha: f32| {
+        let [r, g, b, _] = color.to_rgba_f32s();
+        [r, g, b, alpha]
+    };
+
+    let mut colors = [[0.0; 4]; StyleColor::COUNT];
+    colors[ImGuiCol_Text as usize] = text.into();
+    colors[ImGuiCol_TextDisabled as usize] = text.lerp(background, 0.5).into();
+    colors[ImGuiCol_WindowBg as usize] = background.into();
+    colors[ImGuiCol_ChildBg as usize] = raise(3.0).into();
+    colors[ImGuiCol_PopupBg as usize] = alpha(raise(3.0), 0.96);
+    colors[ImGuiCol_Border as usize] = raise(10.0).into();
+    colors[ImGuiCol_BorderShadow as usize] = Color::TRANSPARENT.into();
+    colors[ImGuiCol_FrameBg as usize] = raise(8.0).into();
+    colors[ImGuiCol_FrameBgHovered as usize] = raise(12.0).into();
+    colors[ImGuiCol_FrameBgActive as usize] = accent.lerp(background, 0.6).into();
+    colors[ImGuiCol_TitleBg as usize] = sink(3.0).into();
+    colors[ImGuiCol_TitleBgActive as usize] = sink(6.0).into();
+    colors[ImGuiCol_TitleBgCollapsed as usize] = alpha(sink(3.0), 0.5);
+    colors[ImGuiCol_MenuBarBg as usize] = raise(3.0).into();
+    colors[ImGuiCol_ScrollbarBg as usize] = alpha(sink(3.0), 0.5);
+    colors[ImGuiCol_ScrollbarGrab as usize] = raise(12.0).into();
+    colors[ImGuiCol_ScrollbarGrabHovered as usize] = raise(18.0).into();
+    colors[ImGuiCol_ScrollbarGrabActive as usize] = accent.into();
+    colors[ImGuiCol_CheckMark as usize] = accent.into();
+    colors[ImGuiCol_SliderGrab as usize] = accent.into();
+    colors[ImGuiCol_SliderGrabActive as usize] = accent.lighten(10.0).into();
+    colors[ImGuiCol_Button as usize] = raise(10.0).into();
+    colors[ImGuiCol_ButtonHovered as usize] = accent.into();
+    colors[ImGuiCol_ButtonActive as usize] = accent.darken(10.0).into();
+    colors[ImGuiCol_Header as usize] = raise(10.0).into();
+    colors[ImGuiCol_HeaderHovered as usize] = alpha(accent, 0.8);
+    colors[ImGuiCol_HeaderActive as usize] = accent.into();
+    colors[ImGuiCol_Separator as usize] = raise(10.0).into();
+    colors[ImGuiCol_SeparatorHovered as usize] = alpha(accent, 0.78);
+    colors[ImGuiCol_SeparatorActive as usize] = accent.into();
+    colors[ImGuiCol_ResizeGrip as usize] = alpha(accent, 0.25);
+    colors[ImGuiCol_ResizeGripHovered as usize] = alpha(accent, 0.67);
+    colors[ImGuiCol_ResizeGripActive as usize] = alpha(accent, 0.95);
+    colors[ImGuiCol_Tab as usize] = raise(3.0).into();
+    colors[ImGuiCol_TabHovered as usize] = alpha(accent, 0.8);
+    colors[ImGuiCol_TabActive as usize] = raise(10.0).into();
+    colors[ImGuiCol_TabUnfocused as usize] = raise(3.0).into();
+    colors[ImGuiCol_TabUnfocusedActive as usize] = raise(6.0).into();
+    colors[ImGuiCol_PlotLines as usize] = text.lerp(background, 0.35).into();
+    colors[ImGuiCol_PlotLinesHovered as usize] = accent.into();
+    colors[ImGuiCol_PlotHistogram as usize] = accent.into();
+    colors[ImGuiCol_PlotHistogramHovered as usize] = accent.lighten(10.0).into();
+    colors[ImGuiCol_TableHeaderBg as usize] = raise(8.0).into();
+    colors[ImGuiCol_TableBorderStrong as usize] = raise(14.0).into();
+    colors[ImGuiCol_TableBorderLight as usize] = raise(10.0).into();
+    colors[ImGuiCol_TableRowBg as usize] = Color::TRANSPARENT.into();
+    colors[ImGuiCol_TableRowBgAlt as usize] = alpha(text, 0.04);
+    colors[ImGuiCol_TextSelectedBg as usize] = alpha(accent, 0.35);
+    colors[ImGuiCol_DragDropTarget as usize] = alpha(accent, 0.9);
+    colors[ImGuiCol_NavHighlight as usize] = accent.into();
+    colors[ImGuiCol_NavWindowingHighlight as usize] = alpha(text, 0.7);
+    colors[ImGuiCol_NavWindowingDimBg as usize] = alpha(text, 0.2);
+    colors[ImGuiCol_ModalWindowDimBg as usize] = alpha(sink(20.0), 0.35);
+    colors
+}
+
+/// Replaces every style color with ones generated from `roles`
+pub fn apply_colors(imgui: &mut imgui::Context, roles: &ColorRoles) {
+    imgui.style_mut().colors = style_colors(roles);
+}
+
+/// Dark theme made from the Blueprint palette
+pub fn blueprint_dark(imgui: &mut imgui::Context) {
+    apply_colors(imgui, &ColorRoles::new(Color::BLUE4, Color::DARK_GRAY2, Color::LIGHT_GRAY5));
+}
+
+/// A font windows are drawn with by default
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ThemeFont {
+    pub font: types::Font,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u32>,
+    /// TTF file to load the font from if it isn't registered at `size` yet
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+}
+
+macro_rules! theme {
+    ($($field:ident: $field_type:ty $(as $with:literal)?),* $(,)?) => {
+        /// Style settings that can be saved to and loaded from TOML or JSON. Every setting is
+        /// optional, so a theme file only needs to contain what it changes
+        #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+        pub struct Theme {
+            $(
+                #[serde(default, skip_serializing_if = "Option::is_none" $(, with = $with)?)]
+                pub $field: Option<$field_type>,
+            )*
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            pub font: Option<ThemeFont>,
+            /// Style colors by their imgui name, e.g. `WindowBg`
+            #[serde(default, with = "color_map")]
+            pub colors: BTreeMap<String, Color>,
+        }
+
+        impl Theme {
+            fn capture_variables(style: &Style) -> Self {
+                Self {
+                    $($field: Some(style.$field),)*
+                    ..Default::default()
+                }
+            }
+
+            /// `style.field = value;` for every setting the theme has
+            fn rust_variables(&self) -> Vec<String> {
+                let mut lines = Vec::new();
+                $(
+                    if let Some(value) = self.$field {
+                        lines.push(format!("style.{} = {};", stringify!($field), value.to_rust()));
+                    }
+                )*
+                lines
+            }
+
+            fn apply_variables(&self, style: &mut Style) {
+                $(
+                    if let Some(value) = self.$field {
+                        style.$field = value;
+                    }
+                )*
+            }
+        }
+    };
+}
+
+// Tables have to come after plain values in TOML, so `font` and `colors` are declared last
+theme! {
+    alpha: f32,
+    window_padding: [f32; 2],
+    window_rounding: f32,
+    window_border_size: f32,
+    window_min_size: [f32; 2],
+    window_title_align: [f32; 2],
+    child_rounding: f32,
+    child_border_size: f32,
+    popup_rounding: f32,
+    popup_border_size: f32,
+    frame_padding: [f32; 2],
+    frame_rounding: f32,
+    frame_border_size: f32,
+    item_spacing: [f32; 2],
+    item_inner_spacing: [f32; 2],
+    cell_padding: [f32; 2],
+    touch_extra_padding: [f32; 2],
+    indent_spacing: f32,
+    scrollbar_size: f32,
+    scrollbar_rounding: f32,
+    grab_min_size: f32,
+    grab_rounding: f32,
+    tab_rounding: f32,
+    tab_border_size: f32,
+    tab_min_width_for_close_button: f32,
+    columns_min_spacing: f32,
+    window_menu_button_position: Direction as "direction",
+    color_button_position: Direction as "direction",
+    button_text_align: [f32; 2],
+    selectable_text_align: [f32; 2],
+    display_window_padding: [f32; 2],
+    display_safe_area_padding: [f32; 2],
+    mouse_cursor_scale: f32,
+    anti_aliased_lines: bool,
+    anti_aliased_lines_use_tex: bool,
+    anti_aliased_fill: bool,
+    curve_tessellation_tol: f32,
+    circle_segment_max_error: f32,
+    log_slider_deadzone: f32,
+}
+
+/// Formats a style setting as a Rust expression for [`Theme::to_rust`]
+trait RustValue {
+    fn to_rust(&self) -> String;
+}
+
+impl RustValue for f32 {
+    fn to_rust(&self) -> String {
+        format!("{:?}", self)
+    }
+}
+
+impl RustValue for bool {
+    fn to_rust(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl RustValue for [f32; 2] {
+    fn to_rust(&self) -> String {
+        format!("{:?}", self)
+    }
+}
+
+impl RustValue for Direction {
+    fn to_rust(&self) -> String {
+        format!("Direction::{:?}", self)
+    }
+}
+
+impl Theme {
+    /// Every setting and color of `style`
+    pub fn capture(style: &Style) -> Self {
+        let mut theme = Self::capture_variables(style);
+        theme.colors = (0..StyleColor::COUNT)
+            .map(|i| (style_color_name(i), Color::from(style.colors[i])))
+            .collect();
+        theme
+    }
+
+    /// A theme with only colors, generated from `roles`
+    pub fn from_roles(roles: &ColorRoles) -> Self {
+        let colors = style_colors(roles);
+        Self {
+            colors: (0..StyleColor::COUNT).map(|i| (style_color_name(i), Color::from(colors[i]))).collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Sets every style setting and color the theme has. Fonts are applied separately with
+    /// [`Theme::load_fonts`] and [`Theme::apply_font`], since they depend on the font atlas
+    pub fn apply(&self, imgui: &mut imgui::Context) {
+        self.apply_to_style(imgui.style_mut());
+    }
+
+    /// Like [`Theme::apply`], for when only the style is at hand
+    pub fn apply_to_style(&self, style: &mut Style) {
+        self.apply_variables(style);
+
+        for (name, &color) in &self.colors {
+            match (0..StyleColor::COUNT).find(|&i| style_color_name(i).eq_ignore_ascii_case(name)) {
+                Some(i) => style.colors[i] = color.into(),
+                None => log::warn!("Unknown style color {:?} in theme", name),
+            }
+        }
+    }
+
+    /// Queues the theme font to be loaded if it comes from a file that isn't loaded yet
+    pub fn load_fonts(&self, fonts: &mut Fonts) {
+        if let Some(ThemeFont { font, size, path: Some(path) }) = &self.font {
+            let size = size.unwrap_or(DEFAULT_FONT_SIZE);
+            if !fonts.sizes(font).contains(&size) {
+                fonts.load(FontDescriptor::new(font.clone(), FontData::File(path.clone()), &[size]));
+            }
+        }
+    }
+
+    /// Makes the theme font the default font of every window. Falls back to imgui's own default when
+    /// the theme has no font or it isn't loaded yet
+    pub fn apply_font(&self, imgui: &mut imgui::Context, fonts: &Fonts) {
+        let font = self.font.as_ref().and_then(|font| fonts.resolve(&font.font, font.size));
+        let font = font
+            .and_then(|id| imgui.fonts().get_font(id).map(|font| font as *const imgui::Font as *mut sys::ImFont))
+            .unwrap_or(std::ptr::null_mut());
+        // Safety: `Io` is a transparent mirror of `ImGuiIO`
+        unsafe {
+            (*(imgui.io_mut() as *mut imgui::Io as *mut sys::ImGuiIO)).FontDefault = font;
+        }
+    }
+
+    /// Rust code for a function that applies the theme, setting `style` fields and then
+    /// `colors[ImGuiCol_* as usize]` like hand written imgui themes
+    pub fn to_rust(&self, function_name: &str) -> String {
+        let mut code = format!("pub fn {}(imgui: &mut imgui::Context) {{\n", function_name);
+        code += "    let style = imgui.style_mut();\n";
+        for line in self.rust_variables() {
+            code += &format!("    {}\n", line);
+        }
+
+        if !self.colors.is_empty() {
+            code += "    let colors = &mut style.colors;\n";
+            for i in 0..StyleColor::COUNT {
+                let name = style_color_name(i);
+                if let Some(color) = self.colors.get(&name) {
+                    let [r, g, b, a] = color.to_rgba_f32s();
+                    code += &format!("    colors[ImGuiCol_{} as usize] = [{:.2}, {:.2}, {:.2}, {:.2}];\n", name, r, g, b, a);
+                }
+            }
+        }
+        code += "}\n";
+        code
+    }
+
+    pub fn from_toml(toml: &str) -> Result<Self> {
+        Ok(toml::from_str(toml)?)
+    }
+
+    pub fn to_toml(&self) -> Result<String> {
+        Ok(toml::to_string_pretty(self)?)
+    }
 
-    let colors = &mut style.colors;
-
-    colors[ImGuiCol_Text as usize] = [0.75, 0.75, 0.75, 1.0];
-    colors[ImGuiCol_TextDisabled as usize] = [0.35, 0.35, 0.35, 1.0];
-    colors[ImGuiCol_WindowBg as usize] = [0.08, 0.08, 0.08, 1.0];
-    colors[ImGuiCol_ChildBg as usize] = [0.08, 0.08, 0.08, 1.0];
-    colors[ImGuiCol_PopupBg as usize] = [0.10, 0.10, 0.10, 1.0];
-    colors[ImGuiCol_Border as usize] = [0.00, 0.00, 0.00, 1.0];
-    colors[ImGuiCol_BorderShadow as usize] = [0.00, 0.00, 0.00, 1.0];
-    colors[ImGuiCol_FrameBg as usize] = [0.00, 0.00, 0.00, 1.0];
-    colors[ImGuiCol_FrameBgHovered as usize] = [0.37, 0.14, 0.14, 1.0];
-    colors[ImGuiCol_FrameBgActive as usize] = [0.39, 0.20, 0.20, 1.0];
-    colors[ImGuiCol_TitleBg as usize] = [0.04, 0.04, 0.04, 1.0];
-    colors[ImGuiCol_TitleBgActive as usize] = [0.48, 0.16, 0.16, 1.0];
-    colors[ImGuiCol_TitleBgCollapsed as usize] = [0.48, 0.16, 0.16, 1.0];
-    colors[ImGuiCol_MenuBarBg as usize] = [0.14, 0.14, 0.14, 1.0];
-    colors[ImGuiCol_ScrollbarBg as usize] = [0.02, 0.02, 0.02, 1.0];
-    colors[ImGuiCol_ScrollbarGrab as usize] = [0.31, 0.31, 0.31, 1.0];
-    colors[ImGuiCol_ScrollbarGrabHovered as usize] = [0.41, 0.41, 0.41, 1.0];
-    colors[ImGuiCol_ScrollbarGrabActive as usize] = [0.51, 0.51, 0.51, 1.0];
-    colors[ImGuiCol_CheckMark as usize] = [0.56, 0.10, 0.10, 1.0];
-    colors[ImGuiCol_SliderGrab as usize] = [1.00, 0.19, 0.19, 1.0];
-    colors[ImGuiCol_SliderGrabActive as usize] = [0.89, 0.00, 0.19, 1.0];
-    colors[ImGuiCol_Button as usize] = [1.00, 0.19, 0.19, 1.0];
-    colors[ImGuiCol_ButtonHovered as usize] = [0.80, 0.17, 0.00, 1.0];
-    colors[ImGuiCol_ButtonActive as usize] = [0.89, 0.00, 0.19, 1.0];
-    colors[ImGuiCol_Header as usize] = [0.33, 0.35, 0.36, 1.0];
-    colors[ImGuiCol_HeaderHovered as usize] = [0.76, 0.28, 0.44, 1.0];
-    colors[ImGuiCol_HeaderActive as usize] = [0.47, 0.47, 0.47, 1.0];
-    colors[ImGuiCol_Separator as usize] = [0.32, 0.32, 0.32, 1.0];
-    colors[ImGuiCol_SeparatorHovered as usize] = [0.32, 0.32, 0.32, 1.0];
-    colors[ImGuiCol_SeparatorActive as usize] = [0.32, 0.32, 0.32, 1.0];
-    colors[ImGuiCol_ResizeGrip as usize] = [1.00, 1.00, 1.00, 1.0];
-    colors[ImGuiCol_ResizeGripHovered as usize] = [1.00, 1.00, 1.00, 1.0];
-    colors[ImGuiCol_ResizeGripActive as usize] = [1.00, 1.00, 1.00, 1.0];
-    colors[ImGuiCol_Tab as usize] = [0.07, 0.07, 0.07, 1.0];
-    colors[ImGuiCol_TabHovered as usize] = [0.86, 0.23, 0.43, 1.0];
-    colors[ImGuiCol_TabActive as usize] = [0.19, 0.19, 0.19, 1.0];
-    colors[ImGuiCol_TabUnfocused as usize] = [0.05, 0.05, 0.05, 1.0];
-    colors[ImGuiCol_TabUnfocusedActive as usize] = [0.13, 0.13, 0.13, 1.0];
-    colors[ImGuiCol_PlotLines as usize] = [0.61, 0.61, 0.61, 1.0];
-    colors[ImGuiCol_PlotLinesHovered as usize] = [1.00, 0.43, 0.35, 1.0];
-    colors[ImGuiCol_PlotHistogram as usize] = [0.90, 0.70, 0.00, 1.0];
-    colors[ImGuiCol_PlotHistogramHovered as usize] = [1.00, 0.60, 0.00, 1.0];
-    colors[ImGuiCol_TableHeaderBg as usize] = [0.19, 0.19, 0.20, 1.0];
-    colors[ImGuiCol_TableBorderStrong as usize] = [0.31, 0.31, 0.35, 1.0];
-    colors[ImGuiCol_TableBorderLight as usize] = [0.23, 0.23, 0.25, 1.0];
-    colors[ImGuiCol_TableRowBg as usize] = [0.00, 0.00, 0.00, 1.0];
-    colors[ImGuiCol_TableRowBgAlt as usize] = [1.00, 1.00, 1.00, 1.0];
-    colors[ImGuiCol_TextSelectedBg as usize] = [0.26, 0.59, 0.98, 1.0];
-    colors[ImGuiCol_DragDropTarget as usize] = [1.00, 1.00, 0.00, 1.0];
-    colors[ImGuiCol_NavHighlight as usize] = [0.26, 0.59, 0.98, 1.0];
-    colors[ImGuiCol_NavWindowingHighlight as usize] = [1.00, 1.00, 1.00, 1.0];
-    colors[ImGuiCol_NavWindowingDimBg as usize] = [0.80, 0.80, 0.80, 1.0];
-    colors[ImGuiCol_ModalWindowDimBg as usize] = [0.80, 0.80, 0.80, 1.0];
-}
\ No newline at end of file
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Reads a theme file, as JSON if it has a `.json` extension and as TOML otherwise
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path).with_context(|| format!("Could not read {}", path.display()))?;
+        match is_json(path) {
+            true => Self::from_json(&contents),
+            false => Self::from_toml(&contents),
+        }
+        .with_context(|| format!("Could not parse {}", path.display()))
+    }
+
+    /// Writes a theme file, as JSON if it has a `.json` extension and as TOML otherwise
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let contents = match is_json(path) {
+            true => self.to_json()?,
+            false => self.to_toml()?,
+        };
+        std::fs::write(path, contents).with_context(|| format!("Could not write {}", path.display()))
+    }
+}
+
+/// Size theme fonts are loaded at when the theme doesn't give one
+const DEFAULT_FONT_SIZE: u32 = 15;
+/// How often a watched theme file is checked for changes
+const THEME_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Reloads a theme file whenever it is modified
+#[derive(Debug, Clone)]
+pub struct ThemeWatcher {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    last_poll: Option<Instant>,
+}
+
+impl ThemeWatcher {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into(), modified: None, last_poll: None }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the theme if the file changed since the last time it was loaded. The first
+    /// call always loads it. Checks the file at most every [`THEME_POLL_INTERVAL`]
+    pub fn poll(&mut self) -> Option<Result<Theme>> {
+        let first_poll = self.last_poll.is_none();
+        if matches!(self.last_poll, Some(last_poll) if last_poll.elapsed() < THEME_POLL_INTERVAL) {
+            return None;
+        }
+        self.last_poll = Some(Instant::now());
+
+        // A missing file is only reported once, then again after it shows up
+        let modified = std::fs::metadata(&self.path).and_then(|metadata| metadata.modified()).ok();
+        if !first_poll && modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+        Some(Theme::load(&self.path))
+    }
+}
+
+fn is_json(path: &Path) -> bool {
+    path.extension().map_or(false, |extension| extension.eq_ignore_ascii_case("json"))
+}
+
+/// The name imgui gives a style color, e.g. `WindowBg`
+fn style_color_name(index: usize) -> String {
+    unsafe { CStr::from_ptr(igGetStyleColorName(index as _)) }.to_string_lossy().into_owned()
+}
+
+/// Stores directions by name, e.g. `"Left"`
+mod direction {
+    use imgui::Direction;
+    use serde::{Serialize, Serializer, Deserialize, Deserializer, de};
+
+    const NAMES: [(&str, Direction); 5] = [
+        ("None", Direction::None),
+        ("Left", Direction::Left),
+        ("Right", Direction::Right),
+        ("Up", Direction::Up),
+        ("Down", Direction::Down),
+    ];
+
+    pub fn serialize<S: Serializer>(direction: &Option<Direction>, serializer: S) -> Result<S::Ok, S::Error> {
+        direction
+            .map(|direction| NAMES.iter().find(|&&(_, named)| named == direction).map_or("None", |&(name, _)| name))
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Direction>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|name| {
+                NAMES.iter()
+                    .find(|(named, _)| named.eq_ignore_ascii_case(&name))
+                    .map(|&(_, direction)| direction)
+                    .ok_or_else(|| de::Error::custom(format!("Unknown direction {:?}", name)))
+            })
+            .transpose()
+    }
+}
+
+/// Stores colors as strings so theme files can be edited by hand
+mod color_map {
+    use std::collections::BTreeMap;
+    use serde::{Serialize, Serializer, Deserialize, Deserializer, de};
+    use crate::color::Color;
+
+    pub fn serialize<S: Serializer>(colors: &BTreeMap<String, Color>, serializer: S) -> Result<S::Ok, S::Error> {
+        colors.iter()
+            .map(|(name, color)| (name, color.to_string()))
+            .collect::<BTreeMap<_, _>>()
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<BTreeMap<String, Color>, D::Error> {
+        BTreeMap::<String, String>::deserialize(deserializer)?
+            .into_iter()
+            .map(|(name, color)| {
+                let color = color.parse().map_err(|e| de::Error::custom(format!("{}: {:#}", name, e)))?;
+                Ok((name, color))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::imgui::lock_context;
+
+    fn lightness(color: [f32; 4]) -> f32 {
+        Color::from(color).to_hsl().2
+    }
+
+    #[test]
+    fn style_colors_come_from_the_roles() {
+        let colors = style_colors(&DARK_BLUE);
+        assert_eq!(colors[ImGuiCol_Text as usize], <[f32; 4]>::from(DARK_BLUE.text));
+        assert_eq!(colors[ImGuiCol_WindowBg as usize], <[f32; 4]>::from(DARK_BLUE.background));
+        for &accented in &[ImGuiCol_CheckMark, ImGuiCol_SliderGrab, ImGuiCol_ButtonHovered, ImGuiCol_HeaderActive, ImGuiCol_NavHighlight] {
+            assert_eq!(colors[accented as usize], <[f32; 4]>::from(DARK_BLUE.accent), "color {}", style_color_name(accented as usize));
+        }
+        assert_eq!(colors[ImGuiCol_BorderShadow as usize], [0.0; 4]);
+    }
+
+    #[test]
+    fn frames_are_raised_from_the_background() {
+        let dark = style_colors(&DARK_BLUE);
+        assert!(lightness(dark[ImGuiCol_FrameBg as usize]) > lightness(dark[ImGuiCol_WindowBg as usize]));
+        assert!(lightness(dark[ImGuiCol_TitleBgActive as usize]) < lightness(dark[ImGuiCol_WindowBg as usize]));
+
+        let light = style_colors(&ColorRoles::new(Color::BLUE3, Color::LIGHT_GRAY5, Color::DARK_GRAY1));
+        assert!(lightness(light[ImGuiCol_FrameBg as usize]) < lightness(light[ImGuiCol_WindowBg as usize]));
+        assert!(lightness(light[ImGuiCol_TitleBgActive as usize]) > lightness(light[ImGuiCol_WindowBg as usize]));
+    }
+
+    #[test]
+    fn stock_themes_are_generated_from_their_roles() {
+        let _lock = lock_context();
+        let mut imgui = imgui::Context::create();
+
+        dark_blue(&mut imgui);
+        assert_eq!(imgui.style().colors, style_colors(&DARK_BLUE));
+        dark_red(&mut imgui);
+        assert_eq!(imgui.style().colors, style_colors(&DARK_RED));
+        blueprint_dark(&mut imgui);
+        assert_eq!(imgui.style().colors, style_colors(&ColorRoles::default()));
+    }
+
+    /// A theme with a setting of every kind, a font and a few colors
+    fn sample_theme() -> Theme {
+        let mut theme = Theme {
+            alpha: Some(0.75),
+            window_padding: Some([6.0, 4.0]),
+            window_menu_button_position: Some(Direction::Right),
+            anti_aliased_fill: Some(false),
+            log_slider_deadzone: Some(2.5),
+            font: Some(ThemeFont { font: types::Font::Verdana, size: Some(16), path: Some(PathBuf::from("fonts/verdana.ttf")) }),
+            ..Default::default()
+        };
+        theme.colors.insert("WindowBg".to_string(), Color::from_hex(0x1C262BF0));
+        theme.colors.insert("Text".to_string(), Color::WHITE);
+        theme
+    }
+
+    #[test]
+    fn theme_files_round_trip() {
+        let theme = sample_theme();
+        assert_eq!(Theme::from_toml(&theme.to_toml().unwrap()).unwrap(), theme);
+        assert_eq!(Theme::from_json(&theme.to_json().unwrap()).unwrap(), theme);
+        assert_eq!(Theme::from_toml("").unwrap(), Theme::default());
+    }
+
+    #[test]
+    fn captured_styles_apply_back_unchanged() {
+        let _lock = lock_context();
+        // Only one context can exist at a time
+        let captured = {
+            let mut imgui = imgui::Context::create();
+            let style = imgui.style_mut();
+            style.window_menu_button_position = Direction::Right;
+            style.color_button_position = Direction::Left;
+            style.anti_aliased_lines = false;
+            style.anti_aliased_lines_use_tex = false;
+            style.curve_tessellation_tol = 0.5;
+            style.circle_segment_max_error = 0.8;
+            style.mouse_cursor_scale = 2.0;
+            style.display_safe_area_padding = [4.0, 8.0];
+            style.tab_min_width_for_close_button = 12.0;
+            style.log_slider_deadzone = 6.0;
+            Theme::capture(imgui.style())
+        };
+
+        let theme = Theme::from_toml(&captured.to_toml().unwrap()).unwrap();
+        assert_eq!(theme, captured);
+
+        let mut imgui = imgui::Context::create();
+        theme.apply(&mut imgui);
+        assert_eq!(Theme::capture(imgui.style()), captured);
+    }
+
+    #[test]
+    fn unknown_color_names_are_skipped() {
+        let _lock = lock_context();
+        let mut imgui = imgui::Context::create();
+        let before = imgui.style().colors;
+
+        let theme = Theme::from_toml("[colors]\nWindowBg = \"#ff0000\"\nNotAColor = \"#00ff00\"\n").unwrap();
+        theme.apply(&mut imgui);
+
+        let mut expected = before;
+        expected[ImGuiCol_WindowBg as usize] = [1.0, 0.0, 0.0, 1.0];
+        assert_eq!(imgui.style().colors, expected);
+    }
+
+    #[test]
+    fn invalid_values_name_the_setting() {
+        let error = Theme::from_toml("[colors]\nWindowBg = \"#nope\"\n").unwrap_err();
+        assert!(format!("{:#}", error).contains("WindowBg"), "{:#}", error);
+
+        let error = Theme::from_toml("window_menu_button_position = \"Sideways\"\n").unwrap_err();
+        assert!(format!("{:#}", error).contains("Unknown direction"), "{:#}", error);
+    }
+
+    #[test]
+    fn watched_themes_reload_when_the_file_changes() {
+        let path = std::env::temp_dir().join(format!("theme-watcher-{}.toml", std::process::id()));
+        let wait = || std::thread::sleep(THEME_POLL_INTERVAL + Duration::from_millis(100));
+        std::fs::write(&path, "alpha = 0.5\n").unwrap();
+
+        let mut watcher = ThemeWatcher::new(&path);
+        assert_eq!(watcher.poll().unwrap().unwrap().alpha, Some(0.5));
+        // Checked at most once per interval, and only reloaded once the file changed
+        assert!(watcher.poll().is_none());
+        wait();
+        assert!(watcher.poll().is_none());
+
+        std::fs::write(&path, "alpha = 0.25\n").unwrap();
+        wait();
+        assert_eq!(watcher.poll().unwrap().unwrap().alpha, Some(0.25));
+
+        // A missing file is reported once
+        std::fs::remove_file(&path).unwrap();
+        wait();
+        assert!(watcher.poll().unwrap().is_err());
+        wait();
+        assert!(watcher.poll().is_none());
+    }
+
+    #[test]
+    fn rust_export_sets_style_fields_and_colors() {
+        let mut theme = Theme::from_roles(&DARK_BLUE);
+        theme.alpha = Some(0.5);
+        theme.window_menu_button_position = Some(Direction::Right);
+        theme.anti_aliased_fill = Some(false);
+
+        let code = theme.to_rust("custom_theme");
+        let lines: Vec<&str> = code.lines().collect();
+        assert_eq!(lines[..6], [
+            "pub fn custom_theme(imgui: &mut imgui::Context) {",
+            "    let style = imgui.style_mut();",
+            "    style.alpha = 0.5;",
+            "    style.window_menu_button_position = Direction::Right;",
+            "    style.anti_aliased_fill = false;",
+            "    let colors = &mut style.colors;",
+        ]);
+        // Colors come in imgui's order, with two decimals like the stock themes used to be written
+        assert_eq!(lines[6], "    colors[ImGuiCol_Text as usize] = [0.95, 0.96, 0.98, 1.00];");
+        assert!(lines.contains(&"    colors[ImGuiCol_WindowBg as usize] = [0.11, 0.15, 0.17, 1.00];"));
+        assert_eq!(lines.len(), 7 + StyleColor::COUNT);
+        assert_eq!(lines.last(), Some(&"}"));
+    }
+}
@@ -0,0 +1,179 @@
+use std::time::Duration;
+use crate::types::TransitionOptions;
+
+/// Tracks how far the ui is between closed (0.0) and open (1.0). Progress moves towards the
+/// target at a constant rate, so reversing halfway through doesn't jump
+#[derive(Debug, Clone)]
+pub struct Transition {
+    pub options: TransitionOptions,
+    open: bool,
+    progress: f32,
+}
+
+impl Transition {
+    /// Starts out fully open or fully closed
+    pub fn new(options: TransitionOptions, open: bool) -> Self {
+        Self { options, open, progress: if open { 1.0 } else { 0.0 } }
+    }
+
+    pub fn set_open(&mut self, open: bool) {
+        self.open = open;
+    }
+
+    /// Advances the transition by a frame
+    pub fn update(&mut self, delta_time: Duration) {
+        let target = if self.open { 1.0 } else { 0.0 };
+        let duration = self.options.duration.as_secs_f32();
+        if duration <= 0.0 {
+            self.progress = target;
+            return;
+        }
+
+        let step = delta_time.as_secs_f32() / duration;
+        self.progress = match self.open {
+            true => (self.progress + step).min(target),
+            false => (self.progress - step).max(target),
+        };
+    }
+
+    /// Progress with the easing curve applied
+    pub fn progress(&self) -> f32 {
+        self.options.easing.apply(self.progress)
+    }
+
+    /// Multiplier for the style alpha
+    pub fn alpha(&self) -> f32 {
+        match self.options.fade {
+            true => self.progress(),
+            false if self.is_visible() => 1.0,
+            false => 0.0,
+        }
+    }
+
+    /// How far windows are moved from where they would be when fully open
+    pub fn offset(&self) -> [f32; 2] {
+        match self.options.slide {
+            Some([x, y]) => {
+                let remaining = 1.0 - self.progress();
+                [x * remaining, y * remaining]
+            }
+            None => [0.0, 0.0],
+        }
+    }
+
+    /// Whether any part of the ui should still be drawn
+    pub fn is_visible(&self) -> bool {
+        self.open || self.progress > 0.0
+    }
+
+    /// Whether input should go through to the window below. Windows keep taking input until they
+    /// have faded out completely
+    pub fn is_clickthrough(&self) -> bool {
+        !self.is_visible()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Easing;
+
+    fn linear() -> TransitionOptions {
+        TransitionOptions::default()
+            .duration(Duration::from_millis(100))
+            .easing(Easing::Linear)
+    }
+
+    fn advance(transition: &mut Transition, millis: u64) {
+        transition.update(Duration::from_millis(millis));
+    }
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!((actual - expected).abs() < 1e-4, "{} is not {}", actual, expected);
+    }
+
+    #[test]
+    fn fades_over_the_duration() {
+        let mut transition = Transition::new(linear(), false);
+        assert_close(transition.alpha(), 0.0);
+
+        transition.set_open(true);
+        advance(&mut transition, 25);
+        assert_close(transition.alpha(), 0.25);
+        advance(&mut transition, 75);
+        assert_close(transition.alpha(), 1.0);
+        advance(&mut transition, 50);
+        assert_close(transition.alpha(), 1.0);
+    }
+
+    #[test]
+    fn progress_follows_the_easing() {
+        let mut transition = Transition::new(linear().easing(Easing::EaseInQuad), false);
+        transition.set_open(true);
+        advance(&mut transition, 50);
+        assert_close(transition.progress(), 0.25);
+        assert_close(transition.alpha(), 0.25);
+    }
+
+    #[test]
+    fn reversing_midway_continues_from_the_current_progress() {
+        let mut transition = Transition::new(linear(), true);
+        transition.set_open(false);
+        advance(&mut transition, 60);
+        assert_close(transition.alpha(), 0.4);
+
+        transition.set_open(true);
+        assert_close(transition.alpha(), 0.4);
+        advance(&mut transition, 30);
+        assert_close(transition.alpha(), 0.7);
+    }
+
+    #[test]
+    fn clickthrough_waits_for_the_fade_out() {
+        let mut transition = Transition::new(linear(), true);
+        transition.set_open(false);
+        assert!(!transition.is_clickthrough());
+        advance(&mut transition, 50);
+        assert!(transition.is_visible());
+        assert!(!transition.is_clickthrough());
+
+        advance(&mut transition, 50);
+        assert!(!transition.is_visible());
+        assert!(transition.is_clickthrough());
+
+        // Input is taken again as soon as the ui starts opening
+        transition.set_open(true);
+        assert!(!transition.is_clickthrough());
+    }
+
+    #[test]
+    fn windows_slide_from_the_offset() {
+        let mut transition = Transition::new(linear().slide(Some([0.0, 100.0])), true);
+        assert_eq!(transition.offset(), [0.0, 0.0]);
+
+        transition.set_open(false);
+        advance(&mut transition, 25);
+        assert_close(transition.offset()[1], 25.0);
+        advance(&mut transition, 75);
+        assert_close(transition.offset()[1], 100.0);
+    }
+
+    #[test]
+    fn without_fade_windows_stay_opaque_while_visible() {
+        let mut transition = Transition::new(linear().fade(false), true);
+        transition.set_open(false);
+        advance(&mut transition, 50);
+        assert_close(transition.alpha(), 1.0);
+        advance(&mut transition, 50);
+        assert_close(transition.alpha(), 0.0);
+    }
+
+    #[test]
+    fn zero_durations_are_instant() {
+        let mut transition = Transition::new(TransitionOptions::none(), true);
+        transition.set_open(false);
+        advance(&mut transition, 0);
+        assert!(transition.is_clickthrough());
+        assert_close(transition.alpha(), 0.0);
+    }
+}
@@ -0,0 +1,509 @@
+//! Keyboard and mouse state built from winit events, so the ui can react to input the same way on
+//! every platform
+
+use crate::keys::{Key, MouseButton};
+use crate::imgui::keybind::Modifiers;
+use winit::event::{Event, WindowEvent, DeviceEvent, ElementState, KeyboardInput, MouseScrollDelta};
+use std::collections::{HashSet, VecDeque};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// How many pixels of a touchpad scroll make up one line of a mouse wheel
+const PIXELS_PER_LINE: f32 = 20.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InputEvent {
+    KeyDown(Key),
+    KeyUp(Key),
+    MouseDown(MouseButton),
+    MouseUp(MouseButton),
+    /// The cursor moved to a position in physical pixels of the overlay window
+    CursorMoved([f32; 2]),
+    /// Raw mouse movement, which keeps coming when the cursor is locked by a game
+    MouseMotion([f32; 2]),
+    /// Scrolled by a number of lines, positive is up and right
+    Scroll([f32; 2]),
+    /// Everything that is held gets released, e.g. when the input source loses access to the devices
+    ReleaseAll,
+}
+
+impl InputEvent {
+    /// Converts a winit event. Keys, buttons, motion and scrolling come from device events since
+    /// they are sent even while the overlay doesn't have focus, which it usually doesn't.
+    ///
+    /// Raw input on Windows only reports the left, middle and right buttons, so the side buttons
+    /// come from [`SideButtons`] there instead.
+    ///
+    /// Everything is released when a device is removed, since its releases never arrive. Losing
+    /// focus is handled by [`Input::handle_winit_event`] instead, since keys may still be held
+    pub fn from_winit<T>(event: &Event<T>) -> Option<Self> {
+        match event {
+            Event::WindowEvent { event: WindowEvent::CursorMoved { position, .. }, .. } => {
+                Some(InputEvent::CursorMoved([position.x as f32, position.y as f32]))
+            }
+            Event::DeviceEvent { event, .. } => match *event {
+                DeviceEvent::Key(input) => {
+                    let key = key_from_input(&input)?;
+                    Some(match input.state {
+                        ElementState::Pressed => InputEvent::KeyDown(key),
+                        ElementState::Released => InputEvent::KeyUp(key),
+                    })
+                }
+                DeviceEvent::Button { button, state } => {
+                    let button = mouse_button_from_id(button)?;
+                    Some(match state {
+                        ElementState::Pressed => InputEvent::MouseDown(button),
+                        ElementState::Released => InputEvent::MouseUp(button),
+                    })
+                }
+                DeviceEvent::MouseMotion { delta: (x, y) } => Some(InputEvent::MouseMotion([x as f32, y as f32])),
+                DeviceEvent::MouseWheel { delta } => Some(InputEvent::Scroll(match delta {
+                    MouseScrollDelta::LineDelta(x, y) => [x, y],
+                    MouseScrollDelta::PixelDelta(position) => {
+                        [position.x as f32 / PIXELS_PER_LINE, position.y as f32 / PIXELS_PER_LINE]
+                    }
+                })),
+                DeviceEvent::Removed => Some(InputEvent::ReleaseAll),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+fn key_from_input(input: &KeyboardInput) -> Option<Key> {
+    input.virtual_keycode.and_then(Key::from_winit).or_else(|| key_from_scancode(input.scancode))
+}
+
+/// Keys without a virtual key code still have the evdev scancode on Linux
+#[cfg(all(unix, not(target_os = "macos")))]
+fn key_from_scancode(scancode: u32) -> Option<Key> {
+    Key::from_scancode(scancode)
+}
+
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+fn key_from_scancode(_scancode: u32) -> Option<Key> {
+    None
+}
+
+/// Button ids are the same as X11 button numbers. winit uses the same ones on Windows, but only
+/// sends 1 to 3 there
+fn mouse_button_from_id(id: u32) -> Option<MouseButton> {
+    match id {
+        1 => Some(MouseButton::Left),
+        2 => Some(MouseButton::Middle),
+        3 => Some(MouseButton::Right),
+        8 => Some(MouseButton::X1),
+        9 => Some(MouseButton::X2),
+        _ => None,
+    }
+}
+
+/// Produces input events, e.g. to replay recorded input or to fake it in tests
+pub trait InputSource {
+    /// Adds every event since the last poll to `events`
+    fn poll(&mut self, events: &mut Vec<InputEvent>);
+}
+
+/// An input source that sends the events queued on it. Clones share the queue, so one clone can be
+/// given to the renderer while another one queues events
+#[derive(Debug, Clone, Default)]
+pub struct FakeInput(Rc<RefCell<VecDeque<InputEvent>>>);
+
+impl FakeInput {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&self, event: InputEvent) {
+        self.0.borrow_mut().push_back(event);
+    }
+
+    pub fn press(&self, key: Key) {
+        self.push(InputEvent::KeyDown(key));
+    }
+
+    pub fn release(&self, key: Key) {
+        self.push(InputEvent::KeyUp(key));
+    }
+
+    /// Presses and releases `key`, which shows up as pressed and released in the same frame
+    pub fn tap(&self, key: Key) {
+        self.press(key);
+        self.release(key);
+    }
+
+    pub fn press_button(&self, button: MouseButton) {
+        self.push(InputEvent::MouseDown(button));
+    }
+
+    pub fn release_button(&self, button: MouseButton) {
+        self.push(InputEvent::MouseUp(button));
+    }
+
+    pub fn click(&self, button: MouseButton) {
+        self.press_button(button);
+        self.release_button(button);
+    }
+
+    pub fn move_cursor(&self, position: [f32; 2]) {
+        self.push(InputEvent::CursorMoved(position));
+    }
+
+    pub fn move_mouse(&self, delta: [f32; 2]) {
+        self.push(InputEvent::MouseMotion(delta));
+    }
+
+    pub fn scroll(&self, lines: [f32; 2]) {
+        self.push(InputEvent::Scroll(lines));
+    }
+}
+
+impl InputSource for FakeInput {
+    fn poll(&mut self, events: &mut Vec<InputEvent>) {
+        events.extend(self.0.borrow_mut().drain(..));
+    }
+}
+
+/// Polls the side mouse buttons, which Windows raw input doesn't report. The renderer adds it on
+/// Windows
+#[cfg(windows)]
+#[derive(Debug, Default)]
+pub struct SideButtons {
+    down: [bool; 2],
+}
+
+#[cfg(windows)]
+impl InputSource for SideButtons {
+    fn poll(&mut self, events: &mut Vec<InputEvent>) {
+        for (down, &button) in self.down.iter_mut().zip(&[MouseButton::X1, MouseButton::X2]) {
+            let now = crate::keys::is_mouse_button_down(button);
+            if now != *down {
+                events.push(if now { InputEvent::MouseDown(button) } else { InputEvent::MouseUp(button) });
+                *down = now;
+            }
+        }
+    }
+}
+
+/// The state of the keyboard and mouse for the current frame. Events are collected between frames,
+/// and `end_frame` clears what only lasts one frame
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Input {
+    keys_down: HashSet<Key>,
+    keys_pressed: HashSet<Key>,
+    keys_released: HashSet<Key>,
+    buttons_down: HashSet<MouseButton>,
+    buttons_pressed: HashSet<MouseButton>,
+    buttons_released: HashSet<MouseButton>,
+    cursor_position: Option<[f32; 2]>,
+    mouse_delta: [f32; 2],
+    scroll: [f32; 2],
+}
+
+impl Input {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn handle_event(&mut self, event: InputEvent) {
+        match event {
+            InputEvent::KeyDown(key) => {
+                // Held keys repeat, but only the first one is a press
+                if self.keys_down.insert(key) {
+                    self.keys_pressed.insert(key);
+                }
+            }
+            InputEvent::KeyUp(key) => {
+                if self.keys_down.remove(&key) {
+                    self.keys_released.insert(key);
+                }
+            }
+            InputEvent::MouseDown(button) => {
+                if self.buttons_down.insert(button) {
+                    self.buttons_pressed.insert(button);
+                }
+            }
+            InputEvent::MouseUp(button) => {
+                if self.buttons_down.remove(&button) {
+                    self.buttons_released.insert(button);
+                }
+            }
+            InputEvent::CursorMoved(position) => self.cursor_position = Some(position),
+            InputEvent::MouseMotion([x, y]) => {
+                self.mouse_delta[0] += x;
+                self.mouse_delta[1] += y;
+            }
+            InputEvent::Scroll([x, y]) => {
+                self.scroll[0] += x;
+                self.scroll[1] += y;
+            }
+            InputEvent::ReleaseAll => {
+                self.keys_released.extend(self.keys_down.drain());
+                self.buttons_released.extend(self.buttons_down.drain());
+            }
+        }
+    }
+
+    /// Handles a winit event if it is about input. When the overlay loses focus, releases can get
+    /// lost, so held keys and buttons are checked against the platform
+    pub fn handle_winit_event<T>(&mut self, event: &Event<T>) {
+        if let Event::WindowEvent { event: WindowEvent::Focused(false), .. } = event {
+            self.sync_held(crate::keys::is_key_down, crate::keys::is_mouse_button_down);
+        }
+        if let Some(event) = InputEvent::from_winit(event) {
+            self.handle_event(event);
+        }
+    }
+
+    /// Handles every event `source` has queued
+    pub fn poll(&mut self, source: &mut dyn InputSource) {
+        let mut events = Vec::new();
+        source.poll(&mut events);
+        for event in events {
+            self.handle_event(event);
+        }
+    }
+
+    /// Releases the held keys and buttons that `is_key_down` and `is_mouse_button_down` say are up.
+    /// Unlike [`InputEvent::ReleaseAll`], keys that are still held stay held, so the key repeats
+    /// that keep coming aren't taken as new presses
+    pub fn sync_held(&mut self, is_key_down: impl Fn(Key) -> bool, is_mouse_button_down: impl Fn(MouseButton) -> bool) {
+        let keys: Vec<Key> = self.keys_down.iter().copied().filter(|&key| !is_key_down(key)).collect();
+        for key in keys {
+            self.handle_event(InputEvent::KeyUp(key));
+        }
+        let buttons: Vec<MouseButton> = self.buttons_down.iter().copied().filter(|&button| !is_mouse_button_down(button)).collect();
+        for button in buttons {
+            self.handle_event(InputEvent::MouseUp(button));
+        }
+    }
+
+    /// Forgets presses, releases, motion and scrolling. Held keys stay held
+    pub fn end_frame(&mut self) {
+        self.keys_pressed.clear();
+        self.keys_released.clear();
+        self.buttons_pressed.clear();
+        self.buttons_released.clear();
+        self.mouse_delta = [0.0, 0.0];
+        self.scroll = [0.0, 0.0];
+    }
+
+    pub fn is_key_down(&self, key: Key) -> bool {
+        self.keys_down.contains(&key)
+    }
+
+    /// Whether `key` went down since the last frame
+    pub fn is_key_pressed(&self, key: Key) -> bool {
+        self.keys_pressed.contains(&key)
+    }
+
+    /// Whether `key` went up since the last frame
+    pub fn is_key_released(&self, key: Key) -> bool {
+        self.keys_released.contains(&key)
+    }
+
+    pub fn keys_down(&self) -> impl Iterator<Item = Key> + '_ {
+        self.keys_down.iter().copied()
+    }
+
+    pub fn keys_pressed(&self) -> impl Iterator<Item = Key> + '_ {
+        self.keys_pressed.iter().copied()
+    }
+
+    pub fn is_mouse_down(&self, button: MouseButton) -> bool {
+        self.buttons_down.contains(&button)
+    }
+
+    pub fn is_mouse_pressed(&self, button: MouseButton) -> bool {
+        self.buttons_pressed.contains(&button)
+    }
+
+    pub fn is_mouse_released(&self, button: MouseButton) -> bool {
+        self.buttons_released.contains(&button)
+    }
+
+    /// Shift, ctrl and alt on either side of the keyboard
+    pub fn modifiers(&self) -> Modifiers {
+        Modifiers {
+            ctrl: self.is_key_down(Key::LControl) || self.is_key_down(Key::RControl),
+            shift: self.is_key_down(Key::LShift) || self.is_key_down(Key::RShift),
+            alt: self.is_key_down(Key::LAlt) || self.is_key_down(Key::RAlt),
+        }
+    }
+
+    /// The last position of the cursor over the overlay window, in physical pixels
+    pub fn cursor_position(&self) -> Option<[f32; 2]> {
+        self.cursor_position
+    }
+
+    /// How far the mouse moved this frame in raw device units
+    pub fn mouse_delta(&self) -> [f32; 2] {
+        self.mouse_delta
+    }
+
+    /// How many lines were scrolled this frame
+    pub fn scroll(&self) -> [f32; 2] {
+        self.scroll
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn poll(input: &mut Input, source: &FakeInput) {
+        input.poll(&mut source.clone());
+    }
+
+    #[test]
+    fn key_presses_and_releases_last_one_frame() {
+        let (mut input, fake) = (Input::new(), FakeInput::new());
+
+        fake.press(Key::A);
+        poll(&mut input, &fake);
+        assert!(input.is_key_down(Key::A) && input.is_key_pressed(Key::A) && !input.is_key_released(Key::A));
+
+        input.end_frame();
+        poll(&mut input, &fake);
+        assert!(input.is_key_down(Key::A) && !input.is_key_pressed(Key::A));
+
+        input.end_frame();
+        fake.release(Key::A);
+        poll(&mut input, &fake);
+        assert!(!input.is_key_down(Key::A) && input.is_key_released(Key::A));
+
+        input.end_frame();
+        assert!(!input.is_key_released(Key::A));
+    }
+
+    #[test]
+    fn taps_are_pressed_and_released_in_the_same_frame() {
+        let (mut input, fake) = (Input::new(), FakeInput::new());
+        fake.tap(Key::Space);
+        fake.click(MouseButton::Right);
+        poll(&mut input, &fake);
+
+        assert!(!input.is_key_down(Key::Space));
+        assert!(input.is_key_pressed(Key::Space) && input.is_key_released(Key::Space));
+        assert!(!input.is_mouse_down(MouseButton::Right));
+        assert!(input.is_mouse_pressed(MouseButton::Right) && input.is_mouse_released(MouseButton::Right));
+    }
+
+    #[test]
+    fn repeated_key_downs_are_one_press() {
+        let (mut input, fake) = (Input::new(), FakeInput::new());
+        fake.press(Key::W);
+        poll(&mut input, &fake);
+        input.end_frame();
+
+        // Held keys keep sending key downs
+        fake.press(Key::W);
+        fake.press(Key::W);
+        fake.press_button(MouseButton::Left);
+        poll(&mut input, &fake);
+        input.end_frame();
+        fake.press_button(MouseButton::Left);
+        poll(&mut input, &fake);
+
+        assert!(input.is_key_down(Key::W) && !input.is_key_pressed(Key::W));
+        assert!(input.is_mouse_down(MouseButton::Left) && !input.is_mouse_pressed(MouseButton::Left));
+        // A release without a press is ignored too
+        fake.release(Key::Q);
+        poll(&mut input, &fake);
+        assert!(!input.is_key_released(Key::Q));
+    }
+
+    #[test]
+    fn release_all_releases_everything_held() {
+        let (mut input, fake) = (Input::new(), FakeInput::new());
+        fake.press(Key::LControl);
+        fake.press(Key::C);
+        fake.press_button(MouseButton::X1);
+        poll(&mut input, &fake);
+        input.end_frame();
+
+        fake.push(InputEvent::ReleaseAll);
+        poll(&mut input, &fake);
+        assert_eq!(input.keys_down().count(), 0);
+        assert!(!input.is_mouse_down(MouseButton::X1));
+        assert!(input.is_key_released(Key::LControl) && input.is_key_released(Key::C));
+        assert!(input.is_mouse_released(MouseButton::X1));
+        assert_eq!(input.modifiers(), Modifiers::default());
+    }
+
+    #[test]
+    fn syncing_keeps_keys_that_are_still_held() {
+        use crate::imgui::keybind::{Keybind, KeybindMode};
+
+        let (mut input, fake) = (Input::new(), FakeInput::new());
+        let mut toggle = Keybind::key(Key::X).with_mode(KeybindMode::Toggle);
+        fake.press(Key::X);
+        fake.press(Key::LShift);
+        fake.press_button(MouseButton::Right);
+        poll(&mut input, &fake);
+        assert!(toggle.is_active(&input));
+        input.end_frame();
+
+        // Focus is lost while X and the right button are held, and shift was let go meanwhile
+        input.sync_held(|key| key == Key::X, |button| button == MouseButton::Right);
+        assert!(input.is_key_down(Key::X) && !input.is_key_released(Key::X));
+        assert!(input.is_mouse_down(MouseButton::Right));
+        assert!(!input.is_key_down(Key::LShift) && input.is_key_released(Key::LShift));
+        assert!(toggle.is_active(&input));
+        input.end_frame();
+
+        // The key repeat that follows is neither a press nor flips the toggle
+        fake.press(Key::X);
+        poll(&mut input, &fake);
+        assert!(!input.is_key_pressed(Key::X));
+        assert!(toggle.is_active(&input));
+        input.end_frame();
+
+        fake.release(Key::X);
+        poll(&mut input, &fake);
+        assert!(toggle.is_active(&input));
+        input.end_frame();
+        fake.press(Key::X);
+        poll(&mut input, &fake);
+        assert!(!toggle.is_active(&input));
+    }
+
+    #[test]
+    fn motion_and_scrolling_add_up_until_the_end_of_the_frame() {
+        let (mut input, fake) = (Input::new(), FakeInput::new());
+        fake.move_mouse([1.0, 2.0]);
+        fake.move_mouse([3.0, -1.0]);
+        fake.scroll([0.0, 1.0]);
+        fake.scroll([0.0, 0.5]);
+        fake.move_cursor([10.0, 20.0]);
+        poll(&mut input, &fake);
+        assert_eq!(input.mouse_delta(), [4.0, 1.0]);
+        assert_eq!(input.scroll(), [0.0, 1.5]);
+
+        input.end_frame();
+        assert_eq!(input.mouse_delta(), [0.0, 0.0]);
+        assert_eq!(input.scroll(), [0.0, 0.0]);
+        assert_eq!(input.cursor_position(), Some([10.0, 20.0]));
+    }
+
+    #[test]
+    fn modifiers_come_from_either_side() {
+        let (mut input, fake) = (Input::new(), FakeInput::new());
+        fake.press(Key::RControl);
+        fake.press(Key::LShift);
+        poll(&mut input, &fake);
+        assert_eq!(input.modifiers(), Modifiers { ctrl: true, shift: true, alt: false });
+    }
+
+    #[test]
+    fn mouse_button_ids() {
+        assert_eq!(mouse_button_from_id(1), Some(MouseButton::Left));
+        assert_eq!(mouse_button_from_id(2), Some(MouseButton::Middle));
+        assert_eq!(mouse_button_from_id(3), Some(MouseButton::Right));
+        assert_eq!(mouse_button_from_id(8), Some(MouseButton::X1));
+        assert_eq!(mouse_button_from_id(9), Some(MouseButton::X2));
+        // 4 to 7 are scrolling on X11
+        assert_eq!(mouse_button_from_id(4), None);
+    }
+}
@@ -0,0 +1,660 @@
+//! Keyboard keys and mouse buttons that mean the same thing on every platform, so keybinds saved on
+//! Windows load on Linux and the other way around
+
+use winit::event::VirtualKeyCode;
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
+use serde::de::{self, Visitor};
+use anyhow::{anyhow, Result};
+use std::borrow::Cow;
+use std::fmt;
+use std::str::FromStr;
+#[cfg(windows)]
+use winapi::um::winuser::{GetAsyncKeyState, GetKeyNameTextW, MapVirtualKeyW};
+
+macro_rules! keys {
+    ($($key:ident => $name:expr, $vkey:expr, $winit:ident, $scancode:expr;)*) => {
+        /// A physical key, named after what it prints on a US layout
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        pub enum Key {
+            $($key,)*
+        }
+
+        impl Key {
+            pub const ALL: &'static [Key] = &[$(Key::$key,)*];
+
+            /// The English name, e.g. `Page Up`. Use `display_name` for text shown to users
+            pub fn name(self) -> &'static str {
+                match self {
+                    $(Key::$key => $name,)*
+                }
+            }
+
+            /// The name of the variant, e.g. `PageUp`. This is what gets serialized
+            pub fn ident(self) -> &'static str {
+                match self {
+                    $(Key::$key => stringify!($key),)*
+                }
+            }
+
+            /// The Windows virtual key code
+            pub fn to_vkey(self) -> Option<i32> {
+                match self {
+                    $(Key::$key => $vkey,)*
+                }
+            }
+
+            pub fn to_winit(self) -> VirtualKeyCode {
+                match self {
+                    $(Key::$key => VirtualKeyCode::$winit,)*
+                }
+            }
+
+            pub fn from_winit(key: VirtualKeyCode) -> Option<Self> {
+                match key {
+                    $(VirtualKeyCode::$winit => Some(Key::$key),)*
+                    _ => None,
+                }
+            }
+
+            /// The Linux evdev scancode. It is the same as the PS/2 set 1 scancode for most of the
+            /// main keyboard, and 8 less than the X11 keycode
+            pub fn scancode(self) -> Option<u32> {
+                match self {
+                    $(Key::$key => $scancode,)*
+                }
+            }
+        }
+    };
+}
+
+keys! {
+    A => "A", Some(0x41), A, Some(30);
+    B => "B", Some(0x42), B, Some(48);
+    C => "C", Some(0x43), C, Some(46);
+    D => "D", Some(0x44), D, Some(32);
+    E => "E", Some(0x45), E, Some(18);
+    F => "F", Some(0x46), F, Some(33);
+    G => "G", Some(0x47), G, Some(34);
+    H => "H", Some(0x48), H, Some(35);
+    I => "I", Some(0x49), I, Some(23);
+    J => "J", Some(0x4A), J, Some(36);
+    K => "K", Some(0x4B), K, Some(37);
+    L => "L", Some(0x4C), L, Some(38);
+    M => "M", Some(0x4D), M, Some(50);
+    N => "N", Some(0x4E), N, Some(49);
+    O => "O", Some(0x4F), O, Some(24);
+    P => "P", Some(0x50), P, Some(25);
+    Q => "Q", Some(0x51), Q, Some(16);
+    R => "R", Some(0x52), R, Some(19);
+    S => "S", Some(0x53), S, Some(31);
+    T => "T", Some(0x54), T, Some(20);
+    U => "U", Some(0x55), U, Some(22);
+    V => "V", Some(0x56), V, Some(47);
+    W => "W", Some(0x57), W, Some(17);
+    X => "X", Some(0x58), X, Some(45);
+    Y => "Y", Some(0x59), Y, Some(21);
+    Z => "Z", Some(0x5A), Z, Some(44);
+    Key0 => "0", Some(0x30), Key0, Some(11);
+    Key1 => "1", Some(0x31), Key1, Some(2);
+    Key2 => "2", Some(0x32), Key2, Some(3);
+    Key3 => "3", Some(0x33), Key3, Some(4);
+    Key4 => "4", Some(0x34), Key4, Some(5);
+    Key5 => "5", Some(0x35), Key5, Some(6);
+    Key6 => "6", Some(0x36), Key6, Some(7);
+    Key7 => "7", Some(0x37), Key7, Some(8);
+    Key8 => "8", Some(0x38), Key8, Some(9);
+    Key9 => "9", Some(0x39), Key9, Some(10);
+    F1 => "F1", Some(0x70), F1, Some(59);
+    F2 => "F2", Some(0x71), F2, Some(60);
+    F3 => "F3", Some(0x72), F3, Some(61);
+    F4 => "F4", Some(0x73), F4, Some(62);
+    F5 => "F5", Some(0x74), F5, Some(63);
+    F6 => "F6", Some(0x75), F6, Some(64);
+    F7 => "F7", Some(0x76), F7, Some(65);
+    F8 => "F8", Some(0x77), F8, Some(66);
+    F9 => "F9", Some(0x78), F9, Some(67);
+    F10 => "F10", Some(0x79), F10, Some(68);
+    F11 => "F11", Some(0x7A), F11, Some(87);
+    F12 => "F12", Some(0x7B), F12, Some(88);
+    F13 => "F13", Some(0x7C), F13, Some(183);
+    F14 => "F14", Some(0x7D), F14, Some(184);
+    F15 => "F15", Some(0x7E), F15, Some(185);
+    F16 => "F16", Some(0x7F), F16, Some(186);
+    F17 => "F17", Some(0x80), F17, Some(187);
+    F18 => "F18", Some(0x81), F18, Some(188);
+    F19 => "F19", Some(0x82), F19, Some(189);
+    F20 => "F20", Some(0x83), F20, Some(190);
+    F21 => "F21", Some(0x84), F21, Some(191);
+    F22 => "F22", Some(0x85), F22, Some(192);
+    F23 => "F23", Some(0x86), F23, Some(193);
+    F24 => "F24", Some(0x87), F24, Some(194);
+    Escape => "Escape", Some(0x1B), Escape, Some(1);
+    Tab => "Tab", Some(0x09), Tab, Some(15);
+    CapsLock => "Caps Lock", Some(0x14), Capital, Some(58);
+    Space => "Space", Some(0x20), Space, Some(57);
+    Enter => "Enter", Some(0x0D), Return, Some(28);
+    Backspace => "Backspace", Some(0x08), Back, Some(14);
+    Insert => "Insert", Some(0x2D), Insert, Some(110);
+    Delete => "Delete", Some(0x2E), Delete, Some(111);
+    Home => "Home", Some(0x24), Home, Some(102);
+    End => "End", Some(0x23), End, Some(107);
+    PageUp => "Page Up", Some(0x21), PageUp, Some(104);
+    PageDown => "Page Down", Some(0x22), PageDown, Some(109);
+    Left => "Left", Some(0x25), Left, Some(105);
+    Up => "Up", Some(0x26), Up, Some(103);
+    Right => "Right", Some(0x27), Right, Some(106);
+    Down => "Down", Some(0x28), Down, Some(108);
+    PrintScreen => "Print Screen", Some(0x2C), Snapshot, Some(99);
+    ScrollLock => "Scroll Lock", Some(0x91), Scroll, Some(70);
+    Pause => "Pause", Some(0x13), Pause, Some(119);
+    NumLock => "Num Lock", Some(0x90), Numlock, Some(69);
+    Numpad0 => "Numpad 0", Some(0x60), Numpad0, Some(82);
+    Numpad1 => "Numpad 1", Some(0x61), Numpad1, Some(79);
+    Numpad2 => "Numpad 2", Some(0x62), Numpad2, Some(80);
+    Numpad3 => "Numpad 3", Some(0x63), Numpad3, Some(81);
+    Numpad4 => "Numpad 4", Some(0x64), Numpad4, Some(75);
+    Numpad5 => "Numpad 5", Some(0x65), Numpad5, Some(76);
+    Numpad6 => "Numpad 6", Some(0x66), Numpad6, Some(77);
+    Numpad7 => "Numpad 7", Some(0x67), Numpad7, Some(71);
+    Numpad8 => "Numpad 8", Some(0x68), Numpad8, Some(72);
+    Numpad9 => "Numpad 9", Some(0x69), Numpad9, Some(73);
+    NumpadAdd => "Numpad +", Some(0x6B), NumpadAdd, Some(78);
+    NumpadSubtract => "Numpad -", Some(0x6D), NumpadSubtract, Some(74);
+    NumpadMultiply => "Numpad *", Some(0x6A), NumpadMultiply, Some(55);
+    NumpadDivide => "Numpad /", Some(0x6F), NumpadDivide, Some(98);
+    NumpadDecimal => "Numpad .", Some(0x6E), NumpadDecimal, Some(83);
+    NumpadComma => "Numpad ,", Some(0x6C), NumpadComma, Some(121);
+    NumpadEnter => "Numpad Enter", Some(0x0D), NumpadEnter, Some(96);
+    NumpadEquals => "Numpad =", Some(0x92), NumpadEquals, Some(117);
+    LShift => "Left Shift", Some(0xA0), LShift, Some(42);
+    RShift => "Right Shift", Some(0xA1), RShift, Some(54);
+    LControl => "Left Ctrl", Some(0xA2), LControl, Some(29);
+    RControl => "Right Ctrl", Some(0xA3), RControl, Some(97);
+    LAlt => "Left Alt", Some(0xA4), LAlt, Some(56);
+    RAlt => "Right Alt", Some(0xA5), RAlt, Some(100);
+    LSuper => "Left Super", Some(0x5B), LWin, Some(125);
+    RSuper => "Right Super", Some(0x5C), RWin, Some(126);
+    Menu => "Menu", Some(0x5D), Apps, Some(127);
+    Grave => "`", Some(0xC0), Grave, Some(41);
+    Minus => "-", Some(0xBD), Minus, Some(12);
+    Equals => "=", Some(0xBB), Equals, Some(13);
+    LeftBracket => "[", Some(0xDB), LBracket, Some(26);
+    RightBracket => "]", Some(0xDD), RBracket, Some(27);
+    Backslash => "\\", Some(0xDC), Backslash, Some(43);
+    Semicolon => ";", Some(0xBA), Semicolon, Some(39);
+    Apostrophe => "'", Some(0xDE), Apostrophe, Some(40);
+    Comma => ",", Some(0xBC), Comma, Some(51);
+    Period => ".", Some(0xBE), Period, Some(52);
+    Slash => "/", Some(0xBF), Slash, Some(53);
+    IntlBackslash => "Intl \\", Some(0xE2), OEM102, Some(86);
+    VolumeUp => "Volume Up", Some(0xAF), VolumeUp, Some(115);
+    VolumeDown => "Volume Down", Some(0xAE), VolumeDown, Some(114);
+    Mute => "Mute", Some(0xAD), Mute, Some(113);
+    MediaPlayPause => "Play/Pause", Some(0xB3), PlayPause, Some(164);
+    MediaStop => "Media Stop", Some(0xB2), MediaStop, Some(166);
+    MediaNext => "Next Track", Some(0xB0), NextTrack, Some(163);
+    MediaPrevious => "Previous Track", Some(0xB1), PrevTrack, Some(165);
+    BrowserBack => "Browser Back", Some(0xA6), WebBack, Some(158);
+    BrowserForward => "Browser Forward", Some(0xA7), WebForward, Some(159);
+    BrowserRefresh => "Browser Refresh", Some(0xA8), WebRefresh, Some(173);
+    BrowserHome => "Browser Home", Some(0xAC), WebHome, Some(172);
+    BrowserSearch => "Browser Search", Some(0xAA), WebSearch, Some(217);
+    Mail => "Mail", Some(0xB4), Mail, Some(155);
+    Calculator => "Calculator", Some(0xB7), Calculator, Some(140);
+    Sleep => "Sleep", Some(0x5F), Sleep, Some(142);
+}
+
+/// Other names `Key::from_str` accepts, besides the name and ident of every key
+const ALIASES: &[(&str, Key)] = &[
+    ("esc", Key::Escape),
+    ("return", Key::Enter),
+    ("back", Key::Backspace),
+    ("ins", Key::Insert),
+    ("del", Key::Delete),
+    ("pgup", Key::PageUp),
+    ("pgdn", Key::PageDown),
+    ("pgdown", Key::PageDown),
+    ("prtsc", Key::PrintScreen),
+    ("printscr", Key::PrintScreen),
+    ("snapshot", Key::PrintScreen),
+    ("scroll", Key::ScrollLock),
+    ("capital", Key::CapsLock),
+    ("caps", Key::CapsLock),
+    ("numlk", Key::NumLock),
+    ("shift", Key::LShift),
+    ("ctrl", Key::LControl),
+    ("control", Key::LControl),
+    ("lctrl", Key::LControl),
+    ("rctrl", Key::RControl),
+    ("alt", Key::LAlt),
+    ("altgr", Key::RAlt),
+    ("super", Key::LSuper),
+    ("win", Key::LSuper),
+    ("lwin", Key::LSuper),
+    ("rwin", Key::RSuper),
+    ("meta", Key::LSuper),
+    ("apps", Key::Menu),
+    ("tilde", Key::Grave),
+    ("backtick", Key::Grave),
+    ("plus", Key::Equals),
+    ("quote", Key::Apostrophe),
+    ("oem102", Key::IntlBackslash),
+];
+
+impl Key {
+    /// Maps a Windows virtual key code to a key. The generic shift, ctrl and alt codes map to the
+    /// left key, and enter always maps to `Enter` since the numpad one has the same code
+    pub fn from_vkey(vkey: i32) -> Option<Self> {
+        match vkey {
+            0x10 => Some(Key::LShift),
+            0x11 => Some(Key::LControl),
+            0x12 => Some(Key::LAlt),
+            _ => Self::ALL.iter().copied().find(|key| key.to_vkey() == Some(vkey)),
+        }
+    }
+
+    pub fn from_scancode(scancode: u32) -> Option<Self> {
+        Self::ALL.iter().copied().find(|key| key.scancode() == Some(scancode))
+    }
+
+    /// Shift, ctrl and alt on either side
+    pub fn is_modifier(self) -> bool {
+        matches!(self, Key::LShift | Key::RShift | Key::LControl | Key::RControl | Key::LAlt | Key::RAlt)
+    }
+
+    /// The name of the key in the language and keyboard layout of the user, e.g. `Strg` for ctrl on
+    /// a German Windows or `Ö` for the semicolon key on a German X11 layout. Falls back to `name`
+    /// where the OS has no name for the key
+    pub fn display_name(self) -> Cow<'static, str> {
+        #[cfg(any(windows, all(unix, not(target_os = "macos"))))]
+        if let Some(name) = self.localized_name() {
+            return Cow::Owned(name);
+        }
+        Cow::Borrowed(self.name())
+    }
+
+    /// X11 only names keys by the symbol they type, so only keys that type a character get a name
+    #[cfg(all(unix, not(target_os = "macos")))]
+    fn localized_name(self) -> Option<String> {
+        x11::localized_name(self)
+    }
+
+    #[cfg(windows)]
+    fn localized_name(self) -> Option<String> {
+        const MAPVK_VK_TO_VSC: u32 = 0;
+        let scancode = unsafe { MapVirtualKeyW(self.to_vkey()? as u32, MAPVK_VK_TO_VSC) };
+        if scancode == 0 {
+            return None;
+        }
+
+        // Bit 24 tells apart keys that share a scancode, like the arrows and the numpad
+        let lparam = (scancode as i32 & 0xFF) << 16 | (self.is_extended() as i32) << 24;
+        let mut buffer = [0u16; 64];
+        let len = unsafe { GetKeyNameTextW(lparam, buffer.as_mut_ptr(), buffer.len() as i32) };
+        if len <= 0 {
+            return None;
+        }
+        Some(String::from_utf16_lossy(&buffer[..len as usize]))
+    }
+
+    /// Keys that are sent with an 0xE0 prefix
+    #[cfg(windows)]
+    fn is_extended(self) -> bool {
+        matches!(self,
+            Key::Insert | Key::Delete | Key::Home | Key::End | Key::PageUp | Key::PageDown
+            | Key::Left | Key::Up | Key::Right | Key::Down
+            | Key::RControl | Key::RAlt | Key::LSuper | Key::RSuper | Key::Menu
+            | Key::NumLock | Key::NumpadDivide | Key::NumpadEnter | Key::PrintScreen)
+    }
+}
+
+/// Ignores case, spaces and underscores, so `page up`, `PageUp` and `PAGE_UP` all work
+fn normalize(name: &str) -> String {
+    name.chars()
+        .filter(|c| !c.is_whitespace() && *c != '_')
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Parses the English name, the ident, a common alias like `esc` or `pgdn`, or the localized name
+impl FromStr for Key {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        let name = normalize(s);
+
+        Self::ALL.iter().copied()
+            .find(|key| normalize(key.name()) == name || normalize(key.ident()) == name)
+            .or_else(|| ALIASES.iter().find(|(alias, _)| *alias == name).map(|&(_, key)| key))
+            .or_else(|| Self::ALL.iter().copied().find(|key| normalize(&key.display_name()) == name))
+            .ok_or_else(|| anyhow!("Unknown key {:?}", s))
+    }
+}
+
+/// Formats with the localized `display_name`
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.display_name())
+    }
+}
+
+impl Serialize for Key {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.ident())
+    }
+}
+
+/// Deserializes from any string `from_str` accepts, or from a Windows virtual key code
+impl<'de> Deserialize<'de> for Key {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        struct KeyVisitor;
+
+        impl<'de> Visitor<'de> for KeyVisitor {
+            type Value = Key;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a key name or a virtual key code")
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> std::result::Result<Key, E> {
+                v.parse().map_err(E::custom)
+            }
+
+            fn visit_i64<E: de::Error>(self, v: i64) -> std::result::Result<Key, E> {
+                Key::from_vkey(v as i32).ok_or_else(|| E::custom(format!("Unknown virtual key code {}", v)))
+            }
+
+            fn visit_u64<E: de::Error>(self, v: u64) -> std::result::Result<Key, E> {
+                self.visit_i64(v as i64)
+            }
+        }
+
+        deserializer.deserialize_any(KeyVisitor)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+    /// Usually the back button
+    X1,
+    /// Usually the forward button
+    X2,
+}
+
+impl MouseButton {
+    pub const ALL: [MouseButton; 5] = [MouseButton::Left, MouseButton::Right, MouseButton::Middle, MouseButton::X1, MouseButton::X2];
+
+    /// The Windows virtual key code
+    pub fn to_vkey(self) -> i32 {
+        match self {
+            MouseButton::Left => 0x01,
+            MouseButton::Right => 0x02,
+            MouseButton::Middle => 0x04,
+            MouseButton::X1 => 0x05,
+            MouseButton::X2 => 0x06,
+        }
+    }
+
+    pub fn from_vkey(vkey: i32) -> Option<Self> {
+        Self::ALL.iter().copied().find(|button| button.to_vkey() == vkey)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            MouseButton::Left => "Mouse1",
+            MouseButton::Right => "Mouse2",
+            MouseButton::Middle => "Mouse3",
+            MouseButton::X1 => "Mouse4",
+            MouseButton::X2 => "Mouse5",
+        }
+    }
+}
+
+impl fmt::Display for MouseButton {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Whether `key` is held right now, even while another window has focus
+#[cfg(windows)]
+pub fn is_key_down(key: Key) -> bool {
+    key.to_vkey().map_or(false, is_vkey_down)
+}
+
+/// Whether `button` is held right now, even while another window has focus
+#[cfg(windows)]
+pub fn is_mouse_button_down(button: MouseButton) -> bool {
+    is_vkey_down(button.to_vkey())
+}
+
+#[cfg(windows)]
+fn is_vkey_down(vkey: i32) -> bool {
+    unsafe { GetAsyncKeyState(vkey) as u16 & 0x8000 != 0 }
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+pub use self::x11::{is_key_down, is_mouse_button_down};
+
+#[cfg(all(unix, not(target_os = "macos")))]
+mod x11 {
+    use super::{Key, MouseButton};
+    use winit::platform::unix::x11::XConnection;
+    use x11_dl::xlib::XkbStateRec;
+    use std::os::raw::{c_char, c_int, c_uint, c_ulong};
+    use std::mem::MaybeUninit;
+
+    /// Device id of the core keyboard for the Xkb functions
+    const XKB_USE_CORE_KBD: c_uint = 0x0100;
+
+    thread_local! {
+        /// A connection of its own, so the state can be queried from any thread
+        static CONNECTION: Option<XConnection> = XConnection::new(None)
+            .map_err(|e| log::error!("Could not connect to the X server to read the keyboard: {:?}", e))
+            .ok();
+    }
+
+    /// Whether `key` is held right now, even while another window has focus
+    pub fn is_key_down(key: Key) -> bool {
+        let scancode = match key.scancode() {
+            Some(scancode) => scancode as usize,
+            None => return false,
+        };
+        // X11 keycodes are the evdev scancodes offset by 8
+        let keycode = scancode + 8;
+
+        CONNECTION.with(|connection| {
+            let connection = match connection {
+                Some(connection) => connection,
+                None => return false,
+            };
+            let mut keymap = [0 as c_char; 32];
+            unsafe { (connection.xlib.XQueryKeymap)(connection.display, keymap.as_mut_ptr()) };
+            keycode < 256 && keymap[keycode / 8] as u8 & (1 << (keycode % 8)) != 0
+        })
+    }
+
+    /// The character `key` types in the current layout and group, uppercased like the labels on
+    /// keycaps. `None` for keys that don't type anything, like the arrows or dead keys
+    pub fn localized_name(key: Key) -> Option<String> {
+        let keycode = key.scancode()? + 8;
+        if keycode > 255 {
+            return None;
+        }
+
+        CONNECTION.with(|connection| {
+            let connection = connection.as_ref()?;
+            let keysym = unsafe {
+                let xlib = &connection.xlib;
+                let mut state = MaybeUninit::<XkbStateRec>::zeroed();
+                let group = match (xlib.XkbGetState)(connection.display, XKB_USE_CORE_KBD, state.as_mut_ptr()) {
+                    0 => state.assume_init().group as c_int,
+                    _ => 0,
+                };
+                (xlib.XkbKeycodeToKeysym)(connection.display, keycode as u8, group, 0)
+            };
+            keysym_to_char(keysym)
+                .filter(|c| !c.is_whitespace() && !c.is_control())
+                .map(|c| c.to_uppercase().collect())
+        })
+    }
+
+    /// Latin-1 keysyms are the code point itself, and Unicode keysyms are the code point with
+    /// 0x01000000 added. Every other keysym doesn't stand for a character
+    pub(super) fn keysym_to_char(keysym: c_ulong) -> Option<char> {
+        match keysym {
+            0x20..=0x7E | 0xA0..=0xFF => std::char::from_u32(keysym as u32),
+            0x0100_0100..=0x0110_FFFF => std::char::from_u32((keysym - 0x0100_0000) as u32),
+            _ => None,
+        }
+    }
+
+    /// Whether `button` is held right now, even while another window has focus. X11 has no way to
+    /// query the side buttons, so `X1` and `X2` are never down
+    pub fn is_mouse_button_down(button: MouseButton) -> bool {
+        let mask = match button {
+            MouseButton::Left => x11_dl::xlib::Button1Mask,
+            MouseButton::Middle => x11_dl::xlib::Button2Mask,
+            MouseButton::Right => x11_dl::xlib::Button3Mask,
+            MouseButton::X1 | MouseButton::X2 => return false,
+        };
+
+        CONNECTION.with(|connection| {
+            let connection = match connection {
+                Some(connection) => connection,
+                None => return false,
+            };
+            unsafe {
+                let xlib = &connection.xlib;
+                let mut root = MaybeUninit::uninit();
+                let mut child = MaybeUninit::uninit();
+                let (mut root_x, mut root_y, mut x, mut y) = (0, 0, 0, 0);
+                let mut state = 0;
+                (xlib.XQueryPointer)(
+                    connection.display,
+                    (xlib.XDefaultRootWindow)(connection.display),
+                    root.as_mut_ptr(),
+                    child.as_mut_ptr(),
+                    &mut root_x,
+                    &mut root_y,
+                    &mut x,
+                    &mut y,
+                    &mut state,
+                );
+                state & mask != 0
+            }
+        })
+    }
+}
+
+/// There is no way to read the keyboard on this platform yet
+#[cfg(not(any(windows, all(unix, not(target_os = "macos")))))]
+pub fn is_key_down(_key: Key) -> bool {
+    false
+}
+
+#[cfg(not(any(windows, all(unix, not(target_os = "macos")))))]
+pub fn is_mouse_button_down(_button: MouseButton) -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_names_idents_and_aliases() {
+        let cases = [
+            ("Page Up", Key::PageUp),
+            ("PageUp", Key::PageUp),
+            ("PAGE_UP", Key::PageUp),
+            (" pgup ", Key::PageUp),
+            ("PgDn", Key::PageDown),
+            ("esc", Key::Escape),
+            ("Return", Key::Enter),
+            ("ctrl", Key::LControl),
+            ("AltGr", Key::RAlt),
+            ("win", Key::LSuper),
+            ("tilde", Key::Grave),
+            ("Numpad Enter", Key::NumpadEnter),
+        ];
+        for &(name, key) in &cases {
+            assert_eq!(name.parse::<Key>().unwrap(), key, "{:?}", name);
+        }
+        assert!("not a key".parse::<Key>().is_err());
+    }
+
+    #[test]
+    fn every_name_and_ident_parses_back() {
+        for &key in Key::ALL {
+            assert_eq!(key.name().parse::<Key>().unwrap(), key, "{:?}", key.name());
+            assert_eq!(key.ident().parse::<Key>().unwrap(), key, "{:?}", key.ident());
+        }
+    }
+
+    #[test]
+    fn serializes_as_the_ident() {
+        for &key in Key::ALL {
+            let json = serde_json::to_string(&key).unwrap();
+            assert_eq!(json, format!("{:?}", key.ident()));
+            assert_eq!(serde_json::from_str::<Key>(&json).unwrap(), key);
+        }
+    }
+
+    #[test]
+    fn deserializes_names_and_virtual_key_codes() {
+        assert_eq!(serde_json::from_str::<Key>("\"Page Up\"").unwrap(), Key::PageUp);
+        assert_eq!(serde_json::from_str::<Key>("65").unwrap(), Key::A);
+        assert_eq!(serde_json::from_str::<Key>("16").unwrap(), Key::LShift);
+        assert!(serde_json::from_str::<Key>("0").is_err());
+    }
+
+    #[test]
+    fn virtual_key_codes_round_trip() {
+        for &key in Key::ALL {
+            if let Some(vkey) = key.to_vkey() {
+                // The numpad enter key has the same code as enter
+                let expected = if key == Key::NumpadEnter { Key::Enter } else { key };
+                assert_eq!(Key::from_vkey(vkey), Some(expected), "{:?}", key);
+            }
+        }
+        for &button in &MouseButton::ALL {
+            assert_eq!(MouseButton::from_vkey(button.to_vkey()), Some(button));
+        }
+    }
+
+    #[test]
+    fn winit_keys_round_trip() {
+        for &key in Key::ALL {
+            assert_eq!(Key::from_winit(key.to_winit()), Some(key), "{:?}", key);
+        }
+        assert_eq!(Key::from_winit(VirtualKeyCode::Return), Some(Key::Enter));
+        assert_eq!(Key::from_winit(VirtualKeyCode::NumpadEnter), Some(Key::NumpadEnter));
+    }
+
+    #[test]
+    fn scancodes_round_trip() {
+        for &key in Key::ALL {
+            if let Some(scancode) = key.scancode() {
+                assert_eq!(Key::from_scancode(scancode), Some(key), "{:?}", key);
+            }
+        }
+    }
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    #[test]
+    fn keysyms_map_to_characters() {
+        use super::x11::keysym_to_char;
+
+        assert_eq!(keysym_to_char(0x61), Some('a'));
+        assert_eq!(keysym_to_char(0xF6), Some('ö'));
+        assert_eq!(keysym_to_char(0x0100_0444), Some('ф'));
+        // Left arrow and a dead circumflex
+        assert_eq!(keysym_to_char(0xFF51), None);
+        assert_eq!(keysym_to_char(0xFE52), None);
+    }
+}
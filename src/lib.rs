@@ -1,5 +1,14 @@
 pub mod window;
+#[cfg(windows)]
 pub mod util;
 pub mod types;
 pub mod imgui;
 pub mod color;
+pub mod keys;
+pub mod input;
+pub mod palette;
+pub mod draw;
+pub mod geometry;
+pub mod composites;
+pub mod text;
+pub mod recorder;
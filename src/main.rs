@@ -1,18 +1,31 @@
 use ::imgui::Context;
 
 pub mod window;
+#[cfg(windows)]
 pub mod util;
 pub mod imgui;
 pub mod types;
 pub mod color;
+pub mod keys;
+pub mod input;
+pub mod palette;
+pub mod draw;
+pub mod geometry;
+pub mod composites;
+pub mod text;
+pub mod recorder;
 
 use ::imgui::*;
 use crate::imgui::Imgui;
-use winapi::um::winuser::{GetAsyncKeyState, VK_F1, VK_LBUTTON};
+#[cfg(windows)]
+use winapi::um::winuser::{GetAsyncKeyState, VK_F1};
 use crate::imgui::keybind::keybind_select;
-use winutil::{get_windows, VK_INSERT};
+#[cfg(windows)]
+use winutil::get_windows;
 use crate::imgui::overlay::ImguiOverlay;
 use crate::types::*;
+use crate::draw::Draw;
+#[cfg(windows)]
 use winapi::shared::windef::HWND;
 use std::thread::spawn;
 
@@ -20,21 +33,22 @@ pub fn main() {
     spawn(|| {
         let mut w = window::OverlayWindow::create().unwrap();
         w.controller.hide_screenshots(true);
-        // let target = get_windows().into_iter().find(|window| window.title == Some("*Untitled - Notepad".to_string())).unwrap();
-        // let target = get_windows().into_iter().find(|window| window.title == Some(r"C:\Windows\system32\cmd.exe".to_string())).unwrap();
-        // let target = find_cod_window(25108).unwrap();
-        let target = get_windows().into_iter().find(|window| window.title == Some(r"Counter-Strike: Global Offensive".to_string())).unwrap().hwnd;
-        w.controller.set_target(Some(target));
+        #[cfg(windows)]
+        {
+            // let target = get_windows().into_iter().find(|window| window.title == Some("*Untitled - Notepad".to_string())).unwrap();
+            // let target = get_windows().into_iter().find(|window| window.title == Some(r"C:\Windows\system32\cmd.exe".to_string())).unwrap();
+            // let target = find_cod_window(25108).unwrap();
+            let target = get_windows().into_iter().find(|window| window.title == Some(r"Counter-Strike: Global Offensive".to_string())).unwrap().hwnd;
+            w.controller.set_target(Some(target));
+        }
 
         let mut ctx = Context::create();
         imgui::themes::main_theme(&mut ctx);
         imgui::themes::dark_blue(&mut ctx);
 
-        let mut aimbot_key = VK_LBUTTON;
+        let mut aimbot_key = keys::MouseButton::Left.to_vkey();
         let mut color = [0.0, 0.0, 0.0, 0.0];
 
-        let lis = winutil::InputEventListener::new();
-
         let imgui: Imgui = Imgui::new(w, ctx);
         imgui.run(move |ui, state, ctx| {
             ImguiOverlay::build(&ui, &ctx, false, |overlay| {
@@ -42,14 +56,10 @@ pub fn main() {
             });
 
             ctx.bypass_screenshots = false;
-            for ev in &lis {
-                if let winutil::Event::KeyDown(key) = ev {
-                    if key == VK_INSERT {
-                        ctx.ui_open = !ctx.ui_open;
-                    }
-                }
+            if ctx.input.is_key_pressed(keys::Key::Insert) {
+                ctx.ui_open = !ctx.ui_open;
             }
-            if !ctx.ui_open {
+            if !ctx.ui_visible {
                 return;
             }
 
@@ -65,7 +75,7 @@ pub fn main() {
                         TabItem::new(im_str!("Aimbot")).build(&ui, || {
                             ui.checkbox(im_str!("Enabled"), &mut true);
                             ui.text(format!("{:?}", color));
-                            keybind_select(&ui, state, im_str!("Aimbot Key"), &mut aimbot_key);
+                            keybind_select(&ui, state, ctx, im_str!("Aimbot Key"), &mut aimbot_key);
                             ui.checkbox(im_str!("Aim at teammates"), &mut true);
                             ComboBox::new(im_str!("Bone")).build(&ui, || {
                                 Selectable::new(im_str!("Head")).build(&ui);
@@ -87,6 +97,7 @@ pub fn main() {
     loop {}
 }
 
+#[cfg(windows)]
 fn find_cod_window(cod_pid: u32) -> Option<HWND> {
     get_windows().into_iter()
         .filter(|window| window.pid == cod_pid)
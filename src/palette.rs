@@ -0,0 +1,135 @@
+//! Named colors shared by the imgui themes and the overlays
+
+use anyhow::{anyhow, Result};
+use crate::color::{Color, names_match};
+
+/// An ordered list of named colors
+#[derive(Debug, Clone, PartialEq)]
+pub struct Palette {
+    colors: Vec<(String, Color)>,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self::blueprint()
+    }
+}
+
+impl Palette {
+    /// A palette without any colors
+    pub fn new() -> Self {
+        Self { colors: Vec::new() }
+    }
+
+    /// Every color constant on [`Color`], named like the constant
+    pub fn blueprint() -> Self {
+        let colors = Color::NAMED.iter()
+            .map(|&(name, color)| (name.to_string(), color))
+            .collect();
+        Self { colors }
+    }
+
+    /// Adds a color, replacing any color with the same name
+    pub fn insert(&mut self, name: impl Into<String>, color: Color) {
+        let name = name.into();
+        match self.colors.iter_mut().find(|(existing, _)| names_match(existing, &name)) {
+            Some(entry) => entry.1 = color,
+            None => self.colors.push((name, color)),
+        }
+    }
+
+    /// Looks up a color ignoring case and treating `-` like `_`
+    pub fn get(&self, name: &str) -> Option<Color> {
+        self.colors.iter()
+            .find(|(existing, _)| names_match(existing, name))
+            .map(|&(_, color)| color)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, Color)> {
+        self.colors.iter().map(|(name, color)| (name.as_str(), *color))
+    }
+
+    pub fn len(&self) -> usize {
+        self.colors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.colors.is_empty()
+    }
+}
+
+/// The few colors a whole theme is generated from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColorRoles {
+    /// Used for anything interactive that is hovered, active or checked
+    pub accent: Color,
+    /// Window background. Frames, headers and borders are derived from it
+    pub background: Color,
+    pub text: Color,
+}
+
+impl Default for ColorRoles {
+    fn default() -> Self {
+        Self {
+            accent: Color::BLUE4,
+            background: Color::DARK_GRAY2,
+            text: Color::LIGHT_GRAY5,
+        }
+    }
+}
+
+impl ColorRoles {
+    pub fn new(accent: Color, background: Color, text: Color) -> Self {
+        Self { accent, background, text }
+    }
+
+    /// Picks each role from `palette` by name
+    pub fn from_palette(palette: &Palette, accent: &str, background: &str, text: &str) -> Result<Self> {
+        let get = |name: &str| palette.get(name).ok_or_else(|| anyhow!("No color named {:?} in the palette", name));
+        Ok(Self::new(get(accent)?, get(background)?, get(text)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lookup_ignores_case_and_dashes() {
+        let palette = Palette::blueprint();
+        assert_eq!(palette.get("BLUE4"), Some(Color::BLUE4));
+        assert_eq!(palette.get("dark-gray2"), Some(Color::DARK_GRAY2));
+        assert_eq!(palette.get(" Light_Gray5 "), Some(Color::LIGHT_GRAY5));
+        assert_eq!(palette.get("blue"), None);
+    }
+
+    #[test]
+    fn blueprint_enumerates_every_named_color_in_order() {
+        let palette = Palette::blueprint();
+        assert_eq!(palette.len(), Color::NAMED.len());
+        assert!(palette.iter().eq(Color::NAMED.iter().copied()));
+        assert_eq!(Palette::default(), palette);
+    }
+
+    #[test]
+    fn insert_replaces_colors_with_a_matching_name() {
+        let mut palette = Palette::new();
+        assert!(palette.is_empty());
+        palette.insert("accent", Color::BLUE4);
+        palette.insert("text", Color::WHITE);
+        palette.insert("ACCENT", Color::RED3);
+
+        let colors: Vec<_> = palette.iter().collect();
+        assert_eq!(colors, vec![("accent", Color::RED3), ("text", Color::WHITE)]);
+    }
+
+    #[test]
+    fn roles_are_picked_by_name() {
+        let palette = Palette::blueprint();
+        let roles = ColorRoles::from_palette(&palette, "blue4", "dark-gray2", "light-gray5").unwrap();
+        assert_eq!(roles, ColorRoles::default());
+
+        let error = ColorRoles::from_palette(&palette, "blue4", "nope", "light-gray5").unwrap_err();
+        assert!(error.to_string().contains("\"nope\""));
+    }
+}
@@ -0,0 +1,438 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use crate::color::Color;
+use crate::draw::{Draw, align_point, align_points};
+use crate::text::TextLine;
+use crate::types::*;
+
+/// A single call made to a [`DrawRecorder`], with positions already aligned and
+/// centered the same way [`ImguiOverlay`](crate::imgui::overlay::ImguiOverlay) would
+#[derive(Debug, Clone, PartialEq)]
+pub enum DrawCommand {
+    Line {
+        p1: [f32; 2],
+        p2: [f32; 2],
+        color: Color,
+        width: f32,
+    },
+    Box {
+        p1: [f32; 2],
+        p2: [f32; 2],
+        color: Color,
+        rounding: f32,
+        width: f32,
+        filled: bool,
+        fill: Fill,
+    },
+    Text {
+        /// Every line of the text, positioned and aligned
+        lines: Vec<TextLine>,
+        color: Color,
+        font: Font,
+        font_size: Option<u32>,
+        style: TextStyle,
+        shadow_color: Color,
+        outline_thickness: Option<f32>,
+        shadow_offset: Option<[f32; 2]>,
+        shadow_blur: f32,
+    },
+    Circle {
+        origin: [f32; 2],
+        radius: f32,
+        color: Color,
+        filled: bool,
+        width: f32,
+        segments: u32,
+    },
+    Polyline {
+        points: Vec<[f32; 2]>,
+        color: Color,
+        width: f32,
+    },
+    Polygon {
+        points: Vec<[f32; 2]>,
+        color: Color,
+        width: f32,
+        filled: bool,
+        fill: Fill,
+    },
+    QuadraticBezier {
+        points: [[f32; 2]; 3],
+        color: Color,
+        width: f32,
+        tolerance: f32,
+    },
+    CubicBezier {
+        points: [[f32; 2]; 4],
+        color: Color,
+        width: f32,
+        tolerance: f32,
+    },
+    Arc {
+        center: [f32; 2],
+        radius: f32,
+        start_angle: f32,
+        end_angle: f32,
+        color: Color,
+        width: f32,
+        filled: bool,
+        tolerance: f32,
+    },
+    Image {
+        image: ImageHandle,
+        p1: [f32; 2],
+        p2: [f32; 2],
+        tint: Color,
+        uv_min: [f32; 2],
+        uv_max: [f32; 2],
+        rounding: f32,
+    },
+    RotatedImage {
+        image: ImageHandle,
+        center: [f32; 2],
+        size: [f32; 2],
+        angle: f32,
+        tint: Color,
+        uv_min: [f32; 2],
+        uv_max: [f32; 2],
+    },
+    PushClipRect {
+        p1: [f32; 2],
+        p2: [f32; 2],
+    },
+    PopClipRect,
+}
+
+/// Approximate metrics used to measure text without a font atlas
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FontMetrics {
+    /// Size the font was measured at
+    pub size_pixels: f32,
+    /// Horizontal advance of every character
+    pub advance: f32,
+    pub line_height: f32,
+}
+
+impl FontMetrics {
+    /// Metrics for a font rasterized at `size_pixels`
+    pub fn from_size(size_pixels: f32) -> Self {
+        Self { size_pixels, advance: size_pixels / 2.0, line_height: size_pixels }
+    }
+
+    /// The same metrics for the font rasterized at `size_pixels` instead
+    pub fn scaled(&self, size_pixels: f32) -> Self {
+        let scale = size_pixels / self.size_pixels;
+        Self { size_pixels, advance: self.advance * scale, line_height: self.line_height * scale }
+    }
+}
+
+/// A headless [`Draw`] implementation that records every call instead of rendering it
+#[derive(Debug)]
+pub struct DrawRecorder {
+    align_to_pixel: bool,
+    font_metrics: HashMap<Font, FontMetrics>,
+    commands: RefCell<Vec<DrawCommand>>,
+}
+
+impl DrawRecorder {
+    /// Creates a recorder with metrics matching the sizes of the built in fonts
+    pub fn new(align_to_pixel: bool) -> Self {
+        let mut font_metrics = HashMap::new();
+        font_metrics.insert(Font::Default, FontMetrics::from_size(13.0));
+        font_metrics.insert(Font::Pixel, FontMetrics::from_size(10.0));
+        font_metrics.insert(Font::Verdana, FontMetrics::from_size(13.0));
+        font_metrics.insert(Font::Tahoma, FontMetrics::from_size(14.0));
+
+        Self { align_to_pixel, font_metrics, commands: RefCell::new(Vec::new()) }
+    }
+
+    /// Overrides the metrics used to measure text drawn with `font`
+    pub fn with_font_metrics(mut self, font: Font, metrics: FontMetrics) -> Self {
+        self.font_metrics.insert(font, metrics);
+        self
+    }
+
+    /// Returns a copy of every command recorded so far
+    pub fn commands(&self) -> Vec<DrawCommand> {
+        self.commands.borrow().clone()
+    }
+
+    /// Removes and returns every command recorded so far
+    pub fn take_commands(&self) -> Vec<DrawCommand> {
+        self.commands.replace(Vec::new())
+    }
+
+    fn record(&self, command: DrawCommand) {
+        self.commands.borrow_mut().push(command);
+    }
+}
+
+impl Default for DrawRecorder {
+    fn default() -> Self {
+        Self::new(false)
+    }
+}
+
+impl Draw for DrawRecorder {
+    fn draw_line(&self, p1: impl Into<[f32; 2]>, p2: impl Into<[f32; 2]>, options: LineOptions) {
+        self.record(DrawCommand::Line {
+            p1: align_point(p1.into(), self.align_to_pixel),
+            p2: align_point(p2.into(), self.align_to_pixel),
+            color: options.color,
+            width: options.width,
+        });
+    }
+
+    fn draw_box(&self, p1: impl Into<[f32; 2]>, p2: impl Into<[f32; 2]>, options: BoxOptions) {
+        self.record(DrawCommand::Box {
+            p1: align_point(p1.into(), self.align_to_pixel),
+            p2: align_point(p2.into(), self.align_to_pixel),
+            color: options.color,
+            rounding: options.rounding,
+            width: options.width,
+            filled: options.filled,
+            fill: options.fill,
+        });
+    }
+
+    fn draw_text(&self, origin: impl Into<[f32; 2]>, text: &str, options: TextOptions) {
+        let origin = align_point(origin.into(), self.align_to_pixel);
+        let mut lines = self.layout_text(origin, text, &options).lines;
+        for line in &mut lines {
+            line.position = align_point(line.position, self.align_to_pixel);
+        }
+
+        self.record(DrawCommand::Text {
+            lines,
+            color: options.color,
+            font: options.font,
+            font_size: options.font_size,
+            style: options.style,
+            shadow_color: options.shadow_color,
+            outline_thickness: options.outline_thickness,
+            shadow_offset: options.shadow_offset,
+            shadow_blur: options.shadow_blur,
+        });
+    }
+
+    fn draw_circle(&self, origin: impl Into<[f32; 2]>, radius: f32, options: CircleOptions) {
+        self.record(DrawCommand::Circle {
+            origin: align_point(origin.into(), self.align_to_pixel),
+            radius,
+            color: options.color,
+            filled: options.filled,
+            width: options.width,
+            segments: options.segments,
+        });
+    }
+
+    fn draw_polyline(&self, points: &[[f32; 2]], options: LineOptions) {
+        self.record(DrawCommand::Polyline {
+            points: align_points(points, self.align_to_pixel),
+            color: options.color,
+            width: options.width,
+        });
+    }
+
+    fn draw_polygon(&self, points: &[[f32; 2]], options: PolygonOptions) {
+        self.record(DrawCommand::Polygon {
+            points: align_points(points, self.align_to_pixel),
+            color: options.color,
+            width: options.width,
+            filled: options.filled,
+            fill: options.fill,
+        });
+    }
+
+    fn draw_quadratic_bezier(&self, p0: impl Into<[f32; 2]>, p1: impl Into<[f32; 2]>, p2: impl Into<[f32; 2]>, options: CurveOptions) {
+        self.record(DrawCommand::QuadraticBezier {
+            points: [
+                align_point(p0.into(), self.align_to_pixel),
+                align_point(p1.into(), self.align_to_pixel),
+                align_point(p2.into(), self.align_to_pixel),
+            ],
+            color: options.color,
+            width: options.width,
+            tolerance: options.tolerance,
+        });
+    }
+
+    fn draw_cubic_bezier(&self, p0: impl Into<[f32; 2]>, p1: impl Into<[f32; 2]>, p2: impl Into<[f32; 2]>, p3: impl Into<[f32; 2]>, options: CurveOptions) {
+        self.record(DrawCommand::CubicBezier {
+            points: [
+                align_point(p0.into(), self.align_to_pixel),
+                align_point(p1.into(), self.align_to_pixel),
+                align_point(p2.into(), self.align_to_pixel),
+                align_point(p3.into(), self.align_to_pixel),
+            ],
+            color: options.color,
+            width: options.width,
+            tolerance: options.tolerance,
+        });
+    }
+
+    fn draw_arc(&self, center: impl Into<[f32; 2]>, radius: f32, start_angle: f32, end_angle: f32, options: ArcOptions) {
+        self.record(DrawCommand::Arc {
+            center: align_point(center.into(), self.align_to_pixel),
+            radius,
+            start_angle,
+            end_angle,
+            color: options.color,
+            width: options.width,
+            filled: options.filled,
+            tolerance: options.tolerance,
+        });
+    }
+
+    fn draw_image(&self, image: &ImageHandle, p1: impl Into<[f32; 2]>, p2: impl Into<[f32; 2]>, options: ImageOptions) {
+        self.record(DrawCommand::Image {
+            image: *image,
+            p1: align_point(p1.into(), self.align_to_pixel),
+            p2: align_point(p2.into(), self.align_to_pixel),
+            tint: options.tint,
+            uv_min: options.uv_min,
+            uv_max: options.uv_max,
+            rounding: options.rounding,
+        });
+    }
+
+    fn draw_image_rotated(&self, image: &ImageHandle, center: impl Into<[f32; 2]>, size: [f32; 2], angle: f32, options: ImageOptions) {
+        self.record(DrawCommand::RotatedImage {
+            image: *image,
+            center: align_point(center.into(), self.align_to_pixel),
+            size,
+            angle,
+            tint: options.tint,
+            uv_min: options.uv_min,
+            uv_max: options.uv_max,
+        });
+    }
+
+    fn push_clip_rect(&self, p1: impl Into<[f32; 2]>, p2: impl Into<[f32; 2]>) {
+        self.record(DrawCommand::PushClipRect {
+            p1: align_point(p1.into(), self.align_to_pixel),
+            p2: align_point(p2.into(), self.align_to_pixel),
+        });
+    }
+
+    fn pop_clip_rect(&self) {
+        self.record(DrawCommand::PopClipRect);
+    }
+
+    fn text_size(&self, text: &str, font: &Font, size: Option<u32>) -> [f32; 2] {
+        let metrics = self.font_metrics.get(font).copied().unwrap_or(FontMetrics::from_size(13.0));
+        let metrics = match size {
+            Some(size) => metrics.scaled(size as f32),
+            None => metrics,
+        };
+        [text.chars().count() as f32 * metrics.advance, metrics.line_height]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_lines_aligned_to_pixels() {
+        let recorder = DrawRecorder::new(true);
+        recorder.draw_line([1.4, 2.6], [10.5, 20.2], LineOptions::default().color(Color::RED1).width(2.0));
+
+        assert_eq!(recorder.commands(), vec![DrawCommand::Line {
+            p1: [1.0, 3.0],
+            p2: [11.0, 20.0],
+            color: Color::RED1,
+            width: 2.0,
+        }]);
+    }
+
+    #[test]
+    fn records_boxes_unaligned() {
+        let recorder = DrawRecorder::new(false);
+        recorder.draw_box([1.4, 2.6], [10.5, 20.2], BoxOptions::default().filled(true).rounding(3.0));
+
+        match &recorder.commands()[..] {
+            [DrawCommand::Box { p1, p2, filled, rounding, .. }] => {
+                assert_eq!(*p1, [1.4, 2.6]);
+                assert_eq!(*p2, [10.5, 20.2]);
+                assert!(*filled);
+                assert_eq!(*rounding, 3.0);
+            }
+            commands => panic!("Expected a single box, got {:?}", commands),
+        }
+    }
+
+    #[test]
+    fn records_text_laid_out_with_font_metrics() {
+        let recorder = DrawRecorder::new(true)
+            .with_font_metrics(Font::Verdana, FontMetrics { size_pixels: 13.0, advance: 5.0, line_height: 10.0 });
+        recorder.draw_text([100.0, 50.0], "ab\ncdef", TextOptions::default().centered_horizontal(true).style(TextStyle::Outlined));
+
+        match &recorder.commands()[..] {
+            [DrawCommand::Text { lines, font, style, .. }] => {
+                assert_eq!(*font, Font::Verdana);
+                assert_eq!(*style, TextStyle::Outlined);
+                assert_eq!(lines.len(), 2);
+                assert_eq!(lines[0].text, "ab");
+                assert_eq!(lines[0].size, [10.0, 10.0]);
+                assert_eq!(lines[0].position, [95.0, 50.0]);
+                assert_eq!(lines[1].text, "cdef");
+                assert_eq!(lines[1].position, [90.0, 60.0]);
+            }
+            commands => panic!("Expected a single text, got {:?}", commands),
+        }
+    }
+
+    #[test]
+    fn records_polygons() {
+        let recorder = DrawRecorder::new(true);
+        let points = [[0.2, 0.2], [10.7, 0.0], [5.0, 8.4]];
+        recorder.draw_polygon(&points, PolygonOptions::default().filled(true).color(Color::BLUE4));
+
+        match &recorder.commands()[..] {
+            [DrawCommand::Polygon { points, color, filled, .. }] => {
+                assert_eq!(*points, vec![[0.0, 0.0], [11.0, 0.0], [5.0, 8.0]]);
+                assert_eq!(*color, Color::BLUE4);
+                assert!(*filled);
+            }
+            commands => panic!("Expected a single polygon, got {:?}", commands),
+        }
+    }
+
+    #[test]
+    fn records_clip_rects_in_order() {
+        let recorder = DrawRecorder::new(false);
+        recorder.push_clip_rect([0.0, 0.0], [50.0, 50.0]);
+        recorder.draw_circle([25.0, 25.0], 10.0, CircleOptions::default());
+        recorder.pop_clip_rect();
+
+        let commands = recorder.take_commands();
+        assert_eq!(commands.len(), 3);
+        assert_eq!(commands[0], DrawCommand::PushClipRect { p1: [0.0, 0.0], p2: [50.0, 50.0] });
+        assert!(matches!(commands[1], DrawCommand::Circle { radius, .. } if radius == 10.0));
+        assert_eq!(commands[2], DrawCommand::PopClipRect);
+        assert!(recorder.commands().is_empty());
+    }
+
+    #[test]
+    fn measures_text_with_requested_size() {
+        let recorder = DrawRecorder::new(false);
+        assert_eq!(recorder.text_size("abcd", &Font::Pixel, None), [20.0, 10.0]);
+        assert_eq!(recorder.text_size("abcd", &Font::Pixel, Some(20)), [40.0, 20.0]);
+    }
+
+    #[test]
+    fn scales_overridden_metrics_to_requested_size() {
+        let recorder = DrawRecorder::new(false)
+            .with_font_metrics(Font::Verdana, FontMetrics { size_pixels: 10.0, advance: 4.0, line_height: 12.0 });
+        assert_eq!(recorder.text_size("abcd", &Font::Verdana, None), [16.0, 12.0]);
+        assert_eq!(recorder.text_size("abcd", &Font::Verdana, Some(20)), [32.0, 24.0]);
+    }
+
+    #[test]
+    fn default_matches_new() {
+        let recorder = DrawRecorder::default();
+        assert_eq!(recorder.text_size("abcd", &Font::Tahoma, None), DrawRecorder::new(false).text_size("abcd", &Font::Tahoma, None));
+        assert_eq!(recorder.text_size("abcd", &Font::Tahoma, None), [28.0, 14.0]);
+    }
+}
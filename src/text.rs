@@ -0,0 +1,431 @@
+use crate::types::*;
+use crate::color::Color;
+
+const ELLIPSIS: &str = "...";
+/// Font size in pixels the default outline thickness and shadow offset are 1.0 at
+const EFFECT_BASE_SIZE: f32 = 13.0;
+
+/// A single line of laid out text
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextLine {
+    /// Top left corner of the line
+    pub position: [f32; 2],
+    pub size: [f32; 2],
+    pub text: String,
+}
+
+/// Text broken into lines and positioned according to its [`TextOptions`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextLayout {
+    pub lines: Vec<TextLine>,
+    /// Top left corner of the text
+    pub min: [f32; 2],
+    /// Bottom right corner of the text
+    pub max: [f32; 2],
+}
+
+impl TextLayout {
+    pub fn size(&self) -> [f32; 2] {
+        [self.max[0] - self.min[0], self.max[1] - self.min[1]]
+    }
+}
+
+/// Splits `text` into lines and positions them around `origin`. `measure` returns the
+/// size of a single line of text in the font from `options`
+pub fn layout_text(origin: [f32; 2], text: &str, options: &TextOptions, measure: impl Fn(&str) -> [f32; 2]) -> TextLayout {
+    let mut lines = Vec::new();
+    for paragraph in text.split('\n') {
+        let paragraph = paragraph.trim_end_matches('\r');
+        match (options.max_width, options.overflow) {
+            (Some(max_width), TextOverflow::Wrap) => lines.extend(wrap_line(paragraph, max_width, &measure)),
+            (Some(max_width), TextOverflow::Ellipsis) => lines.push(truncate_line(paragraph, max_width, &measure)),
+            (None, _) => lines.push(paragraph.to_string()),
+        }
+    }
+
+    // Empty lines still take up vertical space
+    let line_height = measure(" ")[1];
+    let sizes: Vec<[f32; 2]> = lines.iter()
+        .map(|line| {
+            let size = measure(line);
+            [size[0], size[1].max(line_height)]
+        })
+        .collect();
+
+    let width = sizes.iter().map(|size| size[0]).fold(0.0, f32::max);
+    let height: f32 = sizes.iter().map(|size| size[1]).sum();
+
+    let left = match options.horizontal_align {
+        HorizontalAlign::Left => origin[0],
+        HorizontalAlign::Center => origin[0] - width / 2.0,
+        HorizontalAlign::Right => origin[0] - width,
+    };
+    let top = match options.vertical_align {
+        VerticalAlign::Top => origin[1],
+        VerticalAlign::Middle => origin[1] - height / 2.0,
+        VerticalAlign::Bottom => origin[1] - height,
+    };
+
+    let mut y = top;
+    let lines = lines.into_iter().zip(sizes)
+        .map(|(text, size)| {
+            let x = match options.horizontal_align {
+                HorizontalAlign::Left => left,
+                HorizontalAlign::Center => left + (width - size[0]) / 2.0,
+                HorizontalAlign::Right => left + width - size[0],
+            };
+            let line = TextLine { position: [x, y], size, text };
+            y += size[1];
+            line
+        })
+        .collect();
+
+    TextLayout { lines, min: [left, top], max: [left + width, top + height] }
+}
+
+/// Greedily breaks `line` between words so every piece fits in `max_width`
+fn wrap_line(line: &str, max_width: f32, measure: &impl Fn(&str) -> [f32; 2]) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut current = String::new();
+
+    for word in line.split(' ') {
+        let candidate = match current.is_empty() {
+            true => word.to_string(),
+            false => format!("{} {}", current, word),
+        };
+        if measure(&candidate)[0] <= max_width {
+            current = candidate;
+            continue;
+        }
+
+        if !current.is_empty() {
+            lines.push(std::mem::take(&mut current));
+        }
+
+        // The word doesn't fit on a line of its own, so break it between characters
+        for c in word.chars() {
+            current.push(c);
+            if measure(&current)[0] > max_width && current.chars().count() > 1 {
+                current.pop();
+                lines.push(std::mem::replace(&mut current, c.to_string()));
+            }
+        }
+    }
+
+    lines.push(current);
+    lines
+}
+
+/// Cuts `line` short and appends an ellipsis if it is wider than `max_width`
+fn truncate_line(line: &str, max_width: f32, measure: &impl Fn(&str) -> [f32; 2]) -> String {
+    if measure(line)[0] <= max_width {
+        return line.to_string();
+    }
+
+    let mut truncated: String = line.to_string();
+    while truncated.pop().is_some() {
+        let candidate = format!("{}{}", truncated.trim_end(), ELLIPSIS);
+        if measure(&candidate)[0] <= max_width {
+            return candidate;
+        }
+    }
+    ELLIPSIS.to_string()
+}
+
+/// How the glyphs of a [`TextEffect`] are changed before they are drawn
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GlyphEffect {
+    /// The glyphs as they are in the font atlas
+    Plain,
+    /// Glyphs grown by this many pixels in every direction
+    Outline(f32),
+    /// Glyphs blurred over this many pixels
+    Blur(f32),
+}
+
+/// A copy of some text drawn behind it as part of its [`TextStyle`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextEffect {
+    pub offset: [f32; 2],
+    pub color: Color,
+    pub glyphs: GlyphEffect,
+}
+
+/// The copy of the text to draw behind it for the style in `options`. Thicknesses and offsets
+/// the options leave unset scale with `font_size`
+pub fn text_effect(options: &TextOptions, font_size: f32) -> Option<TextEffect> {
+    let scale = (font_size / EFFECT_BASE_SIZE).max(1.0);
+
+    let (offset, glyphs) = match options.style {
+        TextStyle::None => return None,
+        TextStyle::Outlined => ([0.0, 0.0], GlyphEffect::Outline(options.outline_thickness.unwrap_or(scale))),
+        TextStyle::Shadow => {
+            let glyphs = match options.shadow_blur > 0.0 {
+                true => GlyphEffect::Blur(options.shadow_blur),
+                false => GlyphEffect::Plain,
+            };
+            (options.shadow_offset.unwrap_or([scale, scale]), glyphs)
+        }
+    };
+    Some(TextEffect { offset, color: options.shadow_color, glyphs })
+}
+
+/// Alpha at or above which a texel counts as part of the glyph when outlining it
+const OUTLINE_THRESHOLD: u8 = 64;
+
+/// The coverage of a single glyph, cut out of a font atlas
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GlyphBitmap {
+    pub width: u32,
+    pub height: u32,
+    pub alpha: Vec<u8>,
+}
+
+impl GlyphBitmap {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self { width, height, alpha: vec![0; width as usize * height as usize] }
+    }
+
+    /// Copies `rect` (`[x0, y0, x1, y1]`, exclusive) out of an alpha atlas `atlas_width` texels wide
+    pub fn from_atlas(atlas: &[u8], atlas_width: u32, rect: [u32; 4]) -> Self {
+        let mut glyph = Self::new(rect[2] - rect[0], rect[3] - rect[1]);
+        for y in 0..glyph.height {
+            let start = (rect[1] + y) as usize * atlas_width as usize + rect[0] as usize;
+            let row = y as usize * glyph.width as usize;
+            glyph.alpha[row..row + glyph.width as usize].copy_from_slice(&atlas[start..start + glyph.width as usize]);
+        }
+        glyph
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> u8 {
+        self.alpha[y as usize * self.width as usize + x as usize]
+    }
+
+    /// The glyph grown by `radius` texels in every direction. The result is `radius` texels
+    /// larger on every side, so the glyph itself stays at the same place inside the padding
+    pub fn outlined(&self, radius: u32) -> Self {
+        let padded = self.padded(radius);
+        let ink: Vec<f64> = padded.alpha.iter()
+            .map(|&alpha| if alpha >= OUTLINE_THRESHOLD { 0.0 } else { f64::MAX })
+            .collect();
+        let distances = distance_transform(&ink, padded.width as usize, padded.height as usize);
+
+        // Anti alias the edge of the outline over a single texel, and keep the partly covered texels of the glyph
+        let alpha = distances.iter().zip(&padded.alpha)
+            .map(|(&distance, &alpha)| {
+                let coverage = (radius as f64 + 0.5 - distance.sqrt()).clamp(0.0, 1.0);
+                ((coverage * 255.0).round() as u8).max(alpha)
+            })
+            .collect();
+        Self { alpha, ..padded }
+    }
+
+    /// The glyph blurred over `radius` texels, `radius` texels larger on every side like `outlined`
+    pub fn blurred(&self, radius: u32) -> Self {
+        let mut padded = self.padded(radius);
+        // Three box blurs are close to a gaussian, and together spread the glyph by at most `radius`
+        let box_radius = (radius / 3).max(1) as usize;
+        let (width, height) = (padded.width as usize, padded.height as usize);
+        let mut values: Vec<f32> = padded.alpha.iter().map(|&alpha| alpha as f32).collect();
+        for _ in 0..3 {
+            for row in values.chunks_exact_mut(width) {
+                box_blur(row, 1, width, box_radius);
+            }
+            for x in 0..width {
+                box_blur(&mut values[x..], width, height, box_radius);
+            }
+        }
+        padded.alpha = values.iter().map(|&value| value.round().clamp(0.0, 255.0) as u8).collect();
+        padded
+    }
+
+    /// A copy with `padding` transparent texels on every side
+    fn padded(&self, padding: u32) -> Self {
+        let mut padded = Self::new(self.width + 2 * padding, self.height + 2 * padding);
+        for y in 0..self.height {
+            let start = (y + padding) as usize * padded.width as usize + padding as usize;
+            let row = y as usize * self.width as usize;
+            padded.alpha[start..start + self.width as usize].copy_from_slice(&self.alpha[row..row + self.width as usize]);
+        }
+        padded
+    }
+}
+
+/// Squared euclidean distance from every texel to the closest one where `f` is 0, using the
+/// separable algorithm from Felzenszwalb and Huttenlocher
+fn distance_transform(f: &[f64], width: usize, height: usize) -> Vec<f64> {
+    let mut distances = f.to_vec();
+    let mut column = vec![0.0; height];
+    for x in 0..width {
+        for y in 0..height {
+            column[y] = distances[y * width + x];
+        }
+        let transformed = distance_transform_1d(&column);
+        for y in 0..height {
+            distances[y * width + x] = transformed[y];
+        }
+    }
+    for row in distances.chunks_exact_mut(width) {
+        let transformed = distance_transform_1d(row);
+        row.copy_from_slice(&transformed);
+    }
+    distances
+}
+
+/// The lower envelope of the parabolas rooted at every sample of `f`
+fn distance_transform_1d(f: &[f64]) -> Vec<f64> {
+    let n = f.len();
+    // Texels that are far from everything stay far instead of overflowing
+    let f: Vec<f64> = f.iter().map(|&value| value.min(1e20)).collect();
+    let mut vertices = vec![0; n];
+    let mut boundaries = vec![0.0; n + 1];
+    boundaries[0] = f64::NEG_INFINITY;
+    boundaries[1] = f64::INFINITY;
+
+    let intersection = |q: usize, p: usize| {
+        ((f[q] + (q * q) as f64) - (f[p] + (p * p) as f64)) / (2.0 * q as f64 - 2.0 * p as f64)
+    };
+    let mut k = 0;
+    for q in 1..n {
+        let mut s = intersection(q, vertices[k]);
+        while s <= boundaries[k] {
+            k -= 1;
+            s = intersection(q, vertices[k]);
+        }
+        k += 1;
+        vertices[k] = q;
+        boundaries[k] = s;
+        boundaries[k + 1] = f64::INFINITY;
+    }
+
+    let mut k = 0;
+    (0..n)
+        .map(|q| {
+            while boundaries[k + 1] < q as f64 {
+                k += 1;
+            }
+            let distance = q as f64 - vertices[k] as f64;
+            distance * distance + f[vertices[k]]
+        })
+        .collect()
+}
+
+/// Averages `len` values `stride` apart over a window `radius` to each side. Values outside are 0
+fn box_blur(values: &mut [f32], stride: usize, len: usize, radius: usize) {
+    let input: Vec<f32> = (0..len).map(|i| values[i * stride]).collect();
+    let window = (2 * radius + 1) as f32;
+    let mut sum: f32 = input.iter().take(radius).sum();
+    for i in 0..len {
+        if i + radius < len {
+            sum += input[i + radius];
+        }
+        if i > radius {
+            sum -= input[i - radius - 1];
+        }
+        values[i * stride] = sum / window;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn effects_follow_the_style() {
+        assert_eq!(text_effect(&TextOptions::default().style(TextStyle::None), 13.0), None);
+
+        let outline = TextOptions::default().style(TextStyle::Outlined).shadow_color(Color::BLACK);
+        assert_eq!(text_effect(&outline, 13.0), Some(TextEffect { offset: [0.0, 0.0], color: Color::BLACK, glyphs: GlyphEffect::Outline(1.0) }));
+        assert_eq!(text_effect(&outline, 26.0).unwrap().glyphs, GlyphEffect::Outline(2.0));
+        assert_eq!(text_effect(&outline.outline_thickness(Some(40.0)), 13.0).unwrap().glyphs, GlyphEffect::Outline(40.0));
+
+        let shadow = TextOptions::default().style(TextStyle::Shadow);
+        assert_eq!(text_effect(&shadow, 13.0).unwrap().glyphs, GlyphEffect::Plain);
+        assert_eq!(text_effect(&shadow, 39.0).unwrap().offset, [3.0, 3.0]);
+        let blurred = shadow.shadow_offset(Some([2.0, -1.0])).shadow_blur(4.0);
+        assert_eq!(text_effect(&blurred, 13.0), Some(TextEffect {
+            offset: [2.0, -1.0],
+            color: blurred.shadow_color,
+            glyphs: GlyphEffect::Blur(4.0),
+        }));
+    }
+
+    /// A glyph with a single opaque texel in the middle of a 5x5 bitmap
+    fn dot() -> GlyphBitmap {
+        let mut glyph = GlyphBitmap::new(5, 5);
+        glyph.alpha[2 * 5 + 2] = 255;
+        glyph
+    }
+
+    #[test]
+    fn glyphs_are_cut_out_of_the_atlas() {
+        let atlas: Vec<u8> = (0..40).collect();
+        let glyph = GlyphBitmap::from_atlas(&atlas, 8, [2, 1, 5, 3]);
+        assert_eq!((glyph.width, glyph.height), (3, 2));
+        assert_eq!(glyph.alpha, vec![10, 11, 12, 18, 19, 20]);
+    }
+
+    #[test]
+    fn outlines_fill_every_texel_within_the_radius() {
+        for &radius in &[1, 3, 12, 40] {
+            let outlined = dot().outlined(radius);
+            assert_eq!((outlined.width, outlined.height), (5 + 2 * radius, 5 + 2 * radius));
+
+            let center = 2 + radius;
+            for y in 0..outlined.height {
+                for x in 0..outlined.width {
+                    let distance = ((x as f32 - center as f32).powi(2) + (y as f32 - center as f32).powi(2)).sqrt();
+                    let alpha = outlined.get(x, y);
+                    if distance <= radius as f32 - 0.5 {
+                        assert_eq!(alpha, 255, "radius {} at ({}, {})", radius, x, y);
+                    } else if distance >= radius as f32 + 0.5 {
+                        assert_eq!(alpha, 0, "radius {} at ({}, {})", radius, x, y);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn outlines_keep_partly_covered_texels() {
+        let mut glyph = GlyphBitmap::new(3, 1);
+        glyph.alpha = vec![0, 30, 0];
+        let outlined = glyph.outlined(1);
+        // Too faint to be outlined, but still part of the glyph
+        assert_eq!(outlined.get(2, 1), 30);
+        assert_eq!(outlined.alpha.iter().filter(|&&alpha| alpha > 0).count(), 1);
+    }
+
+    #[test]
+    fn blurs_spread_the_glyph_without_adding_coverage() {
+        let mut glyph = GlyphBitmap::new(4, 4);
+        glyph.alpha = vec![255; 16];
+        let blurred = glyph.blurred(6);
+        assert_eq!((blurred.width, blurred.height), (16, 16));
+
+        let total = |glyph: &GlyphBitmap| glyph.alpha.iter().map(|&alpha| alpha as f32).sum::<f32>();
+        assert!((total(&blurred) - total(&glyph)).abs() / total(&glyph) < 0.02);
+        // Softer in the middle, and spread out to the edge of the padding but not past it
+        assert!(blurred.get(8, 8) < 255 && blurred.get(8, 8) > 50);
+        assert!(blurred.get(0, 8) > 0);
+        assert!(blurred.get(8, 8) > blurred.get(3, 8));
+        assert!(blurred.get(3, 8) > blurred.get(1, 8));
+    }
+
+    #[test]
+    fn distance_transform_is_exact() {
+        let (width, height) = (7, 5);
+        let mut ink = vec![f64::MAX; width * height];
+        ink[width + 1] = 0.0;
+        ink[3 * width + 6] = 0.0;
+        let distances = distance_transform(&ink, width, height);
+        for y in 0..height {
+            for x in 0..width {
+                let expected = [(1, 1), (6, 3)].iter()
+                    .map(|&(ix, iy): &(i32, i32)| ((x as i32 - ix).pow(2) + (y as i32 - iy).pow(2)) as f64)
+                    .fold(f64::MAX, f64::min);
+                assert_eq!(distances[y * width + x], expected, "({}, {})", x, y);
+            }
+        }
+    }
+}
@@ -1,5 +1,8 @@
 use imgui::sys::ImColor;
+use imgui::TextureId;
 use crate::color::Color;
+use serde::{Serialize, Deserialize};
+use std::time::Duration;
 
 const DEFAULT_COLOR: Color = Color::from_hex(0xFFFFFF);
 
@@ -41,6 +44,8 @@ pub struct BoxOptions {
     pub rounding: f32,
     pub width: f32,
     pub filled: bool,
+    /// How the inside of the box is colored when `filled` is set
+    pub fill: Fill,
 }
 
 impl Default for BoxOptions {
@@ -50,6 +55,7 @@ impl Default for BoxOptions {
             rounding: 0.0,
             width: 1.0,
             filled: false,
+            fill: Fill::Solid,
         }
     }
 }
@@ -59,16 +65,173 @@ impl BoxOptions {
     generate_setter!(rounding: f32);
     generate_setter!(width: f32);
     generate_setter!(filled: bool);
+    generate_setter!(fill: Fill);
+}
+
+/// A color at a position along a gradient, from 0.0 at the start to 1.0 at the end
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GradientStop {
+    pub position: f32,
+    pub color: Color,
+}
+
+impl GradientStop {
+    pub fn new(position: f32, color: impl Into<Color>) -> Self {
+        Self { position, color: color.into() }
+    }
+}
+
+/// How a filled shape is colored. Positions are relative to the bounding box of the shape,
+/// with `[0.0, 0.0]` at the top left and `[1.0, 1.0]` at the bottom right
+#[derive(Debug, Clone, PartialEq)]
+pub enum Fill {
+    /// Uses the `color` of the options
+    Solid,
+    /// Bilinearly interpolates between a color at each corner of the bounding box
+    Corners {
+        top_left: Color,
+        top_right: Color,
+        bottom_right: Color,
+        bottom_left: Color,
+    },
+    /// Interpolates between the stops along the line from `start` to `end`
+    Linear {
+        start: [f32; 2],
+        end: [f32; 2],
+        stops: Vec<GradientStop>,
+    },
+    /// Interpolates between the stops by distance from `center`, reaching the last stop at `radius`
+    Radial {
+        center: [f32; 2],
+        radius: f32,
+        stops: Vec<GradientStop>,
+    },
+}
+
+impl Default for Fill {
+    fn default() -> Self {
+        Self::Solid
+    }
+}
+
+impl Fill {
+    /// A linear gradient from `top` to `bottom`
+    pub fn vertical(top: impl Into<Color>, bottom: impl Into<Color>) -> Self {
+        Self::Linear { start: [0.0, 0.0], end: [0.0, 1.0], stops: vec![GradientStop::new(0.0, top), GradientStop::new(1.0, bottom)] }
+    }
+
+    /// A linear gradient from `left` to `right`
+    pub fn horizontal(left: impl Into<Color>, right: impl Into<Color>) -> Self {
+        Self::Linear { start: [0.0, 0.0], end: [1.0, 0.0], stops: vec![GradientStop::new(0.0, left), GradientStop::new(1.0, right)] }
+    }
+
+    /// A radial gradient from `inner` in the middle of the shape to `outer` at its edges
+    pub fn radial(inner: impl Into<Color>, outer: impl Into<Color>) -> Self {
+        Self::Radial { center: [0.5, 0.5], radius: 0.5, stops: vec![GradientStop::new(0.0, inner), GradientStop::new(1.0, outer)] }
+    }
+
+    pub fn is_solid(&self) -> bool {
+        matches!(self, Self::Solid)
+    }
+
+    /// Whether interpolating `color_at` the corners of each triangle misses parts of the fill.
+    /// Linear gradients with two stops that don't clamp within the bounding box are linear across
+    /// every triangle. Corner fills are bilinear, but like imgui's multi color rects they are drawn
+    /// by interpolating over the triangles
+    pub fn needs_subdivision(&self) -> bool {
+        match self {
+            Self::Solid | Self::Corners { .. } => false,
+            Self::Linear { start, end, stops } => {
+                let (first, last) = match (stops.first(), stops.last()) {
+                    (Some(first), Some(last)) => (first.position, last.position),
+                    _ => return false,
+                };
+                stops.len() > 2 || [[0.0, 0.0], [1.0, 0.0], [0.0, 1.0], [1.0, 1.0]].iter()
+                    .map(|&corner| linear_position(*start, *end, corner))
+                    .any(|t| t < first || t > last)
+            }
+            Self::Radial { .. } => true,
+        }
+    }
+
+    /// The color at `position`, relative to the bounding box of the shape. `color` is used for `Solid`
+    pub fn color_at(&self, color: Color, position: [f32; 2]) -> Color {
+        let [x, y] = position;
+        match self {
+            Self::Solid => color,
+            Self::Corners { top_left, top_right, bottom_right, bottom_left } => {
+                let top = top_left.lerp(*top_right, x);
+                let bottom = bottom_left.lerp(*bottom_right, x);
+                top.lerp(bottom, y)
+            }
+            Self::Linear { start, end, stops } => sample_stops(stops, color, linear_position(*start, *end, position)),
+            Self::Radial { center, radius, stops } => {
+                let distance = ((x - center[0]).powi(2) + (y - center[1]).powi(2)).sqrt();
+                let t = match *radius > 0.0 {
+                    true => distance / radius,
+                    false => 1.0,
+                };
+                sample_stops(stops, color, t)
+            }
+        }
+    }
+}
+
+/// How far `position` is along the line from `start` to `end`, 0.0 at `start` and 1.0 at `end`
+fn linear_position(start: [f32; 2], end: [f32; 2], position: [f32; 2]) -> f32 {
+    let direction = [end[0] - start[0], end[1] - start[1]];
+    let length_squared = direction[0] * direction[0] + direction[1] * direction[1];
+    match length_squared > 0.0 {
+        true => ((position[0] - start[0]) * direction[0] + (position[1] - start[1]) * direction[1]) / length_squared,
+        false => 0.0,
+    }
+}
+
+/// Finds the color at `t` between gradient stops. Stops are expected to be sorted by position
+fn sample_stops(stops: &[GradientStop], color: Color, t: f32) -> Color {
+    let (first, last) = match (stops.first(), stops.last()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return color,
+    };
+    if t <= first.position {
+        return first.color;
+    }
+    if t >= last.position {
+        return last.color;
+    }
+
+    stops.windows(2)
+        .find(|pair| t <= pair[1].position)
+        .map(|pair| {
+            let span = pair[1].position - pair[0].position;
+            let t = if span > 0.0 { (t - pair[0].position) / span } else { 1.0 };
+            pair[0].color.lerp(pair[1].color, t)
+        })
+        .unwrap_or(last.color)
 }
 
 #[derive(Debug, Clone)]
 pub struct TextOptions {
     pub color: Color,
     pub font: Font,
-    pub centered_horizontal: bool,
-    pub centered_vertical: bool,
+    /// Size in pixels to draw the font at. `None` uses the first size the font was loaded with,
+    /// and sizes that weren't loaded fall back to the closest loaded one
+    pub font_size: Option<u32>,
+    /// Which horizontal edge of the text is placed at the origin. Also aligns lines against each other
+    pub horizontal_align: HorizontalAlign,
+    /// Which vertical edge of the text is placed at the origin
+    pub vertical_align: VerticalAlign,
     pub style: TextStyle,
     pub shadow_color: Color,
+    /// Width in pixels of the outline of `TextStyle::Outlined`. `None` scales it with the font size
+    pub outline_thickness: Option<f32>,
+    /// How far `TextStyle::Shadow` is drawn from the text. `None` scales it with the font size
+    pub shadow_offset: Option<[f32; 2]>,
+    /// Radius in pixels the shadow is spread over, 0.0 for a hard shadow
+    pub shadow_blur: f32,
+    /// Lines wider than this are wrapped or truncated depending on `overflow`
+    pub max_width: Option<f32>,
+    pub overflow: TextOverflow,
 }
 
 impl Default for TextOptions {
@@ -76,10 +239,16 @@ impl Default for TextOptions {
         Self {
             color: DEFAULT_COLOR,
             font: Font::Verdana,
-            centered_horizontal: false,
-            centered_vertical: false,
+            font_size: None,
+            horizontal_align: HorizontalAlign::Left,
+            vertical_align: VerticalAlign::Top,
             style: TextStyle::Shadow,
             shadow_color: Color::from_rgba(20, 20, 20, 150),
+            outline_thickness: None,
+            shadow_offset: None,
+            shadow_blur: 0.0,
+            max_width: None,
+            overflow: TextOverflow::Wrap,
         }
     }
 }
@@ -90,26 +259,73 @@ impl TextOptions {
         self.shadow_color.a = self.color.a;
         self
     }
-    generate_setter!(font: Font);
-    generate_setter!(centered_horizontal: bool);
-    generate_setter!(centered_vertical: bool);
+    generate_setter!(font: impl Into<Font>);
+    generate_setter!(font_size: Option<u32>);
+    generate_setter!(horizontal_align: HorizontalAlign);
+    generate_setter!(vertical_align: VerticalAlign);
     generate_setter!(style: TextStyle);
     generate_setter!(shadow_color: impl Into<Color>);
+    generate_setter!(outline_thickness: Option<f32>);
+    generate_setter!(shadow_offset: Option<[f32; 2]>);
+    generate_setter!(shadow_blur: f32);
+    generate_setter!(max_width: Option<f32>);
+    generate_setter!(overflow: TextOverflow);
+
+    /// Shorthand for `HorizontalAlign::Center` or `HorizontalAlign::Left`
+    pub fn centered_horizontal(self, centered: bool) -> Self {
+        self.horizontal_align(if centered { HorizontalAlign::Center } else { HorizontalAlign::Left })
+    }
+
+    /// Shorthand for `VerticalAlign::Middle` or `VerticalAlign::Top`
+    pub fn centered_vertical(self, centered: bool) -> Self {
+        self.vertical_align(if centered { VerticalAlign::Middle } else { VerticalAlign::Top })
+    }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HorizontalAlign {
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerticalAlign {
+    Top,
+    Middle,
+    Bottom,
+}
+
+/// What happens to lines wider than `TextOptions::max_width`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextOverflow {
+    /// Breaks the line between words, or between characters for words that don't fit on their own
+    Wrap,
+    /// Cuts the line short and ends it with "..."
+    Ellipsis,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum TextStyle {
     None,
     Shadow,
     Outlined,
 }
 
-#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+#[derive(Debug, Clone, Hash, Eq, PartialEq, Serialize, Deserialize)]
 pub enum Font {
     Default,
     Pixel,
     Tahoma,
     Verdana,
+    /// A font registered at runtime under a user chosen key
+    Custom(String),
+}
+
+impl From<&str> for Font {
+    fn from(key: &str) -> Self {
+        Self::Custom(key.to_string())
+    }
 }
 
 impl Default for Font {
@@ -123,6 +339,8 @@ pub struct CircleOptions {
     pub color: Color,
     pub filled: bool,
     pub width: f32,
+    /// The number of line segments used to draw the circle. 0 lets imgui pick one based on the radius
+    pub segments: u32,
 }
 
 impl Default for CircleOptions {
@@ -131,11 +349,329 @@ impl Default for CircleOptions {
             color: DEFAULT_COLOR,
             filled: false,
             width: 1.0,
+            segments: 0,
         }
     }
 }
 
 impl CircleOptions {
+    generate_setter!(color: impl Into<Color>);
+    generate_setter!(filled: bool);
+    generate_setter!(width: f32);
+    generate_setter!(segments: u32);
+}
+
+#[derive(Debug, Clone)]
+pub struct PolygonOptions {
+    pub color: Color,
+    pub width: f32,
+    pub filled: bool,
+    /// How the inside of the polygon is colored when `filled` is set
+    pub fill: Fill,
+}
+
+impl Default for PolygonOptions {
+    fn default() -> Self {
+        Self {
+            color: DEFAULT_COLOR,
+            width: 1.0,
+            filled: false,
+            fill: Fill::Solid,
+        }
+    }
+}
+
+impl PolygonOptions {
+    generate_setter!(color: impl Into<Color>);
+    generate_setter!(width: f32);
+    generate_setter!(filled: bool);
+    generate_setter!(fill: Fill);
+}
+
+#[derive(Debug, Clone)]
+pub struct CurveOptions {
+    pub color: Color,
+    pub width: f32,
+    /// The maximum distance in pixels between the drawn line segments and the real curve
+    pub tolerance: f32,
+}
+
+impl Default for CurveOptions {
+    fn default() -> Self {
+        Self {
+            color: DEFAULT_COLOR,
+            width: 1.0,
+            tolerance: 0.25,
+        }
+    }
+}
+
+impl CurveOptions {
+    generate_setter!(color: impl Into<Color>);
+    generate_setter!(width: f32);
+    generate_setter!(tolerance: f32);
+}
+
+#[derive(Debug, Clone)]
+pub struct ArcOptions {
+    pub color: Color,
+    pub width: f32,
+    /// Fills the pie slice between the arc and its center
+    pub filled: bool,
+    /// The maximum distance in pixels between the drawn line segments and the real arc
+    pub tolerance: f32,
+}
+
+impl Default for ArcOptions {
+    fn default() -> Self {
+        Self {
+            color: DEFAULT_COLOR,
+            width: 1.0,
+            filled: false,
+            tolerance: 0.25,
+        }
+    }
+}
+
+impl ArcOptions {
+    generate_setter!(color: impl Into<Color>);
+    generate_setter!(width: f32);
     generate_setter!(filled: bool);
+    generate_setter!(tolerance: f32);
+}
+
+/// A texture loaded into a renderer with one of its `load_image` functions
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ImageHandle {
+    pub texture_id: TextureId,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl ImageHandle {
+    /// Size of the image in pixels
+    pub fn size(&self) -> [f32; 2] {
+        [self.width as f32, self.height as f32]
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ImageOptions {
+    /// Multiplied with every pixel of the image
+    pub tint: Color,
+    /// Texture coordinate drawn at the top left corner
+    pub uv_min: [f32; 2],
+    /// Texture coordinate drawn at the bottom right corner
+    pub uv_max: [f32; 2],
+    /// Corner rounding. Ignored by `draw_image_rotated`
+    pub rounding: f32,
+}
+
+impl Default for ImageOptions {
+    fn default() -> Self {
+        Self {
+            tint: Color::WHITE,
+            uv_min: [0.0, 0.0],
+            uv_max: [1.0, 1.0],
+            rounding: 0.0,
+        }
+    }
+}
+
+impl ImageOptions {
+    generate_setter!(tint: impl Into<Color>);
+    generate_setter!(uv_min: [f32; 2]);
+    generate_setter!(uv_max: [f32; 2]);
+    generate_setter!(rounding: f32);
+}
+
+#[derive(Debug, Clone)]
+pub struct CornerBoxOptions {
+    pub color: Color,
+    pub width: f32,
+    /// Length of each corner line as a fraction of the side it is on, from 0.0 to 0.5
+    pub length: f32,
+    /// Draws a border of this color around every corner line
+    pub outline: Option<Color>,
+}
+
+impl Default for CornerBoxOptions {
+    fn default() -> Self {
+        Self {
+            color: DEFAULT_COLOR,
+            width: 1.0,
+            length: 0.25,
+            outline: None,
+        }
+    }
+}
+
+impl CornerBoxOptions {
+    generate_setter!(color: impl Into<Color>);
     generate_setter!(width: f32);
+    generate_setter!(length: f32);
+    generate_setter!(outline: Option<Color>);
+}
+
+#[derive(Debug, Clone)]
+pub struct Box3dOptions {
+    pub color: Color,
+    pub width: f32,
+}
+
+impl Default for Box3dOptions {
+    fn default() -> Self {
+        Self {
+            color: DEFAULT_COLOR,
+            width: 1.0,
+        }
+    }
+}
+
+impl Box3dOptions {
+    generate_setter!(color: impl Into<Color>);
+    generate_setter!(width: f32);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BarDirection {
+    /// Fills from the bottom up
+    Vertical,
+    /// Fills from left to right
+    Horizontal,
+}
+
+#[derive(Debug, Clone)]
+pub struct BarOptions {
+    pub direction: BarDirection,
+    pub color: Color,
+    /// How the filled part of the bar is colored. Gradients span the whole bar, not just the filled part
+    pub fill: Fill,
+    pub background: Color,
+    pub outline: Option<Color>,
+    pub outline_width: f32,
+}
+
+impl Default for BarOptions {
+    fn default() -> Self {
+        Self {
+            direction: BarDirection::Vertical,
+            color: Color::FOREST4,
+            fill: Fill::Solid,
+            background: Color::from_rgba(20, 20, 20, 150),
+            outline: Some(Color::BLACK),
+            outline_width: 1.0,
+        }
+    }
+}
+
+impl BarOptions {
+    generate_setter!(direction: BarDirection);
+    generate_setter!(color: impl Into<Color>);
+    generate_setter!(fill: Fill);
+    generate_setter!(background: impl Into<Color>);
+    generate_setter!(outline: Option<Color>);
+    generate_setter!(outline_width: f32);
+}
+
+/// How a transition speeds up and slows down over its duration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Easing {
+    Linear,
+    EaseInQuad,
+    EaseOutQuad,
+    EaseInOutQuad,
+    EaseInCubic,
+    EaseOutCubic,
+    EaseInOutCubic,
+}
+
+impl Easing {
+    /// Maps linear progress from 0.0 to 1.0 onto the curve
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseInQuad => t * t,
+            Easing::EaseOutQuad => 1.0 - (1.0 - t) * (1.0 - t),
+            Easing::EaseInOutQuad => if t < 0.5 { 2.0 * t * t } else { 1.0 - (-2.0 * t + 2.0).powi(2) / 2.0 },
+            Easing::EaseInCubic => t * t * t,
+            Easing::EaseOutCubic => 1.0 - (1.0 - t).powi(3),
+            Easing::EaseInOutCubic => if t < 0.5 { 4.0 * t * t * t } else { 1.0 - (-2.0 * t + 2.0).powi(3) / 2.0 },
+        }
+    }
+}
+
+/// How windows appear and disappear when `RenderContext::ui_open` changes
+#[derive(Debug, Clone)]
+pub struct TransitionOptions {
+    pub duration: Duration,
+    pub easing: Easing,
+    /// Fades windows in and out
+    pub fade: bool,
+    /// Windows slide in from this far away and back out to it
+    pub slide: Option<[f32; 2]>,
+}
+
+impl Default for TransitionOptions {
+    fn default() -> Self {
+        Self {
+            duration: Duration::from_millis(200),
+            easing: Easing::EaseOutCubic,
+            fade: true,
+            slide: None,
+        }
+    }
+}
+
+impl TransitionOptions {
+    generate_setter!(duration: Duration);
+    generate_setter!(easing: Easing);
+    generate_setter!(fade: bool);
+    generate_setter!(slide: Option<[f32; 2]>);
+
+    /// Windows appear and disappear instantly
+    pub fn none() -> Self {
+        Self::default().duration(Duration::from_secs(0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_non_linear_fills_need_subdivision() {
+        assert!(!Fill::Solid.needs_subdivision());
+        assert!(!Fill::vertical(Color::RED1, Color::BLUE1).needs_subdivision());
+        assert!(!Fill::horizontal(Color::RED1, Color::BLUE1).needs_subdivision());
+        assert!(!Fill::Corners {
+            top_left: Color::RED1,
+            top_right: Color::BLUE1,
+            bottom_right: Color::GREEN1,
+            bottom_left: Color::GOLD1,
+        }.needs_subdivision());
+
+        assert!(Fill::radial(Color::RED1, Color::BLUE1).needs_subdivision());
+        // Three stops bend at the middle one
+        assert!(Fill::Linear {
+            start: [0.0, 0.0],
+            end: [0.0, 1.0],
+            stops: vec![GradientStop::new(0.0, Color::RED1), GradientStop::new(0.5, Color::GREEN1), GradientStop::new(1.0, Color::BLUE1)],
+        }.needs_subdivision());
+        // Clamps to the last stop halfway down the shape
+        assert!(Fill::Linear {
+            start: [0.0, 0.0],
+            end: [0.0, 0.5],
+            stops: vec![GradientStop::new(0.0, Color::RED1), GradientStop::new(1.0, Color::BLUE1)],
+        }.needs_subdivision());
+    }
+
+    #[test]
+    fn linear_gradients_interpolate_between_stops() {
+        let fill = Fill::vertical(Color::from_rgba(0, 0, 0, 255), Color::from_rgba(200, 100, 0, 255));
+        assert_eq!(fill.color_at(Color::RED1, [0.3, 0.0]), Color::from_rgba(0, 0, 0, 255));
+        assert_eq!(fill.color_at(Color::RED1, [0.3, 0.5]), Color::from_rgba(100, 50, 0, 255));
+        assert_eq!(fill.color_at(Color::RED1, [0.3, 1.0]), Color::from_rgba(200, 100, 0, 255));
+    }
 }
@@ -208,16 +208,20 @@ impl WindowController {
                     Ok(())
                 }
             } else { // otherwise, we have to set it remotely
-                set_remote_affinity(self.hwnd, affinity);
-                let actual_affinity = self.get_affinity();
-                if affinity != actual_affinity {
-                    bail!("Setting remote affinity did not work. affinity: {:?}, actual_affinity: {:?}", affinity, actual_affinity);
-                }
-                Ok(())
+                self.set_remote_affinity(affinity)
             }
         }
     }
 
+    unsafe fn set_remote_affinity(&self, affinity: WindowAffinity) -> Result<()> {
+        set_remote_affinity(self.hwnd, affinity)?;
+        let actual_affinity = self.get_affinity();
+        if affinity != actual_affinity {
+            bail!("Setting remote affinity did not work. affinity: {:?}, actual_affinity: {:?}", affinity, actual_affinity);
+        }
+        Ok(())
+    }
+
     unsafe fn get_owner_pid(&self) -> u32 {
         let mut pid = 0;
         GetWindowThreadProcessId(self.hwnd, &mut pid);
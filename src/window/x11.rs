@@ -0,0 +1,149 @@
+use anyhow::*;
+use winit::event_loop::EventLoop;
+use winit::platform::unix::{EventLoopExtUnix, WindowBuilderExtUnix, WindowExtUnix, XWindowType};
+use winit::platform::unix::x11::XConnection;
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+use x11_dl::xfixes::XFixes;
+use x11_dl::xlib::{Window, XRectangle};
+use glium::Display;
+use std::os::raw::c_int;
+use std::sync::Arc;
+use std::ptr::null_mut;
+use log::*;
+
+// From X11/extensions/shape.h
+const SHAPE_INPUT: c_int = 2;
+
+pub struct OverlayWindow {
+    pub event_loop: EventLoop<()>,
+    pub display: glium::Display,
+    pub controller: WindowController,
+}
+
+unsafe impl Send for OverlayWindow {}
+
+impl OverlayWindow {
+    /// Creates an overlay window. There is nothing to hijack on X11 so this is the same as `create`
+    pub fn new() -> Result<Self> {
+        Self::create()
+    }
+
+    /// Creates a new window to use as an overlay
+    pub fn create() -> Result<Self> {
+        let event_loop = EventLoop::new_x11_any_thread()?;
+
+        let (position, size) = match event_loop.primary_monitor() {
+            Some(monitor) => (monitor.position(), monitor.size()),
+            None => (PhysicalPosition::new(0, 0), PhysicalSize::new(1920, 1080)),
+        };
+
+        // Override redirect keeps the window manager from decorating, moving or
+        // focusing the window. The ARGB visual is picked by glutin because of `with_transparent`
+        let window_builder = winit::window::WindowBuilder::new()
+            .with_title("overlay")
+            .with_decorations(false)
+            .with_transparent(true)
+            .with_always_on_top(true)
+            .with_resizable(false)
+            .with_position(position)
+            .with_inner_size(size)
+            .with_override_redirect(true)
+            .with_x11_window_type(vec![XWindowType::Notification, XWindowType::Utility]);
+
+        let builder = glutin::ContextBuilder::new();
+        let windowed_context = builder.build_windowed(window_builder, &event_loop)?;
+        let window = windowed_context.window().xlib_window()
+            .ok_or_else(|| anyhow!("Overlay window is not an X11 window"))?;
+        let connection = windowed_context.window().xlib_xconnection()
+            .ok_or_else(|| anyhow!("Could not get the X11 connection of the overlay window"))?;
+        let display = Display::from_gl_window(windowed_context)?;
+
+        Ok(Self { event_loop, display, controller: WindowController::new(connection, window, None)? })
+    }
+}
+
+/// A struct that applies necessary updates to an overlay window
+pub struct WindowController {
+    pub window: Window,
+    pub target_window: Option<Window>,
+    connection: Arc<XConnection>,
+    xfixes: XFixes,
+    last_clickthrough: bool,
+}
+
+impl WindowController {
+    pub fn new(connection: Arc<XConnection>, window: Window, target_window: Option<Window>) -> Result<Self> {
+        let xfixes = XFixes::open().map_err(|e| anyhow!("Could not load libXfixes: {}", e))?;
+        Ok(Self { window, target_window, connection, xfixes, last_clickthrough: false })
+    }
+
+    pub(crate) fn update(&mut self) {
+        let xlib = &self.connection.xlib;
+        let display = self.connection.display;
+
+        unsafe {
+            // Update overlay location to be on top of target
+            if let Some(target) = self.target_window {
+                let mut attributes = std::mem::zeroed();
+                // Fails when the target window has been closed, so the overlay just stays where it is
+                if (xlib.XGetWindowAttributes)(display, target, &mut attributes) == 0 {
+                    debug!("Could not get the attributes of the target window {}", target);
+                } else {
+                    // Window attributes are relative to the parent so translate them to the root window
+                    let (mut x, mut y, mut child) = (0, 0, 0);
+                    let translated = (xlib.XTranslateCoordinates)(
+                        display,
+                        target,
+                        (xlib.XDefaultRootWindow)(display),
+                        0, 0,
+                        &mut x, &mut y,
+                        &mut child,
+                    );
+
+                    // Fails when the target is on another screen, which leaves the coordinates unset
+                    if translated == 0 {
+                        debug!("Could not translate the position of the target window {}", target);
+                    } else {
+                        (xlib.XMoveResizeWindow)(display, self.window, x, y, attributes.width as _, attributes.height as _);
+                    }
+                }
+            }
+
+            // Override redirect windows are not kept on top by the window manager
+            (xlib.XRaiseWindow)(display, self.window);
+            (xlib.XFlush)(display);
+        }
+    }
+
+    pub fn set_target(&mut self, target: Option<Window>) {
+        self.target_window = target;
+    }
+
+    /// Enables or disables window clickthrough
+    pub fn clickthrough(&mut self, clickthrough: bool) {
+        if clickthrough != self.last_clickthrough {
+            self.last_clickthrough = clickthrough;
+
+            let display = self.connection.display;
+            unsafe {
+                // An empty input shape lets every click fall through to the window below,
+                // while resetting the shape to `None` restores the default input region
+                if clickthrough {
+                    let region = (self.xfixes.XFixesCreateRegion)(display, null_mut::<XRectangle>(), 0);
+                    (self.xfixes.XFixesSetWindowShapeRegion)(display, self.window, SHAPE_INPUT, 0, 0, region);
+                    (self.xfixes.XFixesDestroyRegion)(display, region);
+                } else {
+                    (self.xfixes.XFixesSetWindowShapeRegion)(display, self.window, SHAPE_INPUT, 0, 0, 0);
+                }
+                (self.connection.xlib.XFlush)(display);
+            }
+        }
+    }
+
+    /// Hiding from screenshots is not supported on X11, so this only logs the request
+    pub fn hide_screenshots(&mut self, hide: bool) {
+        if hide {
+            debug!("Hiding the overlay from screenshots is not supported on X11");
+        }
+    }
+}